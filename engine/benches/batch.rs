@@ -0,0 +1,129 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use engine::graphics::batch::{Batch, DrawTextureParams};
+use engine::graphics::color::Color;
+use engine::graphics::rect::Rect;
+use engine::graphics::texture::TextureId;
+
+/// Squares and sprites from a few textures, interleaved so that no two shapes in a row
+/// share a texture, which is the worst case for the draw call count
+fn interleaved(batch: &mut Batch) {
+    let white = Color::from_rgb(255, 255, 255, 255);
+    for i in 0..2000 {
+        let x = (i % 100) as f32 * 10.0;
+        let y = (i / 100) as f32 * 10.0;
+        batch.draw_square(x, y, 8.0, white);
+        batch.draw_textured_polygon(
+            TextureId(1 + i % 3),
+            &[
+                ((x, y), (0.0, 0.0)),
+                ((x + 8.0, y), (1.0, 0.0)),
+                ((x, y + 8.0), (0.0, 1.0)),
+                ((x + 8.0, y + 8.0), (1.0, 1.0)),
+            ],
+            &[0, 2, 3, 3, 1, 0],
+            white,
+        );
+    }
+}
+
+fn texture_batching(c: &mut Criterion) {
+    let mut batch = Batch::default();
+    interleaved(&mut batch);
+    let unbatched = batch.draws.len();
+    batch.bucket_by_texture();
+    println!(
+        "draw calls: {} unbatched, {} bucketed by texture",
+        unbatched,
+        batch.draws.len()
+    );
+
+    let mut group = c.benchmark_group("4000 interleaved shapes");
+    group.bench_function("push", |b| {
+        b.iter(|| {
+            interleaved(&mut batch);
+            black_box(&batch.draws);
+            batch.clear();
+        })
+    });
+    group.bench_function("push and bucket by texture", |b| {
+        b.iter(|| {
+            interleaved(&mut batch);
+            batch.bucket_by_texture();
+            black_box(&batch.draws);
+            batch.clear();
+        })
+    });
+    group.finish();
+}
+
+/// Sprites that are all frames from the same atlas, with a square under each, like a tilemap with shadows
+fn atlas(batch: &mut Batch) {
+    let white = Color::from_rgb(255, 255, 255, 255);
+    for i in 0..5000 {
+        let x = (i % 100) as f32 * 16.0;
+        let y = (i / 100) as f32 * 16.0;
+        batch.draw_square(x, y, 16.0, white);
+        batch.draw_texture_ex(
+            TextureId(1),
+            (256, 256),
+            x,
+            y,
+            white,
+            DrawTextureParams {
+                source: Some(Rect::new((i % 16) as f32 * 16.0, 0.0, 16.0, 16.0)),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+fn atlas_batching(c: &mut Criterion) {
+    let mut batch = Batch::default();
+    atlas(&mut batch);
+    batch.bucket_by_texture();
+    let textured = batch
+        .draws
+        .iter()
+        .filter(|draw| draw.texture == TextureId(1))
+        .count();
+    println!("draw calls for 5000 atlas sprites: {}", textured);
+    batch.clear();
+
+    c.bench_function("5000 atlas sprites, bucketed by texture", |b| {
+        b.iter(|| {
+            atlas(&mut batch);
+            batch.bucket_by_texture();
+            black_box(&batch.draws);
+            batch.clear();
+        })
+    });
+}
+
+/// Sorting has to cost nothing on frames that never set a sort key
+fn sort_keys(c: &mut Criterion) {
+    let mut batch = Batch::default();
+    let mut group = c.benchmark_group("4000 interleaved shapes, sorted");
+    group.bench_function("unkeyed", |b| {
+        b.iter(|| {
+            interleaved(&mut batch);
+            batch.sort_draws();
+            black_box(&batch.draws);
+            batch.clear();
+        })
+    });
+    group.bench_function("keyed in reverse", |b| {
+        b.iter(|| {
+            for layer in (0..4).rev() {
+                batch.set_sort_key(layer);
+                interleaved(&mut batch);
+            }
+            batch.sort_draws();
+            black_box(&batch.draws);
+            batch.clear();
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, texture_batching, atlas_batching, sort_keys);
+criterion_main!(benches);
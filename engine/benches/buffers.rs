@@ -0,0 +1,78 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use engine::graphics::batch::Batch;
+use engine::graphics::buffers::GrowableBuffer;
+use engine::graphics::color::Color;
+use wgpu::util::DeviceExt;
+
+/// A few thousand squares, a busy frame's worth of shapes
+fn scene(batch: &mut Batch) {
+    let color = Color::from_rgb(40, 120, 60, 255);
+    for i in 0..4000 {
+        let x = (i % 100) as f32 * 10.0;
+        let y = (i / 100) as f32 * 10.0;
+        batch.draw_square(x, y, 8.0, color);
+    }
+}
+
+/// Any adapter that'll do, without a window. Falls back to a software one if there's no gpu
+fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::Backends::all());
+    let adapter = [false, true]
+        .into_iter()
+        .find_map(|force_fallback_adapter| {
+            futures::executor::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter,
+            }))
+        })?;
+    futures::executor::block_on(adapter.request_device(&Default::default(), None)).ok()
+}
+
+fn buffer_reuse(c: &mut Criterion) {
+    let (device, queue) = match device() {
+        Some(device) => device,
+        None => {
+            println!("no adapter to bench buffers on, skipping");
+            return;
+        }
+    };
+    let mut batch = Batch::default();
+    scene(&mut batch);
+    let vertices = batch.vertex_bytes();
+    let indices: &[u8] = bytemuck::cast_slice(&batch.indices);
+
+    let mut group = c.benchmark_group("4000 squares into gpu buffers");
+    group.bench_function("new buffers every frame", |b| {
+        b.iter(|| {
+            let buffers = [
+                (vertices, wgpu::BufferUsages::VERTEX),
+                (indices, wgpu::BufferUsages::INDEX),
+            ]
+            .map(|(contents, usage)| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents,
+                    usage,
+                })
+            });
+            queue.submit([]);
+            black_box(buffers);
+        })
+    });
+    let mut vertex_buffer =
+        GrowableBuffer::new(&device, "Vertex Buffer", wgpu::BufferUsages::VERTEX);
+    let mut index_buffer = GrowableBuffer::new(&device, "Index Buffer", wgpu::BufferUsages::INDEX);
+    group.bench_function("reused buffers", |b| {
+        b.iter(|| {
+            vertex_buffer.write(&device, &queue, vertices);
+            index_buffer.write(&device, &queue, indices);
+            queue.submit([]);
+        })
+    });
+    group.finish();
+    device.poll(wgpu::Maintain::Wait);
+}
+
+criterion_group!(benches, buffer_reuse);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use engine::graphics::color::Color;
+
+fn colors() -> Vec<Color> {
+    (0..1000u32)
+        .map(|i| Color::from_rgb(i as u8, (i * 7) as u8, (i * 13) as u8, 255))
+        .collect()
+}
+
+/// What the conversion used to do before the lookup table
+fn convert_powf(color: Color) -> wgpu::Color {
+    let cv = |n: u8| (n as f64 / 255.0).powf(2.2);
+    wgpu::Color {
+        r: cv(color.r),
+        g: cv(color.g),
+        b: cv(color.b),
+        a: color.a as f64 / 255.0,
+    }
+}
+
+fn gamma_conversion(c: &mut Criterion) {
+    let colors = colors();
+    let mut group = c.benchmark_group("gamma conversion, 1000 colors");
+    group.bench_function("powf", |b| {
+        b.iter(|| {
+            for color in &colors {
+                black_box(convert_powf(black_box(*color)));
+            }
+        })
+    });
+    group.bench_function("lookup table", |b| {
+        b.iter(|| {
+            for color in &colors {
+                black_box(wgpu::Color::from(black_box(*color)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, gamma_conversion);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use engine::graphics::batch::Batch;
+use engine::graphics::buffers::Uniforms;
+use engine::graphics::color::Color;
+use engine::graphics::mesh::Transform;
+
+/// A 100x50 grid of squares, standing in for static background art
+fn background(batch: &mut Batch) {
+    let color = Color::from_rgb(40, 120, 60, 255);
+    for i in 0..5000 {
+        let x = (i % 100) as f32 * 10.0;
+        let y = (i / 100) as f32 * 10.0;
+        batch.draw_square(x, y, 8.0, color);
+    }
+}
+
+/// Cpu work per frame only, since there's no gpu to bench against here. Re-pushing also copies the
+/// whole background into new gpu buffers every frame, which a mesh doesn't, so the real gap is bigger
+fn static_geometry(c: &mut Criterion) {
+    let mut batch = Batch::default();
+    let uniforms = Uniforms::new(800.0, 600.0);
+
+    let mut group = c.benchmark_group("5000 static squares per frame");
+    group.bench_function("re-push into the batch", |b| {
+        b.iter(|| {
+            background(&mut batch);
+            black_box(batch.vertex_bytes());
+            batch.clear();
+        })
+    });
+    group.bench_function("draw as a mesh", |b| {
+        b.iter(|| {
+            // All a mesh draw does on the cpu is work out its transform
+            let transform = Transform::at(black_box(16.0), 0.0);
+            black_box(uniforms.transformed(transform.matrix()));
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, static_geometry);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+//! A borderless window that stays on top of everything else, with a square following the mouse.
+//! There's no title bar to close it with, so press escape. Space toggles the border back on
+use engine::graphics::color::Color;
+use engine::{context::Context, ContextBuilder, Runnable};
+use winit::event::VirtualKeyCode;
+
+#[derive(Default)]
+struct Borderless {
+    decorated: bool,
+    space_was_down: bool,
+}
+
+impl Runnable for Borderless {
+    fn tick(&mut self, ctx: &mut Context) {
+        let space_down = ctx.keyboard.is_pressed(VirtualKeyCode::Space);
+        if space_down && !self.space_was_down {
+            self.decorated = !self.decorated;
+            ctx.set_decorations(self.decorated);
+        }
+        self.space_was_down = space_down;
+    }
+
+    fn render(&self, ctx: &mut Context, _alpha: f32) {
+        let (x, y) = (ctx.mouse.x, ctx.mouse.y);
+        ctx.graphics.screen_batch.draw_square(
+            x - 10.0,
+            y - 10.0,
+            20.0,
+            Color::from_rgb(240, 200, 40, 255),
+        );
+    }
+}
+
+fn main() {
+    let (event_loop, ctx) = ContextBuilder::new()
+        .with_title("Borderless")
+        .with_margin(400.0)
+        .with_decorations(false)
+        .with_always_on_top(true)
+        .build();
+    engine::main::run(event_loop, ctx, Borderless::default());
+}
@@ -0,0 +1,61 @@
+//! Lists every monitor at startup, then opens on the last one. Press M to move the window to the next monitor
+use engine::graphics::color::Color;
+use engine::{context::Context, ContextBuilder, Runnable};
+use winit::event::VirtualKeyCode;
+
+#[derive(Default)]
+struct Monitors {
+    current: usize,
+    m_was_down: bool,
+}
+
+impl Runnable for Monitors {
+    fn tick(&mut self, ctx: &mut Context) {
+        let m_down = ctx.keyboard.is_pressed(VirtualKeyCode::M);
+        if m_down && !self.m_was_down {
+            let count = ctx.available_monitors().len().max(1);
+            self.current = (self.current + 1) % count;
+            ctx.move_to_monitor(self.current, 100.0);
+        }
+        self.m_was_down = m_down;
+    }
+
+    fn render(&self, ctx: &mut Context, _alpha: f32) {
+        let (width, height) = ctx.window_size();
+        ctx.graphics.screen_batch.draw_square(
+            width as f32 / 2.0 - 20.0,
+            height as f32 / 2.0 - 20.0,
+            40.0,
+            Color::from_rgb(90, 160, 240, 255),
+        );
+    }
+}
+
+fn main() {
+    let (event_loop, mut ctx) = ContextBuilder::new().with_title("Monitors").build();
+    let monitors = ctx.available_monitors();
+    for (index, monitor) in monitors.iter().enumerate() {
+        println!(
+            "{}: {} {}x{} at {:?}, {} hz, scale {}",
+            index,
+            monitor.name.as_deref().unwrap_or("unnamed"),
+            monitor.size.0,
+            monitor.size.1,
+            monitor.position,
+            monitor
+                .refresh_rate
+                .map_or_else(|| String::from("unknown"), |hz| hz.to_string()),
+            monitor.scale_factor
+        );
+    }
+    let last = monitors.len().saturating_sub(1);
+    ctx.move_to_monitor(last, 100.0);
+    engine::main::run(
+        event_loop,
+        ctx,
+        Monitors {
+            current: last,
+            m_was_down: false,
+        },
+    );
+}
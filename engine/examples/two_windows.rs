@@ -0,0 +1,31 @@
+//! Opens two windows on the same event loop, each running its own game. One's cleared red and the other blue
+use engine::graphics::color::Color;
+use engine::{context::Context, ContextBuilder, Runnable};
+
+struct Clear(Color);
+
+impl Runnable for Clear {
+    fn tick(&mut self, _ctx: &mut Context) {}
+
+    fn render(&self, ctx: &mut Context, _alpha: f32) {
+        ctx.graphics.clear_background(self.0);
+    }
+}
+
+fn main() {
+    let builder = ContextBuilder::new().with_title("Game");
+    let (event_loop, game) = builder.try_build().unwrap();
+    let tools = ContextBuilder::new()
+        .with_title("Tools")
+        .with_margin(300.0)
+        .try_build_window(&event_loop)
+        .unwrap();
+
+    engine::main::run_windows(
+        event_loop,
+        vec![
+            (game, Box::new(Clear(Color::from_rgb(200, 40, 40, 255)))),
+            (tools, Box::new(Clear(Color::from_rgb(40, 40, 200, 255)))),
+        ],
+    );
+}
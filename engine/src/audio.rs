@@ -1,14 +1,33 @@
+use rodio::source::ChannelVolume;
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sample, Sink, Source};
-use std::{fs::File, io::BufReader};
+use std::{
+    fs::File,
+    io::{BufReader, Cursor},
+};
 
 use std::error::Error;
 
 use crate::context::Context;
 
+/// How sounds from [`Audio::play_sound_at`] get quieter the farther away they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Falloff {
+    /// Evenly, from full volume at the listener down to silent at the max distance
+    #[default]
+    Linear,
+    /// Quickly close up and slowly farther out, more like real sound, still silent at the max distance
+    Inverse,
+}
+
 /// Aims to be as light of a wrapper over rodio as possible, since the default rodio API is already pretty good IMO
 pub struct Audio {
     _stream: OutputStream,
     stream_handle: OutputStreamHandle,
+    /// Where in the world sounds are heard from. Set to the camera's center before every tick
+    pub listener: (f32, f32),
+    pub falloff: Falloff,
+    /// Whether sounds to the left or right of the listener play louder on that side
+    pub panning: bool,
 }
 
 impl Audio {
@@ -19,10 +38,67 @@ impl Audio {
         Self {
             _stream,
             stream_handle,
+            listener: (0.0, 0.0),
+            falloff: Falloff::default(),
+            panning: true,
+        }
+    }
+
+    /// Plays a sound file (any format rodio can decode) as if it came from `position` in the world, quieter the
+    /// farther it is from [`Audio::listener`] and silent past `max_distance` pixels. With [`Audio::panning`] on,
+    /// it's also panned toward the side it's on, all the way over at `max_distance`.
+    ///
+    /// The volume is worked out once when the sound starts, so it doesn't follow a moving listener or source
+    pub fn play_sound_at(
+        &self,
+        bytes: Vec<u8>,
+        position: (f32, f32),
+        max_distance: f32,
+    ) -> Result<(), Box<dyn Error>> {
+        let (left, right) = spatial_volumes(
+            self.listener,
+            position,
+            max_distance,
+            self.falloff,
+            self.panning,
+        );
+        if left == 0.0 && right == 0.0 {
+            return Ok(());
         }
+        let source = Decoder::new(Cursor::new(bytes))?;
+        let sink = Sink::try_new(&self.stream_handle)?;
+        sink.append(ChannelVolume::new(source, vec![left, right]));
+        sink.detach();
+        Ok(())
     }
 }
 
+/// Left and right volumes for a sound at `position` heard from `listener`, see [`Audio::play_sound_at`]
+pub fn spatial_volumes(
+    listener: (f32, f32),
+    position: (f32, f32),
+    max_distance: f32,
+    falloff: Falloff,
+    panning: bool,
+) -> (f32, f32) {
+    let (dx, dy) = (position.0 - listener.0, position.1 - listener.1);
+    let distance = (dx * dx + dy * dy).sqrt();
+    if max_distance <= 0.0 || distance >= max_distance {
+        return (0.0, 0.0);
+    }
+    let t = distance / max_distance;
+    let volume = match falloff {
+        Falloff::Linear => 1.0 - t,
+        Falloff::Inverse => (1.0 - t) / (1.0 + 9.0 * t),
+    };
+    if !panning {
+        return (volume, volume);
+    }
+    // -1 is all the way left, 1 all the way right
+    let pan = (dx / max_distance).clamp(-1.0, 1.0);
+    (volume * (1.0 - pan).min(1.0), volume * (1.0 + pan).min(1.0))
+}
+
 /// Mainly useful for playing sound effects I think?
 pub fn play_once_vorbis(ctx: &mut Context, path: &str) -> Result<(), Box<dyn Error>> {
     // Load the file via resource manager
@@ -58,3 +134,35 @@ impl Default for Audio {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{spatial_volumes, Falloff};
+
+    #[test]
+    fn full_at_listener_and_silent_far_away() {
+        for falloff in [Falloff::Linear, Falloff::Inverse] {
+            assert_eq!(
+                spatial_volumes((100.0, 100.0), (100.0, 100.0), 500.0, falloff, true),
+                (1.0, 1.0)
+            );
+            assert_eq!(
+                spatial_volumes((100.0, 100.0), (100.0, 700.0), 500.0, falloff, true),
+                (0.0, 0.0)
+            );
+        }
+
+        // Halfway out, and off to the right
+        let (left, right) = spatial_volumes((0.0, 0.0), (250.0, 0.0), 500.0, Falloff::Linear, true);
+        assert_eq!(right, 0.5);
+        assert_eq!(left, 0.25);
+        assert_eq!(
+            spatial_volumes((0.0, 0.0), (250.0, 0.0), 500.0, Falloff::Linear, false),
+            (0.5, 0.5)
+        );
+        // Inverse is quieter than linear anywhere in between
+        let (inverse, _) =
+            spatial_volumes((0.0, 0.0), (0.0, 250.0), 500.0, Falloff::Inverse, false);
+        assert!(inverse < 0.5 && inverse > 0.0);
+    }
+}
@@ -1,13 +1,217 @@
+use super::graphics::batch::Dashes;
+use super::graphics::color::Color;
 use super::graphics::State;
-use super::keyboard::Keyboard;
+use super::input::{InputState, TickInput};
+use super::keyboard::{Keyboard, PhysicalKey};
+use super::mouse::Mouse;
 use super::resource::ResourceManager;
+use super::time::Time;
+use super::ui::Ui;
 use super::audio::Audio;
+use super::monitor::MonitorInfo;
+use winit::event::MouseButton;
 
 pub struct Context {
     pub graphics: State,
     pub keyboard: Keyboard,
+    pub input: TickInput,
+    pub mouse: Mouse,
+    /// Checkboxes and sliders, see [`Ui`]
+    pub ui: Ui,
+    pub time: Time,
     pub audio: Audio,
     pub window: winit::window::Window,
     pub resource_mgr: ResourceManager,
-    pub config: crate::Config
-}
\ No newline at end of file
+    pub config: crate::Config,
+    /// Saving and loading game state, needs the `serde` feature
+    #[cfg(feature = "serde")]
+    pub storage: crate::storage::Storage,
+    /// Opened the first time it's used. It has to be kept around, since on Linux what we copied
+    /// disappears from the clipboard as soon as it's dropped
+    #[cfg(feature = "clipboard")]
+    pub(crate) clipboard: Option<arboard::Clipboard>,
+    /// See [`Context::request_exit`]
+    pub(crate) exit_requested: bool,
+    /// See [`Context::request_redraw`]
+    pub(crate) redraw_requested: bool,
+}
+
+impl Context {
+    /// Size of the window's drawable area in physical pixels, the actual pixels on the screen.
+    /// This is the size of the surface that gets drawn to, and what draw calls are positioned in
+    pub fn window_size(&self) -> (u32, u32) {
+        let size = self.graphics.size();
+        (size.width, size.height)
+    }
+
+    /// Input as it was when the current tick started. Unlike `keyboard` and `mouse`, it doesn't change in the middle of a tick,
+    /// and every tick run in the same frame gets its own snapshot.
+    ///
+    /// For replays and lockstep networking, record this every tick (it's serializable with the `serde` feature)
+    /// and feed the recording back with `input.play_back`. A game that only reads input from here, and doesn't
+    /// depend on wall clock time or unseeded randomness, runs the same given the same snapshots: each tick gets
+    /// exactly one snapshot, in order, no matter how fast the machine renders
+    pub fn input_snapshot(&self) -> InputState {
+        self.input.current()
+    }
+
+    /// Seconds each tick moves the game forward by, for writing movement as `speed * ctx.delta_time()` instead of
+    /// per tick. Always `1 / config.ticks`: ticks are caught up a whole tick at a time out of the frame's time, which
+    /// is already clamped by [`Time::max_delta`], so every tick stands for the same length of time even when a few
+    /// run back to back after a slow frame
+    pub fn delta_time(&self) -> f32 {
+        1.0 / self.config.ticks.max(1) as f32
+    }
+
+    /// Seconds a [`crate::Runnable::fixed_update`] moves the game forward by, always `1 / config.fixed_update_rate`
+    pub fn fixed_delta_time(&self) -> f32 {
+        1.0 / self.config.fixed_update_rate.max(1) as f32
+    }
+
+    /// Seconds since the game started, with stalls clamped out, see [`Time::elapsed`]
+    pub fn time(&self) -> f32 {
+        self.time.elapsed()
+    }
+
+    /// Milliseconds the gpu took to render a recent frame, compare it with the cpu frame time to see which one's
+    /// the bottleneck. Needs `wgpu::Features::TIMESTAMP_QUERY`, so it's `None` on adapters without it.
+    /// The timing is read back without stalling, so it lags a frame or two behind
+    pub fn gpu_frame_time(&self) -> Option<f32> {
+        self.graphics.gpu_frame_time()
+    }
+
+    /// Whether the key at this physical position is held, regardless of keyboard layout. Use it for movement keys
+    /// like WASD, and `keyboard`'s layout dependent keys for keys that mean something (text, "R to restart").
+    /// `scancode` is platform specific, [`Context::is_physical_key_down`] is the portable version
+    pub fn is_scancode_down(&self, scancode: u32) -> bool {
+        self.keyboard.is_scancode_down(scancode)
+    }
+
+    pub fn is_physical_key_down(&self, key: PhysicalKey) -> bool {
+        self.keyboard.is_physical_key_down(key)
+    }
+
+    /// Where the cursor is, in the same physical pixels from the top left of the window that screen space shapes
+    /// are drawn in
+    pub fn mouse_position(&self) -> (f32, f32) {
+        (self.mouse.x, self.mouse.y)
+    }
+
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.mouse.is_button_down(button)
+    }
+
+    /// Whether `button` went down this frame, see [`Mouse::is_button_pressed`]
+    pub fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.mouse.is_button_pressed(button)
+    }
+
+    /// Lines the wheel scrolled this frame, see [`Mouse::wheel_delta`]
+    pub fn mouse_wheel_delta(&self) -> f32 {
+        self.mouse.wheel_delta()
+    }
+
+    /// Whether `button` is being held as the second click of a double click, see [`Mouse::is_double_click`]
+    pub fn is_double_click(&self, button: MouseButton) -> bool {
+        self.mouse.is_double_click(button)
+    }
+
+    /// Seconds `button` has been held for, `0.0` if it isn't held
+    pub fn mouse_held_duration(&self, button: MouseButton) -> f32 {
+        self.mouse.held_duration(button)
+    }
+
+    /// Text currently on the system clipboard. `None` if it's empty, holds something that isn't text,
+    /// or the clipboard can't be opened (e.g. no display server). Needs the `clipboard` feature, which pulls in `arboard`
+    #[cfg(feature = "clipboard")]
+    pub fn get_clipboard(&mut self) -> Option<String> {
+        self.clipboard().ok()?.get_text().ok()
+    }
+
+    /// Puts `text` on the system clipboard. Needs the `clipboard` feature
+    #[cfg(feature = "clipboard")]
+    pub fn set_clipboard(&mut self, text: &str) -> crate::error::Result<()> {
+        self.clipboard()?.set_text(text.to_string())?;
+        Ok(())
+    }
+
+    #[cfg(feature = "clipboard")]
+    fn clipboard(&mut self) -> crate::error::Result<&mut arboard::Clipboard> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new()?);
+        }
+        Ok(self.clipboard.as_mut().unwrap())
+    }
+
+    /// Turns the window's title bar and border on or off, see [`crate::ContextBuilder::with_decorations`].
+    /// Some platforms only apply it once the window's redrawn, and Wayland compositors can refuse to drop them
+    pub fn set_decorations(&mut self, decorations: bool) {
+        self.window.set_decorations(decorations);
+    }
+
+    /// Keeps the window above every other window or stops doing so, see [`crate::ContextBuilder::with_always_on_top`]
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.window.set_always_on_top(always_on_top);
+    }
+
+    /// Renders another frame, when only rendering on demand with [`crate::ContextBuilder::with_redraw_on_demand`].
+    /// Call it whenever something changes outside of an event, e.g. every tick an animation's playing.
+    /// Does nothing when rendering every frame anyway
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+
+    /// Closes the window once the current event, tick or frame is done, and exits if it was the last one.
+    /// This doesn't go through [`crate::Runnable::on_close_requested`], so call it after the player's confirmed
+    pub fn request_exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    /// Every monitor the window could go on, in the order the platform lists them. The order can change when a
+    /// monitor's plugged in or out, so an index is only good until then, see [`crate::ContextBuilder::with_monitor`]
+    pub fn available_monitors(&self) -> Vec<MonitorInfo> {
+        self.window
+            .available_monitors()
+            .map(|monitor| MonitorInfo::from_handle(&monitor))
+            .collect()
+    }
+
+    /// The monitor the window's mostly on, `None` if the platform can't tell
+    pub fn current_monitor(&self) -> Option<MonitorInfo> {
+        self.window
+            .current_monitor()
+            .map(|monitor| MonitorInfo::from_handle(&monitor))
+    }
+
+    /// Moves the window onto the monitor at `index` in [`Context::available_monitors`], filling it the same
+    /// way the window was first opened, all but `margin` pixels on every side. Returns false if there's no
+    /// monitor at `index`, e.g. it was just unplugged
+    pub fn move_to_monitor(&mut self, index: usize, margin: f32) -> bool {
+        match self.window.available_monitors().nth(index) {
+            Some(monitor) => {
+                crate::monitor::fit_to(&self.window, &monitor, margin);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Dashed outline of `rect` with its dashes crawling around it over [`Time::elapsed`], the classic "marching
+    /// ants" selection outline, for editors and tools. It's here instead of on `graphics` since it needs the time,
+    /// see [`crate::graphics::batch::Dashes`] to draw the dashes some other way
+    pub fn draw_marching_ants(&mut self, rect: crate::graphics::rect::Rect, color: Color) {
+        let dashes = Dashes::marching_ants(self.time.elapsed());
+        self.graphics
+            .batch
+            .draw_dashed_rect(rect, 1.0, dashes, color);
+    }
+
+    /// Size of the window's drawable area in logical pixels, which is the physical size divided by
+    /// the monitor's scale factor. On a 4k monitor at 200% scaling, a 3840x2160 window is 1920x1080 logical.
+    /// Use this for laying out things so they're the same size on any dpi
+    pub fn logical_size(&self) -> (f32, f32) {
+        let (width, height) = self.window_size();
+        let scale_factor = self.window.scale_factor() as f32;
+        (width as f32 / scale_factor, height as f32 / scale_factor)
+    }
+}
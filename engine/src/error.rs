@@ -0,0 +1,133 @@
+use std::fmt;
+
+/// Every way the engine's fallible functions can fail
+#[derive(Debug)]
+pub enum EngineError {
+    /// Reading a file failed
+    Io(std::io::Error),
+    /// A string couldn't be parsed into a [`crate::graphics::color::Color`]
+    ColorParse(String),
+    /// The bytes weren't a font that could be read
+    FontLoad(wgpu_glyph::ab_glyph::InvalidFont),
+    /// The bytes weren't an image that could be decoded
+    TextureLoad(image::ImageError),
+    /// A shader failed to compile, with the reason why
+    ShaderCompile(String),
+    /// No graphics adapter could draw to the window
+    AdapterNotFound,
+    /// The adapter can't do everything the engine needs, with a description of each thing it's missing
+    Unsupported(Vec<String>),
+    /// The adapter was found, but it couldn't give us a device
+    DeviceNotCreated(wgpu::RequestDeviceError),
+    /// Creating the window failed
+    Window(winit::error::OsError),
+    /// The surface was lost and couldn't be recreated
+    SurfaceLost,
+    /// The system clipboard couldn't be opened or written to
+    #[cfg(feature = "clipboard")]
+    Clipboard(arboard::Error),
+    /// A value couldn't be turned into json for [`crate::storage::Storage`]
+    #[cfg(feature = "serde")]
+    Serialize(serde_json::Error),
+    /// Asset files couldn't be watched for changes
+    #[cfg(feature = "hot-reload")]
+    HotReload(notify::Error),
+}
+
+/// Shorthand for results that fail with an [`EngineError`]
+pub type Result<T> = std::result::Result<T, EngineError>;
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Io(e) => write!(f, "io error: {}", e),
+            EngineError::ColorParse(hex) => write!(f, "invalid color: {}", hex),
+            EngineError::FontLoad(e) => write!(f, "unable to load font: {}", e),
+            EngineError::TextureLoad(e) => write!(f, "unable to load texture: {}", e),
+            EngineError::ShaderCompile(e) => write!(f, "unable to compile shader: {}", e),
+            EngineError::AdapterNotFound => write!(f, "unable to find adapter"),
+            EngineError::Unsupported(missing) => {
+                write!(f, "graphics adapter is missing: {}", missing.join(", "))
+            }
+            EngineError::DeviceNotCreated(e) => write!(f, "unable to create device: {}", e),
+            EngineError::Window(e) => write!(f, "unable to create window: {}", e),
+            EngineError::SurfaceLost => write!(f, "surface was lost"),
+            #[cfg(feature = "clipboard")]
+            EngineError::Clipboard(e) => write!(f, "unable to use clipboard: {}", e),
+            #[cfg(feature = "serde")]
+            EngineError::Serialize(e) => write!(f, "unable to serialize: {}", e),
+            #[cfg(feature = "hot-reload")]
+            EngineError::HotReload(e) => write!(f, "unable to watch assets: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EngineError::Io(e) => Some(e),
+            EngineError::FontLoad(e) => Some(e),
+            EngineError::TextureLoad(e) => Some(e),
+            EngineError::DeviceNotCreated(e) => Some(e),
+            EngineError::Window(e) => Some(e),
+            #[cfg(feature = "clipboard")]
+            EngineError::Clipboard(e) => Some(e),
+            #[cfg(feature = "serde")]
+            EngineError::Serialize(e) => Some(e),
+            #[cfg(feature = "hot-reload")]
+            EngineError::HotReload(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for EngineError {
+    fn from(e: std::io::Error) -> Self {
+        EngineError::Io(e)
+    }
+}
+
+impl From<wgpu_glyph::ab_glyph::InvalidFont> for EngineError {
+    fn from(e: wgpu_glyph::ab_glyph::InvalidFont) -> Self {
+        EngineError::FontLoad(e)
+    }
+}
+
+impl From<image::ImageError> for EngineError {
+    fn from(e: image::ImageError) -> Self {
+        EngineError::TextureLoad(e)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for EngineError {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        EngineError::DeviceNotCreated(e)
+    }
+}
+
+impl From<winit::error::OsError> for EngineError {
+    fn from(e: winit::error::OsError) -> Self {
+        EngineError::Window(e)
+    }
+}
+
+#[cfg(feature = "clipboard")]
+impl From<arboard::Error> for EngineError {
+    fn from(e: arboard::Error) -> Self {
+        EngineError::Clipboard(e)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for EngineError {
+    fn from(e: serde_json::Error) -> Self {
+        EngineError::Serialize(e)
+    }
+}
+
+#[cfg(feature = "hot-reload")]
+impl From<notify::Error> for EngineError {
+    fn from(e: notify::Error) -> Self {
+        EngineError::HotReload(e)
+    }
+}
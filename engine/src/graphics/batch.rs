@@ -0,0 +1,1803 @@
+use std::f32::consts::PI;
+use std::ops::Range;
+
+use cgmath::{Matrix3, Vector2};
+
+use super::buffers::Vertex;
+use super::color::{self, Color};
+use super::rect::Rect;
+use super::texture::TextureId;
+
+/// A position, and the uv coordinate of the texture to sample there
+pub type TexturedPoint = ((f32, f32), (f32, f32));
+
+/// How shapes get drawn, set with [`super::State::set_draw_style`].
+/// Only affects squares, rectangles, circles and polygons, text and textures ignore it
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum DrawStyle {
+    /// Filled in, the default
+    #[default]
+    Fill,
+    /// Only the outline, `thickness` wide
+    Line { thickness: f32 },
+}
+
+/// Pattern for a dashed outline, see [`Batch::draw_dashed_rect`]. Lengths are in pixels along the outline
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Dashes {
+    pub dash: f32,
+    pub gap: f32,
+    /// How far the pattern's moved along the outline, changing it over time makes the dashes crawl.
+    /// The pattern repeats every `dash + gap`, so it loops without a jump when the offset wraps
+    pub offset: f32,
+}
+
+impl Dashes {
+    /// Dash and gap length of marching ants
+    const ANT: f32 = 4.0;
+    /// Pixels a second marching ants crawl
+    const ANT_SPEED: f32 = 16.0;
+
+    /// The classic selection outline, 4 pixel dashes and gaps crawling clockwise, `elapsed` seconds in
+    pub fn marching_ants(elapsed: f32) -> Self {
+        Self {
+            dash: Self::ANT,
+            gap: Self::ANT,
+            offset: (elapsed * Self::ANT_SPEED).rem_euclid(Self::ANT * 2.0),
+        }
+    }
+
+    /// Parts of the outline from `start` to `start + length` that are dashes, as ranges from `start`
+    fn spans(&self, start: f32, length: f32) -> Vec<(f32, f32)> {
+        let period = self.dash + self.gap;
+        if self.dash <= 0.0 || length <= 0.0 {
+            return Vec::new();
+        }
+        if self.gap <= 0.0 {
+            return vec![(0.0, length)];
+        }
+        let mut spans = Vec::new();
+        let mut phase = (start - self.offset).rem_euclid(period);
+        let mut at = 0.0;
+        while at < length {
+            if phase < self.dash {
+                let end = (at + self.dash - phase).min(length);
+                spans.push((at, end));
+                at = end;
+                phase = self.dash;
+            } else {
+                at += period - phase;
+                phase = 0.0;
+            }
+        }
+        spans
+    }
+}
+
+/// How to draw a texture with [`Batch::draw_texture_ex`]. The default draws the whole texture at its own size,
+/// the same as [`super::State::draw_texture`]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct DrawTextureParams {
+    /// Part of the texture to draw in pixels, e.g. one frame of a sprite sheet. The whole texture if `None`
+    pub source: Option<Rect>,
+    /// Size to draw it at in pixels, the size of `source` if `None`
+    pub dest_size: Option<(f32, f32)>,
+    /// Radians, clockwise on screen
+    pub rotation: f32,
+    /// Point it's rotated around, in the same space it's drawn in (not relative to the texture). The middle of
+    /// where it's drawn if `None`
+    pub pivot: Option<(f32, f32)>,
+    /// Mirrors it left to right, e.g. for a character facing the other way
+    pub flip_x: bool,
+    /// Mirrors it top to bottom
+    pub flip_y: bool,
+}
+
+/// A run of indices that all get drawn with the same texture, in one draw call
+#[derive(Debug, PartialEq, Clone)]
+pub struct DrawCall {
+    pub texture: TextureId,
+    pub indices: Range<u32>,
+    /// From [`Batch::set_sort_key`] when the shapes were pushed
+    pub key: i32,
+}
+
+/// Holds all the shapes pushed during a frame. They are copied into the vertex and index buffer
+/// in `State::render()` to be batch rendered, then cleared.
+///
+/// Doesn't touch the gpu at all, so shapes can be built (and tested) without a window
+pub struct Batch {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    /// Draw calls in the order shapes were pushed, each covering a range of `indices`
+    pub draws: Vec<DrawCall>,
+    /// Whether colors get gamma corrected before going into vertices, see [`super::State::set_gamma_correction`]
+    pub gamma_correction: bool,
+    pub style: DrawStyle,
+    /// Whether [`Batch::bucket_by_texture`] runs before submitting, see [`super::State::set_texture_batching`]
+    pub texture_batching: bool,
+    /// Transforms pushed with [`Batch::push_transform`], each already combined with the ones below it
+    transforms: Vec<Matrix3<f32>>,
+    /// Key shapes get pushed with, see [`Batch::set_sort_key`]
+    sort_key: i32,
+    /// Whether any shape this frame was pushed with a key other than 0, so [`Batch::sort_draws`] has work to do
+    keyed: bool,
+}
+
+impl Default for Batch {
+    fn default() -> Self {
+        Self {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            draws: Vec::new(),
+            gamma_correction: true,
+            style: DrawStyle::Fill,
+            texture_batching: false,
+            transforms: Vec::new(),
+            sort_key: 0,
+            keyed: false,
+        }
+    }
+}
+
+impl Batch {
+    /// Empty batch, with gamma correction on only if the surface is sRGB.
+    ///
+    /// sRGB surfaces treat what's written to them as linear, and encode it back to sRGB,
+    /// so our sRGB `Color`s have to be made linear first. A non sRGB surface passes values
+    /// straight to the screen, and so correcting there would make every color too dark
+    pub fn new(surface_format: wgpu::TextureFormat) -> Self {
+        Self {
+            gamma_correction: super::gamma_correction(surface_format, None),
+            ..Self::default()
+        }
+    }
+
+    /// Converts a `Color` to what gets stored in a `Vertex`
+    pub fn vertex_color(&self, color: Color) -> [f32; 4] {
+        if self.gamma_correction {
+            color::to_f32_array(wgpu::Color::from(color))
+        } else {
+            color.to_f32_array()
+        }
+    }
+
+    /// Converts a `Color`, gamma correcting it only if the batch is set to
+    pub fn wgpu_color(&self, color: Color) -> wgpu::Color {
+        if self.gamma_correction {
+            wgpu::Color::from(color)
+        } else {
+            color.to_wgpu_uncorrected()
+        }
+    }
+
+    /// Takes in top left coordinate of square, width, and a `color::Color`
+    pub fn draw_square(&mut self, x: f32, y: f32, width: f32, color: Color) {
+        if let DrawStyle::Line { thickness } = self.style {
+            self.draw_rectangle_outline(x, y, width, width, thickness, color);
+            return;
+        }
+        let color = self.vertex_color(color);
+        // We're allowed to pass in coords straight from our game, since our view matrix
+        // will take care of transforming coords
+
+        // Z is always 0 for a 2d game
+        let vertices = &[
+            // Top left, 0
+            Vertex {
+                position: [x, y, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            // Top right, 1
+            Vertex {
+                position: [x + width, y, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            // Bot left, 2
+            Vertex {
+                position: [x, y + width, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            // bot right, 3
+            Vertex {
+                position: [x + width, y + width, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+        ];
+
+        let indices = &[
+            0, 2, 3, // Top triangle
+            3, 1, 0, // Bot triangle
+        ];
+
+        self.push_shape(vertices, indices);
+    }
+
+    pub fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        if let DrawStyle::Line { thickness } = self.style {
+            self.draw_rectangle_outline(x, y, width, height, thickness, color);
+            return;
+        }
+        let color = self.vertex_color(color);
+        // We're allowed to pass in coords straight from our game, since our view matrix
+        // will take care of transforming coords
+
+        // Z is always 0 for a 2d game
+        let vertices = &[
+            // Top left, 0
+            Vertex {
+                position: [x, y, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            // Top right, 1
+            Vertex {
+                position: [x + width, y, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            // Bot left, 2
+            Vertex {
+                position: [x, y + height, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            // bot right, 3
+            Vertex {
+                position: [x + width, y + height, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+        ];
+
+        let indices = &[
+            0, 2, 3, // Top triangle
+            3, 1, 0, // Bot triangle
+        ];
+
+        self.push_shape(vertices, indices);
+    }
+
+    /// Border of a rectangle, as 4 quads that don't overlap so corners aren't blended twice, which would show
+    /// through a translucent color. The border is inside `width` & `height`, so it doesn't grow the rectangle.
+    /// Always drawn like this, whatever the style
+    pub fn draw_rectangle_outline(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        thickness: f32,
+        color: Color,
+    ) {
+        // Draws them filled no matter the style
+        let style = std::mem::replace(&mut self.style, DrawStyle::Fill);
+        let t = thickness.min(width / 2.0).min(height / 2.0);
+        // Top and bottom take the corners, sides fit between them
+        self.draw_rectangle(x, y, width, t, color);
+        self.draw_rectangle(x, y + height - t, width, t, color);
+        self.draw_rectangle(x, y + t, t, height - t * 2.0, color);
+        self.draw_rectangle(x + width - t, y + t, t, height - t * 2.0, color);
+        self.style = style;
+    }
+
+    /// Outline of `rect` `thickness` wide, broken into dashes. The pattern runs clockwise from the top left corner
+    /// and carries on around corners instead of starting over on each side. Like the plain outline, it's inside
+    /// `rect`, and always drawn this way whatever the style
+    pub fn draw_dashed_rect(&mut self, rect: Rect, thickness: f32, dashes: Dashes, color: Color) {
+        let style = std::mem::replace(&mut self.style, DrawStyle::Fill);
+        let t = thickness.min(rect.width / 2.0).min(rect.height / 2.0);
+        let (w, h) = (rect.width, rect.height);
+        let sides = [w, h, w, h];
+        let mut start = 0.0;
+        for (side, &length) in sides.iter().enumerate() {
+            for (a, b) in dashes.spans(start, length) {
+                let (x, y, width, height) = match side {
+                    0 => (rect.x + a, rect.y, b - a, t),
+                    1 => (rect.right() - t, rect.y + a, t, b - a),
+                    2 => (rect.right() - b, rect.bottom() - t, b - a, t),
+                    _ => (rect.x, rect.bottom() - b, t, b - a),
+                };
+                self.draw_rectangle(x, y, width, height, color);
+            }
+            start += length;
+        }
+        self.style = style;
+    }
+
+    /// Covers `bounds` with a translucent `color`, always filled, see [`super::State::fade_screen`]
+    pub fn fade(&mut self, bounds: Rect, color: Color) {
+        let style = std::mem::replace(&mut self.style, DrawStyle::Fill);
+        self.draw_rectangle(bounds.x, bounds.y, bounds.width, bounds.height, color);
+        self.style = style;
+    }
+
+    /// Circle centered on `(x, y)`, with as many segments as it needs to look round at `radius` pixels across,
+    /// see [`circle_segments`]. The outline, when drawing lines, is inside `radius`. A radius of 0 or less draws nothing
+    pub fn draw_circle(&mut self, x: f32, y: f32, radius: f32, color: Color) {
+        self.draw_circle_ex(x, y, radius, circle_segments(radius), color);
+    }
+
+    /// Circle made of exactly `segments` straight edges (at least 3), e.g. 6 for a hexagon
+    pub fn draw_circle_ex(&mut self, x: f32, y: f32, radius: f32, segments: u32, color: Color) {
+        self.draw_polygon(x, y, radius, segments.max(3), 0.0, color);
+    }
+
+    /// Regular polygon with `sides` corners `radius` from `(x, y)`, the first one `rotation` radians clockwise
+    /// from the right. For a hex grid, `0.0` is a flat top hexagon and `PI / 6.0` a pointy top one.
+    /// Less than 3 sides, or a radius of 0 or less, draws nothing. Outlined with [`DrawStyle::Line`]
+    pub fn draw_polygon(
+        &mut self,
+        x: f32,
+        y: f32,
+        radius: f32,
+        sides: u32,
+        rotation: f32,
+        color: Color,
+    ) {
+        // Every triangle would be a point, so skip them instead of pushing them for nothing
+        if radius <= 0.0 || sides < 3 {
+            return;
+        }
+        let point = |i: u32, r: f32| {
+            let angle = rotation + i as f32 / sides as f32 * PI * 2.0;
+            (x + angle.cos() * r, y + angle.sin() * r)
+        };
+
+        match self.style {
+            DrawStyle::Fill => {
+                let rim: Vec<(f32, f32)> = (0..=sides).map(|i| point(i, radius)).collect();
+                self.draw_triangle_fan((x, y), &rim, color);
+            }
+            DrawStyle::Line { thickness } => {
+                let inner = (radius - thickness).max(0.0);
+                let ring: Vec<(f32, f32)> = (0..=sides)
+                    .flat_map(|i| [point(i, radius), point(i, inner)])
+                    .collect();
+                self.draw_triangle_strip(&ring, color);
+            }
+        }
+    }
+
+    /// Ring `thickness` wide centered on `radius`, half inside it and half outside, unlike the outline
+    /// [`DrawStyle::Line`] draws which is all inside. Same segments as [`Batch::draw_circle`]
+    pub fn draw_circle_outline(
+        &mut self,
+        x: f32,
+        y: f32,
+        radius: f32,
+        thickness: f32,
+        color: Color,
+    ) {
+        let segments = circle_segments(radius);
+        self.draw_circle_outline_ex(x, y, radius, thickness, segments, color);
+    }
+
+    /// Ring made of exactly `segments` edges, with its points at the same angles as [`Batch::draw_circle_ex`]'s so
+    /// a circle and its ring line up. The inside stops at the center if `thickness` is more than twice `radius`
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_circle_outline_ex(
+        &mut self,
+        x: f32,
+        y: f32,
+        radius: f32,
+        thickness: f32,
+        segments: u32,
+        color: Color,
+    ) {
+        if radius <= 0.0 || thickness <= 0.0 {
+            return;
+        }
+        let segments = segments.max(3);
+        let outer = radius + thickness / 2.0;
+        let inner = (radius - thickness / 2.0).max(0.0);
+        let ring: Vec<(f32, f32)> = (0..=segments)
+            .flat_map(|i| {
+                let angle = i as f32 / segments as f32 * PI * 2.0;
+                let (cos, sin) = (angle.cos(), angle.sin());
+                [
+                    (x + cos * outer, y + sin * outer),
+                    (x + cos * inner, y + sin * inner),
+                ]
+            })
+            .collect();
+        self.draw_triangle_strip(&ring, color);
+    }
+
+    pub fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32, color: Color) {
+        let color = self.vertex_color(color);
+        // Get angle of line, atan2 so vertical lines don't divide by zero
+        let angle = (y2 - y1).atan2(x2 - x1);
+        // Get perpendicular upper angle of line
+        let pangle = angle + PI / 2.0;
+        let r = thickness / 2.0;
+        // Get diffs
+        let pdx = pangle.cos() * r;
+        let pdy = pangle.sin() * r;
+
+        let vertices = &[
+            // Top left, 0
+            Vertex {
+                position: [x2 + pdx, y2 + pdy, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            // Top right, 1
+            Vertex {
+                position: [x1 + pdx, y1 + pdy, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            // bot right, 3
+            Vertex {
+                position: [x2 - pdx, y2 - pdy, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            // Bot left, 2
+            Vertex {
+                position: [x1 - pdx, y1 - pdy, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+        ];
+
+        let indices = &[
+            0, 2, 3, // Top triangle
+            3, 1, 0, // Bot triangle
+        ];
+
+        self.push_shape(vertices, indices);
+    }
+
+    /// Same as [`Batch::draw_line`], but with a `feather` wide fringe along both edges that fades out to
+    /// transparent, which cheaply smooths out the jagged edges without msaa. Costs 8 vertices instead of 4.
+    ///
+    /// `feather` is in world units. To smooth the edge without blurring it, make it one screen pixel wide,
+    /// which is what [`super::State::draw_line_smooth`] does with [`super::camera::Camera::world_per_pixel`]
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_line_smooth(
+        &mut self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        thickness: f32,
+        feather: f32,
+        color: Color,
+    ) {
+        let color = self.vertex_color(color);
+        // Same color, but fully transparent, so the gpu fades between the two
+        let fringe = [color[0], color[1], color[2], 0.0];
+
+        let angle = (y2 - y1).atan2(x2 - x1);
+        // Unit vector perpendicular to the line
+        let (px, py) = (-angle.sin(), angle.cos());
+        let r = thickness / 2.0;
+        let f = r + feather;
+
+        let vertices = &[
+            // Inner edges, same as `draw_line`
+            Vertex {
+                position: [x2 + px * r, y2 + py * r, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            Vertex {
+                position: [x1 + px * r, y1 + py * r, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            Vertex {
+                position: [x2 - px * r, y2 - py * r, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            Vertex {
+                position: [x1 - px * r, y1 - py * r, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            },
+            // Outer edges of the fringe, 4-7
+            Vertex {
+                position: [x2 + px * f, y2 + py * f, 0.0],
+                color: fringe,
+                tex_coords: [0.0, 0.0],
+            },
+            Vertex {
+                position: [x1 + px * f, y1 + py * f, 0.0],
+                color: fringe,
+                tex_coords: [0.0, 0.0],
+            },
+            Vertex {
+                position: [x2 - px * f, y2 - py * f, 0.0],
+                color: fringe,
+                tex_coords: [0.0, 0.0],
+            },
+            Vertex {
+                position: [x1 - px * f, y1 - py * f, 0.0],
+                color: fringe,
+                tex_coords: [0.0, 0.0],
+            },
+        ];
+
+        let indices = &[
+            0, 2, 3, // Body
+            3, 1, 0, //
+            4, 0, 1, // Top fringe
+            1, 5, 4, //
+            2, 6, 7, // Bot fringe
+            7, 3, 2,
+        ];
+
+        self.push_shape(vertices, indices);
+    }
+
+    /// Draws a line from `(x1, y1)` to `(x2, y2)` with a triangular head at `(x2, y2)`.
+    /// `head_size` is both the length and the width of the head
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_arrow(
+        &mut self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        thickness: f32,
+        head_size: f32,
+        color: Color,
+    ) {
+        // atan2 so that the head still points the right way for vertical & backwards lines
+        let angle = (y2 - y1).atan2(x2 - x1);
+        let (dx, dy) = (angle.cos(), angle.sin());
+        // Perpendicular to the line
+        let (pdx, pdy) = (-dy * head_size / 2.0, dx * head_size / 2.0);
+
+        // Stop the shaft at the base of the head so it doesn't poke out of the tip. An arrow shorter than its head
+        // is all head, the shaft would otherwise go out backwards past the start
+        let shaft = ((x2 - x1).hypot(y2 - y1) - head_size).max(0.0);
+        if shaft > 0.0 {
+            self.draw_line(x1, y1, x1 + dx * shaft, y1 + dy * shaft, thickness, color);
+        }
+
+        let bx = x2 - dx * head_size;
+        let by = y2 - dy * head_size;
+        self.draw_triangle((x2, y2), (bx + pdx, by + pdy), (bx - pdx, by - pdy), color);
+    }
+
+    /// Draws a stadium shape: a line from `(x1, y1)` to `(x2, y2)` `radius * 2` thick with a half circle on each end,
+    /// like a character's collider. It's one fan around the middle instead of a line and two circles, so there
+    /// are no seams or overlaps showing through a translucent color. Both ends on the same point is just a circle.
+    /// Outlined instead with [`DrawStyle::Line`], like [`Batch::draw_circle_ex`]
+    pub fn draw_capsule(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32, color: Color) {
+        // Both rims of an outline get the outer one's segments, so their points pair up at the same angles
+        let half = (circle_segments(radius) / 2).max(2);
+        let outer = capsule_rim(x1, y1, x2, y2, radius, half);
+        match self.style {
+            DrawStyle::Fill => {
+                self.draw_triangle_fan(((x1 + x2) / 2.0, (y1 + y2) / 2.0), &outer, color);
+            }
+            DrawStyle::Line { thickness } => {
+                let inner = capsule_rim(x1, y1, x2, y2, (radius - thickness).max(0.0), half);
+                let ring: Vec<(f32, f32)> = outer
+                    .into_iter()
+                    .zip(inner)
+                    .flat_map(|(outer, inner)| [outer, inner])
+                    .collect();
+                self.draw_triangle_strip(&ring, color);
+            }
+        }
+    }
+
+    /// Filled triangle between three points, in any order. They're put in counter clockwise order on screen, the
+    /// front face for the pipeline. Points in a line (or on top of each other) make a triangle with no area, which
+    /// would only take up room in the buffers, so they draw nothing
+    pub fn draw_triangle(&mut self, p1: (f32, f32), p2: (f32, f32), p3: (f32, f32), color: Color) {
+        let points = match counter_clockwise(p1, p2, p3) {
+            Some(points) => points,
+            None => return,
+        };
+        let color = self.vertex_color(color);
+        let vertices: Vec<Vertex> = points
+            .iter()
+            .map(|&(x, y)| Vertex {
+                position: [x, y, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            })
+            .collect();
+        self.push_shape(&vertices, &[0, 1, 2]);
+    }
+
+    /// Draws a filled convex polygon where every point has its own color, which the gpu blends
+    /// across the shape. Triangulated as a fan from the first point, so it has to be convex.
+    /// Less than 3 points draws nothing, and more than 65536 panics
+    pub fn draw_polygon_colored(&mut self, points: &[((f32, f32), Color)]) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let vertices: Vec<Vertex> = points
+            .iter()
+            .map(|&((x, y), color)| Vertex {
+                position: [x, y, 0.0],
+                color: self.vertex_color(color),
+                tex_coords: [0.0, 0.0],
+            })
+            .collect();
+
+        // Same fan as `draw_triangle_fan`, with the first point as the center
+        self.push_shape(&vertices, &fan_indices(points.len() - 1));
+    }
+
+    /// Draws the triangles `(center, rim[i], rim[i + 1])`, fanning out from `center`. The fan isn't closed, repeat
+    /// `rim[0]` at the end to go all the way around. Less than 2 rim points draws nothing, and more than 65535 panics.
+    ///
+    /// Triangles wind the same way the rim goes, though nothing is culled so either way shows.
+    /// Gets turned into a plain list of triangles in the batch like everything else, so it costs no extra draw call
+    pub fn draw_triangle_fan(&mut self, center: (f32, f32), rim: &[(f32, f32)], color: Color) {
+        if rim.len() < 2 {
+            return;
+        }
+
+        let color = self.vertex_color(color);
+        let vertices: Vec<Vertex> = std::iter::once(&center)
+            .chain(rim)
+            .map(|&(x, y)| Vertex {
+                position: [x, y, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            })
+            .collect();
+
+        self.push_shape(&vertices, &fan_indices(rim.len()));
+    }
+
+    /// Draws the triangles `(points[i], points[i + 1], points[i + 2])`, each one sharing an edge with the last,
+    /// like a ribbon. Every other triangle is flipped so they all wind the same way as the first.
+    /// Less than 3 points draws nothing, and more than 65536 panics. Converted to a list of triangles, same as
+    /// [`Batch::draw_triangle_fan`]
+    pub fn draw_triangle_strip(&mut self, points: &[(f32, f32)], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let color = self.vertex_color(color);
+        let vertices: Vec<Vertex> = points
+            .iter()
+            .map(|&(x, y)| Vertex {
+                position: [x, y, 0.0],
+                color,
+                tex_coords: [0.0, 0.0],
+            })
+            .collect();
+
+        self.push_shape(&vertices, &strip_indices(points.len()));
+    }
+
+    /// Draws triangles out of `indices` into `verts`, where each vert is a position and the uv coordinate
+    /// of the texture at that position. `(0, 0)` is the top left of the texture and `(1, 1)` the bottom right.
+    /// Nothing gets culled, so winding doesn't matter, though counter clockwise is the front face
+    pub fn draw_textured_polygon(
+        &mut self,
+        texture: TextureId,
+        verts: &[TexturedPoint],
+        indices: &[u16],
+        color: Color,
+    ) {
+        let color = self.vertex_color(color);
+        let vertices: Vec<Vertex> = verts
+            .iter()
+            .map(|&((x, y), (u, v))| Vertex {
+                position: [x, y, 0.0],
+                color,
+                tex_coords: [u, v],
+            })
+            .collect();
+
+        self.push_textured_shape(texture, &vertices, indices);
+    }
+
+    /// The whole texture stretched over `dst`, tinted by `color`. White leaves it as is
+    pub fn draw_texture_rect(&mut self, texture: TextureId, dst: Rect, color: Color) {
+        self.draw_textured_polygon(
+            texture,
+            &[
+                ((dst.x, dst.y), (0.0, 0.0)),
+                ((dst.right(), dst.y), (1.0, 0.0)),
+                ((dst.x, dst.bottom()), (0.0, 1.0)),
+                ((dst.right(), dst.bottom()), (1.0, 1.0)),
+            ],
+            &[0, 2, 3, 3, 1, 0],
+            color,
+        );
+    }
+
+    /// Draws `texture`, which is `texture_size` pixels, with its top left at `x`, `y` before it's rotated.
+    /// See [`DrawTextureParams`] for drawing part of it, scaling, rotating and flipping it
+    pub fn draw_texture_ex(
+        &mut self,
+        texture: TextureId,
+        texture_size: (u32, u32),
+        x: f32,
+        y: f32,
+        color: Color,
+        params: DrawTextureParams,
+    ) {
+        let (texture_width, texture_height) = (texture_size.0 as f32, texture_size.1 as f32);
+        let source = params
+            .source
+            .unwrap_or_else(|| Rect::new(0.0, 0.0, texture_width, texture_height));
+        let (width, height) = params.dest_size.unwrap_or((source.width, source.height));
+
+        let (mut left, mut right) = (
+            source.x / texture_width.max(1.0),
+            source.right() / texture_width.max(1.0),
+        );
+        let (mut top, mut bottom) = (
+            source.y / texture_height.max(1.0),
+            source.bottom() / texture_height.max(1.0),
+        );
+        if params.flip_x {
+            std::mem::swap(&mut left, &mut right);
+        }
+        if params.flip_y {
+            std::mem::swap(&mut top, &mut bottom);
+        }
+
+        let (pivot_x, pivot_y) = params.pivot.unwrap_or((x + width / 2.0, y + height / 2.0));
+        let (sin, cos) = params.rotation.sin_cos();
+        let rotate = |(px, py): (f32, f32)| {
+            let (dx, dy) = (px - pivot_x, py - pivot_y);
+            (pivot_x + dx * cos - dy * sin, pivot_y + dx * sin + dy * cos)
+        };
+
+        self.draw_textured_polygon(
+            texture,
+            &[
+                (rotate((x, y)), (left, top)),
+                (rotate((x + width, y)), (right, top)),
+                (rotate((x, y + height)), (left, bottom)),
+                (rotate((x + width, y + height)), (right, bottom)),
+            ],
+            &[0, 2, 3, 3, 1, 0],
+            color,
+        );
+    }
+
+    /// Pushes a plain colored shape into the vector of shapes.
+    /// Internally, converts `indices` on shape based off of previous number of vertices
+    pub fn push_shape(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        self.push_textured_shape(TextureId::WHITE, vertices, indices);
+    }
+
+    /// Pushes a shape that samples from `texture`. Shapes pushed one after another with
+    /// the same texture get merged into the same draw call.
+    ///
+    /// `indices` count from the shape's own first vertex, so they only have to fit in a `u16` for one shape.
+    /// The batch keeps them as `u32`s, so a frame can have well past 65535 vertices
+    pub fn push_textured_shape(
+        &mut self,
+        texture: TextureId,
+        vertices: &[Vertex],
+        indices: &[u16],
+    ) {
+        let start = self.indices.len() as u32;
+        let len = self.vertices.len() as u32;
+
+        // Not sure which implementation is better/faster
+        // indices.iter_mut().map(|i| *i += len);
+        // self.state.indices.extend_from_slice(indices);
+        // The reason is because while for_each avoids iterating over the
+        // array twice, push() might increase/decrease array len
+        // Need to benchmark
+
+        indices.iter().for_each(|i| {
+            self.indices.push(u32::from(*i) + len);
+        });
+
+        match self.transforms.last() {
+            Some(transform) => self.vertices.extend(vertices.iter().map(|vertex| {
+                let [x, y, z] = vertex.position;
+                let position = transform * cgmath::vec3(x, y, 1.0);
+                Vertex {
+                    position: [position.x, position.y, z],
+                    ..*vertex
+                }
+            })),
+            None => self.vertices.extend_from_slice(vertices),
+        }
+
+        let end = self.indices.len() as u32;
+        match self.draws.last_mut() {
+            Some(draw) if draw.texture == texture && draw.key == self.sort_key => {
+                draw.indices.end = end
+            }
+            _ => self.draws.push(DrawCall {
+                texture,
+                indices: start..end,
+                key: self.sort_key,
+            }),
+        }
+    }
+
+    /// Shapes pushed from now on are drawn in order of `key`, lowest first, instead of in the order they were
+    /// pushed, e.g. a key per layer, or the y position so things lower on the screen go in front. Shapes with
+    /// the same key stay in the order they were pushed. Goes back to 0 every frame
+    pub fn set_sort_key(&mut self, key: i32) {
+        self.sort_key = key;
+        self.keyed |= key != 0;
+    }
+
+    /// Reorders the draw calls by their [`Batch::set_sort_key`] key, keeping the pushed order within a key.
+    ///
+    /// Most frames never set a key, and those skip sorting entirely: nothing's done unless a key other than 0
+    /// was set this frame, or if the draws are already in order, so unkeyed frames cost the same as before keys existed
+    pub fn sort_draws(&mut self) {
+        if !self.keyed || self.draws.windows(2).all(|pair| pair[0].key <= pair[1].key) {
+            return;
+        }
+
+        // Stable, so shapes with the same key keep their order
+        self.draws.sort_by_key(|draw| draw.key);
+        let indices = std::mem::take(&mut self.indices);
+        self.indices.reserve(indices.len());
+        for draw in &mut self.draws {
+            let start = self.indices.len() as u32;
+            self.indices.extend_from_slice(
+                &indices[draw.indices.start as usize..draw.indices.end as usize],
+            );
+            draw.indices = start..self.indices.len() as u32;
+        }
+    }
+
+    /// Makes every shape pushed from now until the matching [`Batch::pop_transform`] get scaled by `scale`,
+    /// rotated `rotation` radians clockwise, then moved by `translation`, all around the origin. `scale` can
+    /// differ on x and y, for stretching sprites. Transforms nest, so a push inside another applies first.
+    ///
+    /// Only changes what goes into the batch, the camera (and so mapping the mouse into the world) is untouched
+    pub fn push_transform(
+        &mut self,
+        translation: Vector2<f32>,
+        rotation: f32,
+        scale: Vector2<f32>,
+    ) {
+        let transform = Matrix3::from_translation(translation)
+            * Matrix3::from_angle_z(cgmath::Rad(rotation))
+            * Matrix3::from_nonuniform_scale(scale.x, scale.y);
+        let transform = match self.transforms.last() {
+            Some(parent) => parent * transform,
+            None => transform,
+        };
+        self.transforms.push(transform);
+    }
+
+    /// Undoes the last [`Batch::push_transform`]. Does nothing if there's nothing to pop
+    pub fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+
+    /// Bytes that get copied into the vertex buffer
+    pub fn vertex_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.vertices)
+    }
+
+    /// Moves every shape in `other` to the end of this batch, leaving `other` empty
+    pub fn append(&mut self, other: &mut Batch) {
+        let offset = self.vertices.len() as u32;
+        let start = self.indices.len() as u32;
+        self.vertices.append(&mut other.vertices);
+        self.indices
+            .extend(other.indices.drain(..).map(|i| i + offset));
+        for draw in other.draws.drain(..) {
+            let indices = draw.indices.start + start..draw.indices.end + start;
+            match self.draws.last_mut() {
+                Some(last)
+                    if last.texture == draw.texture
+                        && last.key == draw.key
+                        && last.indices.end == indices.start =>
+                {
+                    last.indices.end = indices.end
+                }
+                _ => self.draws.push(DrawCall {
+                    texture: draw.texture,
+                    indices,
+                    key: draw.key,
+                }),
+            }
+        }
+        self.keyed |= other.keyed;
+        other.keyed = false;
+    }
+
+    /// Regroups the indices so that every texture ends up in a single draw call, in the
+    /// order each texture was first used. Shapes sharing a texture keep their relative order,
+    /// but a shape can now be drawn before one of a different texture pushed earlier,
+    /// so overlapping shapes of different textures may swap which one's on top.
+    ///
+    /// Textures are only bucketed within a sort key, so call [`Batch::sort_draws`] first to keep the keys in order
+    pub fn bucket_by_texture(&mut self) {
+        if self.draws.len() <= 1 {
+            return;
+        }
+
+        let mut buckets: Vec<(i32, TextureId, Vec<u32>)> = Vec::new();
+        for draw in &self.draws {
+            let indices = &self.indices[draw.indices.start as usize..draw.indices.end as usize];
+            // Only a handful of textures per frame, so a linear search beats hashing
+            match buckets
+                .iter_mut()
+                .find(|(key, texture, _)| *key == draw.key && *texture == draw.texture)
+            {
+                Some((_, _, bucket)) => bucket.extend_from_slice(indices),
+                None => buckets.push((draw.key, draw.texture, indices.to_vec())),
+            }
+        }
+
+        self.indices.clear();
+        self.draws.clear();
+        for (key, texture, indices) in buckets {
+            let start = self.indices.len() as u32;
+            self.indices.extend_from_slice(&indices);
+            self.draws.push(DrawCall {
+                texture,
+                indices: start..self.indices.len() as u32,
+                key,
+            });
+        }
+    }
+
+    /// Empties the batch, keeping the allocations around for the next frame
+    pub fn clear(&mut self) {
+        self.indices.clear();
+        self.vertices.clear();
+        self.draws.clear();
+        self.transforms.clear();
+        self.sort_key = 0;
+        self.keyed = false;
+    }
+}
+
+/// Segments for a circle `radius` pixels across on screen to look round: about one every 4 pixels
+/// of circumference, but no fewer than 8 for tiny circles, and no more than 256 for huge ones
+pub fn circle_segments(radius: f32) -> u32 {
+    let circumference = radius.abs() * PI * 2.0;
+    ((circumference / 4.0).ceil() as u32).clamp(8, 256)
+}
+
+/// The points in counter clockwise order as they show up on screen, `None` if they don't make a triangle.
+/// y goes down the screen, so that's clockwise going by the numbers
+fn counter_clockwise(p1: (f32, f32), p2: (f32, f32), p3: (f32, f32)) -> Option<[(f32, f32); 3]> {
+    let cross = (p2.0 - p1.0) * (p3.1 - p1.1) - (p2.1 - p1.1) * (p3.0 - p1.0);
+    if cross.abs() < 1e-6 {
+        None
+    } else if cross < 0.0 {
+        Some([p1, p2, p3])
+    } else {
+        Some([p1, p3, p2])
+    }
+}
+
+/// Outline of a capsule, going around the cap at `(x2, y2)` and then the one at `(x1, y1)`, with the first point
+/// repeated at the end to close it. Each cap is made of `half` segments
+fn capsule_rim(x1: f32, y1: f32, x2: f32, y2: f32, radius: f32, half: u32) -> Vec<(f32, f32)> {
+    // Zero length has no direction, any angle works since the caps make a full circle
+    let angle = (y2 - y1).atan2(x2 - x1);
+    let cap = |(x, y): (f32, f32), start: f32| {
+        (0..=half).map(move |i| {
+            let a = start + i as f32 / half as f32 * PI;
+            (x + a.cos() * radius, y + a.sin() * radius)
+        })
+    };
+    let mut rim: Vec<(f32, f32)> = cap((x2, y2), angle - PI / 2.0)
+        .chain(cap((x1, y1), angle + PI / 2.0))
+        .collect();
+    rim.push(rim[0]);
+    rim
+}
+
+/// Panics if a shape's `vertices` can't all be numbered by its `u16` indices
+fn check_index_range(vertices: usize) {
+    assert!(
+        vertices <= u16::MAX as usize + 1,
+        "a shape can have at most 65536 vertices, got {}",
+        vertices
+    );
+}
+
+/// Triangle list for a fan with the center at 0, followed by `rim` points. Empty under 2 rim points
+fn fan_indices(rim: usize) -> Vec<u16> {
+    check_index_range(rim + 1);
+    (1..rim as u16).flat_map(|i| [0, i, i + 1]).collect()
+}
+
+/// Triangle list for a strip, with odd triangles flipped to keep the winding. Empty under 3 points
+fn strip_indices(points: usize) -> Vec<u16> {
+    check_index_range(points);
+    (0..points.saturating_sub(2) as u16)
+        .flat_map(|i| {
+            if i % 2 == 0 {
+                [i, i + 1, i + 2]
+            } else {
+                [i + 1, i, i + 2]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        circle_segments, fan_indices, strip_indices, Batch, Dashes, DrawCall, DrawStyle,
+        DrawTextureParams, Vertex,
+    };
+    use crate::graphics::color::{self, Color};
+    use crate::graphics::rect::Rect;
+    use crate::graphics::texture::TextureId;
+
+    #[test]
+    fn transform_scales_x_and_y_separately() {
+        let mut batch = Batch::default();
+        batch.push_transform(cgmath::vec2(10.0, 20.0), 0.0, cgmath::vec2(2.0, 0.5));
+        batch.draw_square(0.0, 0.0, 1.0, Color::from_rgb(255, 255, 255, 255));
+        batch.pop_transform();
+        batch.draw_square(0.0, 0.0, 1.0, Color::from_rgb(255, 255, 255, 255));
+
+        let corners: Vec<[f32; 3]> = batch.vertices.iter().map(|v| v.position).collect();
+        assert_eq!(
+            corners[..4],
+            [
+                [10.0, 20.0, 0.0],
+                [12.0, 20.0, 0.0],
+                [10.0, 20.5, 0.0],
+                [12.0, 20.5, 0.0]
+            ]
+        );
+        // Popped, so back to drawing untransformed
+        assert_eq!(corners[5], [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn edited_vertices_are_what_gets_uploaded() {
+        let mut batch = Batch::default();
+        batch.draw_square(0.0, 0.0, 10.0, Color::from_rgb(255, 255, 255, 255));
+        batch.vertices[3].position[1] += 5.0;
+
+        let uploaded: &[Vertex] = bytemuck::cast_slice(batch.vertex_bytes());
+        assert_eq!(uploaded[3].position, [10.0, 15.0, 0.0]);
+    }
+
+    #[test]
+    fn bigger_circles_get_more_segments() {
+        assert_eq!(circle_segments(1.0), 8);
+        assert!(circle_segments(100.0) > circle_segments(10.0));
+        assert_eq!(circle_segments(100_000.0), 256);
+
+        let white = Color::from_rgb(255, 255, 255, 255);
+        let mut small = Batch::default();
+        small.draw_circle(0.0, 0.0, 5.0, white);
+        let mut large = Batch::default();
+        large.draw_circle(0.0, 0.0, 200.0, white);
+        assert!(large.vertices.len() > small.vertices.len());
+
+        // Outlines go with the style too, as a ring
+        let mut ring = Batch {
+            style: DrawStyle::Line { thickness: 2.0 },
+            ..Batch::default()
+        };
+        ring.draw_circle_ex(0.0, 0.0, 10.0, 8, white);
+        assert_eq!(ring.vertices.len(), 18);
+        assert_eq!(ring.indices.len(), 16 * 3);
+    }
+
+    #[test]
+    fn ring_is_centered_on_the_radius_and_lines_up_with_the_circle() {
+        let mut circle = Batch::default();
+        circle.draw_circle(0.0, 0.0, 20.0, Color::WHITE);
+        let mut ring = Batch::default();
+        ring.draw_circle_outline(0.0, 0.0, 20.0, 4.0, Color::WHITE);
+
+        // A fan has the center then a rim point per segment, the ring an outer and inner point per segment
+        let segments = circle.vertices.len() - 1;
+        assert_eq!(ring.vertices.len(), segments * 2);
+        for (i, rim) in circle.vertices[1..].iter().enumerate() {
+            let outer = ring.vertices[i * 2].position;
+            let inner = ring.vertices[i * 2 + 1].position;
+            assert!((outer[0].hypot(outer[1]) - 22.0).abs() < 1e-4);
+            assert!((inner[0].hypot(inner[1]) - 18.0).abs() < 1e-4);
+            // Same angle as the filled circle's rim
+            let mid = [(outer[0] + inner[0]) / 2.0, (outer[1] + inner[1]) / 2.0];
+            assert!((mid[0] - rim.position[0]).abs() < 1e-4);
+            assert!((mid[1] - rim.position[1]).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn triangles_wind_the_same_way_and_skip_flat_ones() {
+        let mut batch = Batch::default();
+        // Same triangle, given both ways around
+        batch.draw_triangle((0.0, 0.0), (0.0, 10.0), (10.0, 10.0), Color::WHITE);
+        batch.draw_triangle((0.0, 0.0), (10.0, 10.0), (0.0, 10.0), Color::WHITE);
+        assert_eq!(batch.vertices.len(), 6);
+        let first: Vec<[f32; 3]> = batch.vertices[..3].iter().map(|v| v.position).collect();
+        let second: Vec<[f32; 3]> = batch.vertices[3..].iter().map(|v| v.position).collect();
+        assert_eq!(first, second);
+        // Same order as the top left, bottom left, bottom right of a square
+        assert_eq!(first[1], [0.0, 10.0, 0.0]);
+
+        batch.draw_triangle((0.0, 0.0), (5.0, 5.0), (10.0, 10.0), Color::WHITE);
+        batch.draw_triangle((3.0, 3.0), (3.0, 3.0), (8.0, 1.0), Color::WHITE);
+        assert_eq!(batch.vertices.len(), 6);
+    }
+
+    #[test]
+    fn hexagon_rotation_picks_flat_or_pointy_top() {
+        let mut flat = Batch::default();
+        flat.draw_polygon(0.0, 0.0, 10.0, 6, 0.0, Color::WHITE);
+        // Center, then 6 corners with the first repeated to close the fan
+        assert_eq!(flat.vertices.len(), 8);
+        assert_eq!(flat.indices.len(), 6 * 3);
+        assert_eq!(flat.vertices[1].position, [10.0, 0.0, 0.0]);
+
+        let mut pointy = Batch::default();
+        pointy.draw_polygon(0.0, 0.0, 10.0, 6, std::f32::consts::PI / 6.0, Color::WHITE);
+        // A corner straight down, y goes down the screen
+        let bottom = pointy.vertices[2].position;
+        assert!(bottom[0].abs() < 1e-4 && (bottom[1] - 10.0).abs() < 1e-4);
+
+        let mut nothing = Batch::default();
+        nothing.draw_polygon(0.0, 0.0, 10.0, 2, 0.0, Color::WHITE);
+        assert!(nothing.vertices.is_empty());
+    }
+
+    #[test]
+    fn zero_radius_circle_draws_nothing() {
+        let mut batch = Batch::default();
+        batch.draw_circle(10.0, 10.0, 0.0, Color::WHITE);
+        batch.draw_circle_ex(10.0, 10.0, -3.0, 16, Color::WHITE);
+        assert!(batch.vertices.is_empty());
+        assert!(batch.draws.is_empty());
+    }
+
+    #[test]
+    fn largest_polygon_keeps_every_index() {
+        let white = Color::from_rgb(255, 255, 255, 255);
+        let points: Vec<_> = (0..65536).map(|i| ((i as f32, 0.0), white)).collect();
+        let mut batch = Batch::default();
+        batch.draw_polygon_colored(&points);
+        assert_eq!(batch.indices.len(), 65534 * 3);
+        assert_eq!(batch.indices.last(), Some(&65535));
+
+        batch.draw_triangle_strip(&[(0.0, 0.0); 65536], white);
+        assert_eq!(batch.indices.last(), Some(&(65536 + 65535)));
+    }
+
+    #[test]
+    #[should_panic(expected = "a shape can have at most 65536 vertices, got 65537")]
+    fn polygon_past_u16_indices_panics() {
+        let white = Color::from_rgb(255, 255, 255, 255);
+        let points = vec![((0.0, 0.0), white); 65537];
+        Batch::default().draw_polygon_colored(&points);
+    }
+
+    #[test]
+    fn fan_and_strip_become_triangle_lists() {
+        assert_eq!(fan_indices(4), [0, 1, 2, 0, 2, 3, 0, 3, 4]);
+        assert_eq!(strip_indices(5), [0, 1, 2, 2, 1, 3, 2, 3, 4]);
+        assert!(fan_indices(0).is_empty());
+        assert!(fan_indices(1).is_empty());
+        assert!(strip_indices(0).is_empty());
+        assert!(strip_indices(2).is_empty());
+
+        let white = Color::from_rgb(255, 255, 255, 255);
+        let mut batch = Batch::default();
+        batch.draw_square(0.0, 0.0, 1.0, white);
+        batch.draw_triangle_fan((0.0, 0.0), &[(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0)], white);
+        // Offset past the square's 4 vertices
+        assert_eq!(batch.indices[6..], [4, 5, 6, 4, 6, 7]);
+        assert_eq!(batch.vertices[4].position, [0.0, 0.0, 0.0]);
+
+        // Too few points to make a triangle
+        batch.draw_triangle_strip(&[(0.0, 0.0), (1.0, 1.0)], white);
+        batch.draw_triangle_fan((0.0, 0.0), &[(1.0, 0.0)], white);
+        assert_eq!(batch.indices.len(), 12);
+    }
+
+    #[test]
+    fn arrow_head_points_along_vertical_line() {
+        let mut batch = Batch::default();
+        batch.draw_arrow(
+            10.0,
+            0.0,
+            10.0,
+            100.0,
+            2.0,
+            10.0,
+            Color::from_rgb(255, 255, 255, 255),
+        );
+
+        // Shaft is a quad, head is the last triangle
+        assert_eq!(batch.vertices.len(), 7);
+        let tip = batch.vertices[4].position;
+        let left = batch.vertices[5].position;
+        let right = batch.vertices[6].position;
+
+        assert_eq!([tip[0], tip[1]], [10.0, 100.0]);
+        // Base of the head sits `head_size` behind the tip, spread evenly across the line
+        assert!((left[1] - 90.0).abs() < 1e-4);
+        assert!((right[1] - 90.0).abs() < 1e-4);
+        assert!(((left[0] - 10.0).abs() - 5.0).abs() < 1e-4);
+        assert!((left[0] + right[0] - 20.0).abs() < 1e-4);
+        assert_eq!(&batch.indices[6..], &[4, 5, 6]);
+    }
+
+    #[test]
+    fn arrow_shorter_than_its_head_has_no_shaft() {
+        let mut batch = Batch::default();
+        batch.draw_arrow(
+            0.0,
+            0.0,
+            5.0,
+            0.0,
+            2.0,
+            10.0,
+            Color::from_rgb(255, 255, 255, 255),
+        );
+
+        // Just the head, with its tip still on the end
+        assert_eq!(batch.vertices.len(), 3);
+        assert_eq!(batch.indices, [0, 1, 2]);
+        let tip = batch.vertices[0].position;
+        assert_eq!([tip[0], tip[1]], [5.0, 0.0]);
+    }
+
+    #[test]
+    fn marching_ants_crawl_and_loop() {
+        let early = Dashes::marching_ants(0.1);
+        let later = Dashes::marching_ants(0.2);
+        assert_ne!(early.offset, later.offset);
+        assert!(later.offset > early.offset);
+        // One whole period (8 pixels at 16 a second) later it's back where it was
+        let looped = Dashes::marching_ants(0.6);
+        assert!((looped.offset - early.offset).abs() < 1e-4);
+
+        // Moving the offset moves where the dashes fall
+        let still = Dashes::marching_ants(0.0);
+        assert_eq!(still.spans(0.0, 10.0), [(0.0, 4.0), (8.0, 10.0)]);
+        let moved = Dashes {
+            offset: 2.0,
+            ..still
+        };
+        assert_eq!(moved.spans(0.0, 10.0), [(2.0, 6.0)]);
+    }
+
+    #[test]
+    fn dashes_carry_on_around_corners() {
+        let dashes = Dashes {
+            dash: 4.0,
+            gap: 4.0,
+            offset: 0.0,
+        };
+        // The side before ended 2 pixels into a dash, so this one starts with the rest of it
+        assert_eq!(dashes.spans(10.0, 12.0), [(0.0, 2.0), (6.0, 10.0)]);
+
+        let mut batch = Batch::default();
+        batch.draw_dashed_rect(Rect::new(0.0, 0.0, 16.0, 8.0), 1.0, dashes, Color::WHITE);
+        // 48 pixels around is 6 dashes, nothing splits over a corner here so each is one quad
+        assert_eq!(batch.vertices.len(), 6 * 4);
+        assert_eq!(batch.vertices[0].position, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn zero_length_capsule_is_a_circle() {
+        let mut batch = Batch::default();
+        batch.draw_capsule(30.0, 40.0, 30.0, 40.0, 12.0, Color::WHITE);
+
+        // Fanned out from the center, with every rim point `radius` away
+        assert_eq!(batch.vertices[0].position, [30.0, 40.0, 0.0]);
+        for vertex in &batch.vertices[1..] {
+            let [x, y, _] = vertex.position;
+            assert!(((x - 30.0).hypot(y - 40.0) - 12.0).abs() < 1e-4);
+        }
+        // And the rim goes all the way around, with no gap where the caps meet
+        let angles: Vec<f32> = batch.vertices[1..]
+            .iter()
+            .map(|v| (v.position[1] - 40.0).atan2(v.position[0] - 30.0))
+            .collect();
+        for pair in angles.windows(2) {
+            let step = (pair[1] - pair[0]).rem_euclid(std::f32::consts::PI * 2.0);
+            assert!(step < 1.0);
+        }
+        let last = batch.vertices.last().unwrap().position;
+        assert_eq!(batch.vertices[1].position, last);
+    }
+
+    #[test]
+    fn capsule_sides_are_radius_from_the_line() {
+        let mut batch = Batch::default();
+        batch.draw_capsule(0.0, 0.0, 100.0, 0.0, 5.0, Color::WHITE);
+        let ys: Vec<f32> = batch.vertices[1..].iter().map(|v| v.position[1]).collect();
+        let top = ys.iter().cloned().fold(f32::MIN, f32::max);
+        let bottom = ys.iter().cloned().fold(f32::MAX, f32::min);
+        assert!((top - 5.0).abs() < 1e-4);
+        assert!((bottom + 5.0).abs() < 1e-4);
+        let xs: Vec<f32> = batch.vertices[1..].iter().map(|v| v.position[0]).collect();
+        assert!((xs.iter().cloned().fold(f32::MIN, f32::max) - 105.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn outlined_capsule_rims_pair_up_and_close() {
+        let mut batch = Batch {
+            style: DrawStyle::Line { thickness: 2.0 },
+            ..Default::default()
+        };
+        // A circle 18 across would get fewer segments than one 20 across, but the inner rim has to keep up
+        batch.draw_capsule(0.0, 0.0, 100.0, 0.0, 20.0, Color::WHITE);
+
+        // Outer and inner points alternate, so an even number of them, every outer one 20 from the line's ends
+        // or sides and the inner one next to it 2 closer, at the same angle
+        let points: Vec<(f32, f32)> = batch
+            .vertices
+            .iter()
+            .map(|v| (v.position[0], v.position[1]))
+            .collect();
+        assert_eq!(points.len() % 2, 0);
+        let half = (circle_segments(20.0) / 2).max(2) as usize;
+        assert_eq!(points.len(), 2 * (2 * (half + 1) + 1));
+        let from_line = |(x, y): (f32, f32)| {
+            let nearest = x.clamp(0.0, 100.0);
+            ((x - nearest).hypot(y), y.atan2(x - nearest))
+        };
+        for pair in points.chunks(2) {
+            let (outer, outer_angle) = from_line(pair[0]);
+            let (inner, inner_angle) = from_line(pair[1]);
+            assert!((outer - 20.0).abs() < 1e-3);
+            assert!((inner - 18.0).abs() < 1e-3);
+            assert!((outer_angle - inner_angle).abs() < 1e-3);
+        }
+        // The strip ends where it started, so there's no gap
+        assert_eq!(points[0], points[points.len() - 2]);
+        assert_eq!(points[1], points[points.len() - 1]);
+    }
+
+    #[test]
+    fn vertical_line_is_a_thickness_wide_quad() {
+        let mut batch = Batch::default();
+        batch.draw_line(10.0, 0.0, 10.0, 100.0, 4.0, Color::WHITE);
+        assert_eq!(batch.vertices.len(), 4);
+        for vertex in &batch.vertices {
+            assert!(vertex.position.iter().all(|v| v.is_finite()));
+        }
+        let mut xs: Vec<f32> = batch.vertices.iter().map(|v| v.position[0]).collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!((xs[0] - 8.0).abs() < 1e-4 && (xs[1] - 8.0).abs() < 1e-4);
+        assert!((xs[2] - 12.0).abs() < 1e-4 && (xs[3] - 12.0).abs() < 1e-4);
+        let mut ys: Vec<f32> = batch.vertices.iter().map(|v| v.position[1]).collect();
+        ys.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!(ys[0].abs() < 1e-4 && ys[1].abs() < 1e-4);
+        assert!((ys[2] - 100.0).abs() < 1e-4 && (ys[3] - 100.0).abs() < 1e-4);
+
+        // A point has no direction, but still shouldn't make NaNs
+        batch.clear();
+        batch.draw_line(5.0, 5.0, 5.0, 5.0, 2.0, Color::WHITE);
+        assert!(batch
+            .vertices
+            .iter()
+            .all(|v| v.position.iter().all(|p| p.is_finite())));
+    }
+
+    #[test]
+    fn smooth_line_fringe_fades_out() {
+        let mut batch = Batch::default();
+        batch.draw_line_smooth(
+            0.0,
+            0.0,
+            100.0,
+            0.0,
+            4.0,
+            1.5,
+            Color::from_rgb(255, 0, 0, 255),
+        );
+
+        assert_eq!(batch.vertices.len(), 8);
+        assert_eq!(batch.indices.len(), 18);
+        let body = &batch.vertices[..4];
+        let fringe = &batch.vertices[4..];
+        assert!(body.iter().all(|v| v.color[3] > 0.99));
+        assert!(fringe.iter().all(|v| v.color[3] == 0.0));
+        // Fringe keeps the color, and sits the feather past the body
+        assert_eq!(fringe[0].color[0], body[0].color[0]);
+        assert!((fringe[0].position[1].abs() - 3.5).abs() < 1e-4);
+        assert!((body[0].position[1].abs() - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn colored_polygon_keeps_each_vertex_color() {
+        let red = Color::from_rgb(255, 0, 0, 255);
+        let green = Color::from_rgb(0, 255, 0, 255);
+        let blue = Color::from_rgb(0, 0, 255, 255);
+
+        let mut batch = Batch::default();
+        batch.draw_polygon_colored(&[((0.0, 0.0), red), ((10.0, 0.0), green), ((0.0, 10.0), blue)]);
+
+        assert_eq!(batch.indices, vec![0, 1, 2]);
+        let colors: Vec<[f32; 4]> = batch.vertices.iter().map(|v| v.color).collect();
+        assert!(colors[0][0] > 0.9 && colors[0][1] == 0.0 && colors[0][2] == 0.0);
+        assert!(colors[1][1] > 0.9 && colors[1][0] == 0.0 && colors[1][2] == 0.0);
+        assert!(colors[2][2] > 0.9 && colors[2][0] == 0.0 && colors[2][1] == 0.0);
+
+        // Not enough points for a triangle
+        batch.draw_polygon_colored(&[((0.0, 0.0), red), ((10.0, 0.0), green)]);
+        assert_eq!(batch.vertices.len(), 3);
+    }
+
+    #[test]
+    fn gamma_correction_can_be_turned_off() {
+        let color = Color::from_rgb(128, 64, 255, 255);
+
+        let mut batch = Batch::default();
+        batch.draw_square(0.0, 0.0, 1.0, color);
+        let corrected = batch.vertices[0].color;
+        assert_eq!(corrected, batch.vertex_color(color));
+        assert!(corrected[0] < 0.25);
+
+        batch.gamma_correction = false;
+        batch.draw_square(0.0, 0.0, 1.0, color);
+        let raw = batch.vertices[4].color;
+        assert_eq!(raw, [128.0 / 255.0, 64.0 / 255.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn text_gets_the_same_color_as_shapes() {
+        let color = Color::from_hex("#3fa2c7").unwrap();
+        let mut batch = Batch::default();
+        for gamma_correction in [true, false] {
+            batch.gamma_correction = gamma_correction;
+            assert_eq!(
+                color::to_f32_array(batch.wgpu_color(color)),
+                batch.vertex_color(color)
+            );
+        }
+    }
+
+    #[test]
+    fn white_is_full_intensity_for_any_surface_format() {
+        use wgpu::TextureFormat;
+
+        for format in [
+            TextureFormat::Bgra8UnormSrgb,
+            TextureFormat::Bgra8Unorm,
+            TextureFormat::Rgba8UnormSrgb,
+            TextureFormat::Rgba8Unorm,
+        ] {
+            let mut batch = Batch::new(format);
+            assert_eq!(batch.gamma_correction, format.describe().srgb);
+            batch.draw_square(0.0, 0.0, 1.0, Color::from_rgb(255, 255, 255, 255));
+            assert!(batch.vertices[0].color.iter().all(|c| *c > 0.99));
+        }
+    }
+
+    #[test]
+    fn texture_rect_is_the_whole_texture_tinted() {
+        let mut batch = Batch::default();
+        let red = Color::from_rgb(255, 0, 0, 255);
+        batch.draw_texture_rect(TextureId(2), Rect::new(10.0, 20.0, 16.0, 8.0), red);
+
+        assert_eq!(batch.vertices[0].position, [10.0, 20.0, 0.0]);
+        assert_eq!(batch.vertices[3].position, [26.0, 28.0, 0.0]);
+        assert_eq!(batch.vertices[0].tex_coords, [0.0, 0.0]);
+        assert_eq!(batch.vertices[3].tex_coords, [1.0, 1.0]);
+        assert!(batch
+            .vertices
+            .iter()
+            .all(|v| v.color == batch.vertex_color(red)));
+        assert_eq!(batch.draws[0].texture, TextureId(2));
+    }
+
+    #[test]
+    fn texture_ex_samples_the_source_rect() {
+        let mut batch = Batch::default();
+        // Second 16x16 frame of a 64x32 sheet, drawn twice as big
+        batch.draw_texture_ex(
+            TextureId(1),
+            (64, 32),
+            0.0,
+            0.0,
+            Color::WHITE,
+            DrawTextureParams {
+                source: Some(Rect::new(16.0, 0.0, 16.0, 16.0)),
+                dest_size: Some((32.0, 32.0)),
+                ..Default::default()
+            },
+        );
+        assert_eq!(batch.vertices[0].tex_coords, [0.25, 0.0]);
+        assert_eq!(batch.vertices[3].tex_coords, [0.5, 0.5]);
+        assert_eq!(batch.vertices[3].position, [32.0, 32.0, 0.0]);
+
+        batch.clear();
+        batch.draw_texture_ex(
+            TextureId(1),
+            (64, 32),
+            0.0,
+            0.0,
+            Color::WHITE,
+            DrawTextureParams {
+                flip_x: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(batch.vertices[0].tex_coords, [1.0, 0.0]);
+        assert_eq!(batch.vertices[1].tex_coords, [0.0, 0.0]);
+        assert_eq!(batch.vertices[1].position, [64.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn texture_ex_rotates_around_the_pivot() {
+        let mut batch = Batch::default();
+        let quarter_turn = DrawTextureParams {
+            rotation: std::f32::consts::FRAC_PI_2,
+            ..Default::default()
+        };
+        // Around its middle, (5, 5), the top left corner goes to the top right
+        batch.draw_texture_ex(TextureId(1), (10, 10), 0.0, 0.0, Color::WHITE, quarter_turn);
+        let [x, y, _] = batch.vertices[0].position;
+        assert!((x - 10.0).abs() < 1e-4 && y.abs() < 1e-4);
+
+        batch.clear();
+        batch.draw_texture_ex(
+            TextureId(1),
+            (10, 10),
+            0.0,
+            0.0,
+            Color::WHITE,
+            DrawTextureParams {
+                pivot: Some((0.0, 0.0)),
+                ..quarter_turn
+            },
+        );
+        // Around its top left, the top right corner swings down below it
+        let [x, y, _] = batch.vertices[1].position;
+        assert!(x.abs() < 1e-4 && (y - 10.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn textured_quad_out_of_two_triangles() {
+        let texture = TextureId(1);
+        let white = Color::from_rgb(255, 255, 255, 255);
+
+        let mut batch = Batch::default();
+        batch.draw_square(0.0, 0.0, 1.0, white);
+        batch.draw_textured_polygon(
+            texture,
+            &[
+                ((0.0, 0.0), (0.0, 0.0)),
+                ((32.0, 0.0), (1.0, 0.0)),
+                ((0.0, 32.0), (0.0, 1.0)),
+                ((32.0, 32.0), (1.0, 1.0)),
+            ],
+            &[0, 2, 3, 3, 1, 0],
+            white,
+        );
+
+        assert_eq!(batch.vertices[7].position, [32.0, 32.0, 0.0]);
+        assert_eq!(batch.vertices[7].tex_coords, [1.0, 1.0]);
+        assert_eq!(&batch.indices[6..], &[4, 6, 7, 7, 5, 4]);
+        assert_eq!(
+            batch.draws,
+            vec![
+                DrawCall {
+                    texture: TextureId::WHITE,
+                    indices: 0..6,
+                    key: 0,
+                },
+                DrawCall {
+                    texture,
+                    indices: 6..12,
+                    key: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn outline_sides_cover_the_border_exactly_once() {
+        let mut batch = Batch::default();
+        batch.draw_rectangle_outline(10.0, 20.0, 30.0, 16.0, 3.0, Color::WHITE);
+        let quads: Vec<Rect> = batch
+            .vertices
+            .chunks(4)
+            .map(|quad| {
+                let [left, top, _] = quad[0].position;
+                let [right, bottom, _] = quad[3].position;
+                Rect::new(left, top, right - left, bottom - top)
+            })
+            .collect();
+        // Adds up to the outside minus the hole, so no corner is covered twice
+        let area: f32 = quads.iter().map(|q| q.width * q.height).sum();
+        assert_eq!(area, 30.0 * 16.0 - 24.0 * 10.0);
+        // And stays inside the rectangle
+        assert!(quads
+            .iter()
+            .all(|q| q.x >= 10.0 && q.y >= 20.0 && q.right() <= 40.0 && q.bottom() <= 36.0));
+    }
+
+    #[test]
+    fn line_style_draws_square_outline() {
+        let white = Color::from_rgb(255, 255, 255, 255);
+        let mut batch = Batch::default();
+        batch.draw_square(0.0, 0.0, 10.0, white);
+        assert_eq!(batch.vertices.len(), 4);
+
+        batch.clear();
+        batch.style = DrawStyle::Line { thickness: 2.0 };
+        batch.draw_square(0.0, 0.0, 10.0, white);
+        // One quad per side
+        assert_eq!(batch.vertices.len(), 16);
+        assert_eq!(batch.indices.len(), 24);
+        assert_eq!(batch.style, DrawStyle::Line { thickness: 2.0 });
+        // Nothing drawn over the hole in the middle
+        assert!(batch.vertices.iter().all(|v| !(v.position[0] > 2.0
+            && v.position[0] < 8.0
+            && v.position[1] > 2.0
+            && v.position[1] < 8.0)));
+    }
+
+    #[test]
+    fn bucketing_merges_interleaved_textures() {
+        let texture = TextureId(1);
+        let white = Color::from_rgb(255, 255, 255, 255);
+        let quad = [
+            ((0.0, 0.0), (0.0, 0.0)),
+            ((1.0, 0.0), (1.0, 0.0)),
+            ((0.0, 1.0), (0.0, 1.0)),
+        ];
+
+        let mut batch = Batch::default();
+        for _ in 0..3 {
+            batch.draw_square(0.0, 0.0, 1.0, white);
+            batch.draw_textured_polygon(texture, &quad, &[0, 1, 2], white);
+        }
+        assert_eq!(batch.draws.len(), 6);
+
+        batch.bucket_by_texture();
+        assert_eq!(
+            batch.draws,
+            vec![
+                DrawCall {
+                    texture: TextureId::WHITE,
+                    indices: 0..18,
+                    key: 0,
+                },
+                DrawCall {
+                    texture,
+                    indices: 18..27,
+                    key: 0,
+                },
+            ]
+        );
+        // Squares keep their order, the second one starts after the first's 4 + 3 vertices
+        assert_eq!(&batch.indices[6..9], &[7, 9, 10]);
+        assert_eq!(&batch.indices[18..], &[4, 5, 6, 11, 12, 13, 18, 19, 20]);
+    }
+
+    #[test]
+    fn indices_keep_counting_past_u16() {
+        let mut batch = Batch::default();
+        // 4 vertices each, 72000 vertices in all
+        for i in 0..18_000 {
+            batch.draw_square(i as f32, 0.0, 1.0, Color::WHITE);
+        }
+        assert!(batch.vertices.len() > 70_000);
+        assert_eq!(
+            &batch.indices[batch.indices.len() - 6..],
+            [71996, 71998, 71999, 71999, 71997, 71996]
+        );
+        // Every square's indices land on its own vertices, none wrap around onto an earlier one
+        for (square, indices) in batch.indices.chunks(6).enumerate() {
+            assert!(indices.iter().all(|&i| i as usize / 4 == square));
+        }
+    }
+
+    #[test]
+    fn atlas_sprites_are_one_draw_call() {
+        let atlas = TextureId(1);
+        let frame = |i: usize| DrawTextureParams {
+            source: Some(Rect::new((i % 8) as f32 * 16.0, 0.0, 16.0, 16.0)),
+            ..Default::default()
+        };
+
+        let mut batch = Batch::default();
+        for i in 0..5000 {
+            batch.draw_texture_ex(atlas, (128, 16), i as f32, 0.0, Color::WHITE, frame(i));
+        }
+        assert_eq!(batch.draws.len(), 1);
+
+        // With solid shapes between every sprite, bucketing still leaves one draw for the atlas
+        batch.clear();
+        for i in 0..5000 {
+            batch.draw_square(i as f32, 0.0, 1.0, Color::WHITE);
+            batch.draw_texture_ex(atlas, (128, 16), i as f32, 0.0, Color::WHITE, frame(i));
+        }
+        batch.bucket_by_texture();
+        let textures: Vec<TextureId> = batch.draws.iter().map(|draw| draw.texture).collect();
+        assert_eq!(textures, [TextureId::WHITE, atlas]);
+        assert_eq!(batch.draws[1].indices.len(), 5000 * 6);
+    }
+
+    #[test]
+    fn sort_keys_reorder_draws_and_keep_ties_in_order() {
+        let white = Color::from_rgb(255, 255, 255, 255);
+        let mut batch = Batch::default();
+        batch.draw_square(0.0, 0.0, 1.0, white);
+        let unkeyed = batch.indices.clone();
+        batch.sort_draws();
+        // Nothing keyed, nothing touched
+        assert_eq!(batch.indices, unkeyed);
+
+        batch.set_sort_key(2);
+        batch.draw_square(1.0, 0.0, 1.0, white);
+        batch.set_sort_key(-1);
+        batch.draw_square(2.0, 0.0, 1.0, white);
+        batch.set_sort_key(0);
+        batch.draw_square(3.0, 0.0, 1.0, white);
+        assert_eq!(batch.draws.len(), 4);
+
+        batch.sort_draws();
+        let keys: Vec<i32> = batch.draws.iter().map(|draw| draw.key).collect();
+        assert_eq!(keys, vec![-1, 0, 0, 2]);
+        // Each square is 4 vertices, so the first index says which square it is
+        let squares: Vec<u32> = batch
+            .draws
+            .iter()
+            .map(|draw| batch.indices[draw.indices.start as usize] / 4)
+            .collect();
+        assert_eq!(squares, vec![2, 0, 3, 1]);
+
+        batch.clear();
+        batch.draw_square(0.0, 0.0, 1.0, white);
+        assert_eq!(batch.draws[0].key, 0);
+    }
+
+    #[test]
+    fn fade_fills_bounds_even_when_outlining() {
+        let mut batch = Batch {
+            style: DrawStyle::Line { thickness: 1.0 },
+            ..Default::default()
+        };
+        batch.fade(
+            Rect::new(-10.0, -10.0, 100.0, 50.0),
+            Color::from_rgb(0, 0, 0, 64),
+        );
+
+        assert_eq!(batch.vertices.len(), 4);
+        assert_eq!(batch.vertices[0].position, [-10.0, -10.0, 0.0]);
+        assert_eq!(batch.vertices[3].position, [90.0, 40.0, 0.0]);
+        assert!(batch.vertices.iter().all(|v| v.color[3] < 1.0));
+        assert_eq!(batch.style, DrawStyle::Line { thickness: 1.0 });
+    }
+
+    #[test]
+    fn repeated_fades_compound() {
+        // A white pixel left over from before, faded to black a quarter at a time, blended the way the pipeline's
+        // alpha blending does it: new = fade * alpha + old * (1 - alpha)
+        let mut batch = Batch::default();
+        let mut pixel = 1.0;
+        for _ in 0..10 {
+            batch.clear();
+            batch.fade(
+                Rect::new(0.0, 0.0, 800.0, 600.0),
+                Color::from_rgb(0, 0, 0, 64),
+            );
+            let [fade, _, _, alpha] = batch.vertices[0].color;
+            pixel = fade * alpha + pixel * (1.0 - alpha);
+        }
+
+        // Each frame keeps 191/255 of what was there, so 10 frames leave (191/255)^10, about 5.6%
+        let expected = (1.0 - 64.0 / 255.0f32).powi(10);
+        assert!((pixel - expected).abs() < 1e-4);
+        assert!(pixel > 0.05 && pixel < 0.06);
+    }
+}
@@ -10,6 +10,8 @@ pub struct Vertex {
     pub position: [f32; 3],
     // rgb
     pub color: [f32; 4],
+    // Where to sample the texture, (0, 0) is top left and (1, 1) bottom right
+    pub tex_coords: [f32; 2],
 }
 
 impl Vertex {
@@ -36,11 +38,29 @@ impl Vertex {
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                // Lastly texture coords, 2 f32s, u, v
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
             ],
         }
     }
 }
 
+/// A vertex that can be drawn with its own attributes, see [`super::State::push_custom_shape`].
+/// `desc` lays out the attributes for the vertex shader, the way [`Vertex::desc`] does for the built in vertex
+pub trait VertexLayout: bytemuck::Pod {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a>;
+}
+
+impl VertexLayout for Vertex {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        Vertex::desc()
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Uniforms {
@@ -55,8 +75,37 @@ impl Uniforms {
             model: cgmath::ortho(0.0, x, y, 0.0, -1.0, 1.0).into(),
         }
     }
+    /// No camera, just pixels straight to the screen, for things like ui that shouldn't move with the world
+    pub fn screen(width: f32, height: f32) -> Self {
+        Self::new(width, height)
+    }
+    /// Same camera, with `transform` applied to vertices before anything else
+    pub fn transformed(&self, transform: cgmath::Matrix4<f32>) -> Self {
+        Self {
+            view: self.view,
+            model: (cgmath::Matrix4::from(self.model) * transform).into(),
+        }
+    }
     pub fn update_view_proj(&mut self, camera: &Camera) {
-        self.view = camera.build_view_projection_matrix().into();
+        self.view = camera.build_render_matrix().into();
+    }
+
+    /// Layout of the bind group the uniforms are bound in, group(0) in the shaders
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                // Only the vertex shaders position things with them
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("Uniform Bind Group Layout"),
+        })
     }
 }
 
@@ -113,6 +162,89 @@ impl InstanceRaw {
     }
 }
 
+/// Bytes a [`GrowableBuffer`] starts out with, so even an empty frame has a buffer to bind
+const MIN_BUFFER_SIZE: wgpu::BufferAddress = 1024;
+
+/// A vertex or index buffer kept from frame to frame. Each frame's data is written over the last's, and a new
+/// buffer is only allocated when a frame has more than fits, half again as big as it needs, so a scene that grows a
+/// little at a time doesn't reallocate every frame. Anything past what was written is left over from earlier
+/// frames, so draws have to stick to the indices they wrote.
+///
+/// Written data has to be a multiple of 4 bytes long, which vertices of `f32`s and `u32` indices always are
+pub struct GrowableBuffer {
+    buffer: wgpu::Buffer,
+    capacity: wgpu::BufferAddress,
+    usage: wgpu::BufferUsages,
+    label: &'static str,
+}
+
+impl GrowableBuffer {
+    /// `usage` is what it's for, `VERTEX` or `INDEX`. It can always be copied into
+    pub fn new(device: &wgpu::Device, label: &'static str, usage: wgpu::BufferUsages) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        Self {
+            buffer: Self::allocate(device, label, usage, MIN_BUFFER_SIZE),
+            capacity: MIN_BUFFER_SIZE,
+            usage,
+            label,
+        }
+    }
+
+    /// Writes `data` to the start of the buffer, allocating a bigger one first if it doesn't fit.
+    /// Returns whether it had to
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) -> bool {
+        let needed = data.len() as wgpu::BufferAddress;
+        let grown = grown_capacity(self.capacity, needed);
+        let reallocated = grown != self.capacity;
+        if reallocated {
+            self.buffer = Self::allocate(device, self.label, self.usage, grown);
+            self.capacity = grown;
+        }
+        if !data.is_empty() {
+            queue.write_buffer(&self.buffer, 0, data);
+        }
+        reallocated
+    }
+
+    /// The whole buffer, including whatever's past the last write
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(..)
+    }
+
+    /// Bytes it can hold before it has to grow
+    pub fn capacity(&self) -> wgpu::BufferAddress {
+        self.capacity
+    }
+
+    fn allocate(
+        device: &wgpu::Device,
+        label: &str,
+        usage: wgpu::BufferUsages,
+        size: wgpu::BufferAddress,
+    ) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+}
+
+/// How big a [`GrowableBuffer`] with `capacity` bytes has to be to fit `needed` bytes. The same if they fit,
+/// otherwise at least half again as big, rounded up to what buffers have to be aligned to
+fn grown_capacity(
+    capacity: wgpu::BufferAddress,
+    needed: wgpu::BufferAddress,
+) -> wgpu::BufferAddress {
+    if needed <= capacity {
+        return capacity;
+    }
+    let grown = (capacity + capacity / 2).max(needed);
+    let align = wgpu::COPY_BUFFER_ALIGNMENT;
+    grown.div_ceil(align) * align
+}
+
 // NEW!
 impl Instance {
     pub fn to_raw(&self) -> InstanceRaw {
@@ -126,7 +258,7 @@ impl Instance {
 mod test {
     use cgmath::Vector4;
 
-    use super::Uniforms;
+    use super::{grown_capacity, Uniforms};
     #[test]
     fn make_sure_ortho_works() {
         let uniforms = Uniforms::new(800.0, 600.0);
@@ -138,4 +270,24 @@ mod test {
 
         assert_eq!(res, cgmath::vec4(-0.5, -1.0, 0.0, 1.0));
     }
+
+    #[test]
+    fn buffers_grow_by_half_and_only_when_full() {
+        assert_eq!(grown_capacity(1024, 0), 1024);
+        assert_eq!(grown_capacity(1024, 1024), 1024);
+        assert_eq!(grown_capacity(1024, 1025), 1536);
+        // Jumps straight to what's needed when that's more than half again
+        assert_eq!(grown_capacity(1024, 10_000), 10_000);
+        assert_eq!(grown_capacity(1024, 10_001), 10_004);
+
+        // A scene growing by a few shapes a frame only reallocates now and then
+        let mut capacity = 1024;
+        let mut reallocations = 0;
+        for frame in 0..1000 {
+            let grown = grown_capacity(capacity, 1024 + frame * 36 * 4);
+            reallocations += usize::from(grown != capacity);
+            capacity = grown;
+        }
+        assert!(reallocations < 15);
+    }
 }
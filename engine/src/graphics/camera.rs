@@ -1,3 +1,20 @@
+use cgmath::{InnerSpace, SquareMatrix};
+
+use super::rect::Rect;
+
+/// How far the eye starts from the world, where the whole `width` x `height` fits on screen at a zoom of 1
+const DISTANCE: f32 = 2.46;
+
+/// What the shake randomness starts from, until [`Camera::set_shake_seed`] picks another
+const SHAKE_SEED: u32 = 0x9E37_79B9;
+
+/// One [`Camera::shake`] that's still going
+struct Shake {
+    intensity: f32,
+    duration: f32,
+    remaining: f32,
+}
+
 pub struct Camera {
     pub eye: cgmath::Point3<f32>,
     pub target: cgmath::Point3<f32>,
@@ -6,6 +23,19 @@ pub struct Camera {
     pub fov: f32,
     pub z_near: f32,
     pub z_far: f32,
+    /// Size of the world (in pixels) that gets mapped onto the screen, should match `Uniforms::model`
+    pub width: f32,
+    pub height: f32,
+    /// Part of the world the camera has to stay inside of, see [`Camera::clamp_to_bounds`]
+    pub bounds: Option<Rect>,
+    /// How far, in world pixels each way from the center, a target can wander before [`Camera::follow`]
+    /// moves. `(0.0, 0.0)` by default, which follows every move
+    pub dead_zone: (f32, f32),
+    shakes: Vec<Shake>,
+    /// Xorshift state the shake offsets are picked with, never 0
+    shake_rng: u32,
+    /// Where the shakes currently have the camera, in world pixels. Only rendering sees it
+    shake_offset: (f32, f32),
 }
 
 impl Camera {
@@ -14,18 +44,55 @@ impl Camera {
             // x is your horizontal location <- ->
             // y is your vertical location V ^
             // z is how far away or close you are to obj
-            eye: cgmath::Point3::new(0.0, 0.0, 2.46),
+            eye: cgmath::Point3::new(0.0, 0.0, DISTANCE),
             target: cgmath::Point3::new(0.0, 0.0, 0.0),
             up: cgmath::Vector3::unit_y(),
             aspect_ratio: (width / height) / 2.0,
             fov: 45.0,
             z_near: 0.1,
             z_far: 100.0,
+            width,
+            height,
+            bounds: None,
+            dead_zone: (0.0, 0.0),
+            shakes: Vec::new(),
+            shake_rng: SHAKE_SEED,
+            shake_offset: (0.0, 0.0),
         }
     }
+    /// Puts the camera back where [`Camera::new`] had it: centered, not zoomed, facing straight at the world, and not
+    /// shaking.
+    /// The aspect ratio and `bounds` are left alone, since they follow the window and the level rather than camera movement
+    pub fn reset(&mut self) {
+        let default = Camera::new(self.width, self.height);
+        self.eye = default.eye;
+        self.target = default.target;
+        self.up = default.up;
+        self.fov = default.fov;
+        self.z_near = default.z_near;
+        self.z_far = default.z_far;
+        self.shakes.clear();
+        self.shake_offset = (0.0, 0.0);
+    }
+
     pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+        self.view_projection(self.eye, self.target)
+    }
+
+    /// The view projection that actually goes to the gpu, [`Camera::build_view_projection_matrix`] moved by the
+    /// current [`Camera::shake_offset`]
+    pub fn build_render_matrix(&self) -> cgmath::Matrix4<f32> {
+        let offset = self.world_offset(self.shake_offset.0, self.shake_offset.1);
+        self.view_projection(self.eye + offset, self.target + offset)
+    }
+
+    fn view_projection(
+        &self,
+        eye: cgmath::Point3<f32>,
+        target: cgmath::Point3<f32>,
+    ) -> cgmath::Matrix4<f32> {
         // Moves world to be at position & rotation of camera?
-        let view = cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up);
+        let view = cgmath::Matrix4::look_at_rh(eye, target, self.up);
 
         // Gives effect of depth
         let proj = cgmath::perspective(
@@ -38,4 +105,571 @@ impl Camera {
         // Order matters! Proj before view
         proj * view
     }
+
+    /// The whole transform from world coordinates to clip space, same as what the shader does
+    pub fn build_world_matrix(&self) -> cgmath::Matrix4<f32> {
+        self.build_view_projection_matrix()
+            * cgmath::ortho(0.0, self.width, self.height, 0.0, -1.0, 1.0)
+    }
+
+    /// Where `(x, y)` in the world currently is in window pixels, given the part of the window the world
+    /// is drawn in (see [`super::State::viewport`])
+    pub fn project(&self, x: f32, y: f32, viewport: &Rect) -> (f32, f32) {
+        let clip = self.build_world_matrix() * cgmath::vec4(x, y, 0.0, 1.0);
+        let (ndc_x, ndc_y) = (clip.x / clip.w, clip.y / clip.w);
+        (
+            viewport.x + (ndc_x + 1.0) / 2.0 * viewport.width,
+            viewport.y + (1.0 - ndc_y) / 2.0 * viewport.height,
+        )
+    }
+
+    /// Same as [`Camera::project`], under the name that goes with [`Camera::screen_to_world`]
+    pub fn world_to_screen(&self, x: f32, y: f32, viewport: &Rect) -> (f32, f32) {
+        self.project(x, y, viewport)
+    }
+
+    /// Where window pixel `(x, y)` is in the world, the inverse of [`Camera::world_to_screen`]. For turning a click
+    /// into a world position, with the camera panned, zoomed, or rotated. `viewport` is the part of the window the
+    /// world is drawn in, see [`super::State::screen_to_world`] for the one that passes it in
+    pub fn screen_to_world(&self, x: f32, y: f32, viewport: &Rect) -> (f32, f32) {
+        let inverse = match self.build_world_matrix().invert() {
+            Some(inverse) => inverse,
+            None => return (x, y),
+        };
+        let ndc_x = (x - viewport.x) / viewport.width * 2.0 - 1.0;
+        let ndc_y = 1.0 - (y - viewport.y) / viewport.height * 2.0;
+        let hit = Self::unproject(&inverse, ndc_x, ndc_y);
+        (hit.x, hit.y)
+    }
+
+    /// How wide one window pixel is in the world, measured at the middle of `viewport`. For sizing things that
+    /// should stay the same on screen at any zoom, like the fringe of [`super::State::draw_line_smooth`]
+    pub fn world_per_pixel(&self, viewport: &Rect) -> f32 {
+        let (x, y) = (
+            viewport.x + viewport.width / 2.0,
+            viewport.y + viewport.height / 2.0,
+        );
+        let (x1, y1) = self.screen_to_world(x, y, viewport);
+        let (x2, y2) = self.screen_to_world(x + 1.0, y, viewport);
+        (x2 - x1).hypot(y2 - y1)
+    }
+
+    /// Where the point at `(x, y)` in normalized device coordinates lands on the world, at z = 0 where
+    /// everything 2d gets drawn. `inverse` is the inverted [`Camera::build_world_matrix`]
+    fn unproject(inverse: &cgmath::Matrix4<f32>, x: f32, y: f32) -> cgmath::Vector3<f32> {
+        // Unproject at two depths, then see where the ray between them hits z = 0
+        let near = inverse * cgmath::vec4(x, y, -1.0, 1.0);
+        let far = inverse * cgmath::vec4(x, y, 1.0, 1.0);
+        let near = near.truncate() / near.w;
+        let far = far.truncate() / far.w;
+        let t = near.z / (near.z - far.z);
+        near + (far - near) * t
+    }
+
+    /// Rectangle of the world that is currently on screen.
+    /// If the camera is rotated, this is the smallest rectangle containing everything on screen
+    pub fn visible_bounds(&self) -> Rect {
+        let inverse = match self.build_world_matrix().invert() {
+            Some(inverse) => inverse,
+            None => return Rect::default(),
+        };
+
+        let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+        let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for (nx, ny) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            let hit = Self::unproject(&inverse, nx, ny);
+
+            min_x = min_x.min(hit.x);
+            min_y = min_y.min(hit.y);
+            max_x = max_x.max(hit.x);
+            max_y = max_y.max(hit.y);
+        }
+
+        Rect::new(min_x, min_y, max_x - min_x, max_y - min_y)
+    }
+
+    /// World position at the center of the screen
+    pub fn center(&self) -> (f32, f32) {
+        let bounds = self.visible_bounds();
+        (
+            bounds.x + bounds.width / 2.0,
+            bounds.y + bounds.height / 2.0,
+        )
+    }
+
+    /// How zoomed in the camera is, `1.0` when the whole world size fits on screen, `2.0` when half of it does
+    pub fn zoom(&self) -> f32 {
+        DISTANCE / (self.eye - self.target).magnitude()
+    }
+
+    /// Zooms in (above `1.0`) or out (below it) by moving the eye closer to or further from what it's looking at,
+    /// so the center of the screen stays put. World coordinates don't change, only how much of the world is visible:
+    /// at `2.0` the screen shows half as much in each direction, each world pixel twice as big.
+    /// Shows up the same frame, since the camera goes to the gpu right before every render.
+    /// A zoom of 0 or less can't be shown, so it's ignored with a warning
+    pub fn set_zoom(&mut self, zoom: f32) {
+        if !(zoom > 0.0 && zoom.is_finite()) {
+            log::warn!("Ignoring camera zoom of {}, it has to be above 0", zoom);
+            return;
+        }
+        let direction = (self.eye - self.target).normalize();
+        self.eye = self.target + direction * (DISTANCE / zoom);
+    }
+
+    /// Pans the camera, without changing its zoom or angle, so that `(x, y)` in the world is at the center of the screen
+    pub fn look_at(&mut self, x: f32, y: f32) {
+        let (cx, cy) = self.center();
+        self.move_by(x - cx, y - cy);
+    }
+
+    /// World position the camera's centered on, the same as [`Camera::center`]
+    pub fn position(&self) -> (f32, f32) {
+        self.center()
+    }
+
+    /// Centers the camera on `(x, y)` in the world, the same as [`Camera::look_at`]. Everything drawn scrolls the
+    /// same frame, since the camera goes to the gpu right before every render
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.look_at(x, y);
+    }
+
+    /// Eases the camera toward `target`, for trailing the player instead of being locked onto them. Call it every
+    /// tick with [`crate::context::Context::delta_time`] as `dt`. Each call covers `1 - e^(-smoothing * dt)` of the
+    /// way left, so it's the same speed at any tick rate: higher `smoothing` catches up faster, and `0.0` doesn't move.
+    ///
+    /// With a [`Camera::dead_zone`], the target can move around the middle of the screen freely, and the camera
+    /// only follows once it leaves, just far enough to bring it back to the edge
+    pub fn follow(&mut self, target: (f32, f32), smoothing: f32, dt: f32) {
+        let (x, y) = self.position();
+        let axis = |position: f32, target: f32, dead_zone: f32| {
+            let offset = target - position;
+            if offset.abs() <= dead_zone {
+                0.0
+            } else {
+                offset - dead_zone.copysign(offset)
+            }
+        };
+        let t = 1.0 - (-smoothing.max(0.0) * dt.max(0.0)).exp();
+        let dx = axis(x, target.0, self.dead_zone.0);
+        let dy = axis(y, target.1, self.dead_zone.1);
+        if dx != 0.0 || dy != 0.0 {
+            self.move_by(dx * t, dy * t);
+        }
+    }
+
+    /// Scrolls the camera by `dx`, `dy` world pixels, without changing its zoom or angle. It's world pixels
+    /// whatever the zoom, so zoomed in the screen scrolls further for the same move.
+    ///
+    /// Moves the eye and target together. World coordinates go through `ortho(0, width, height, 0)` before the
+    /// camera sees them, hence the scaling & flipped y
+    pub fn move_by(&mut self, dx: f32, dy: f32) {
+        let offset = self.world_offset(dx, dy);
+        self.eye += offset;
+        self.target += offset;
+    }
+
+    /// How far the eye and target move for the camera to move `dx`, `dy` world pixels
+    fn world_offset(&self, dx: f32, dy: f32) -> cgmath::Vector3<f32> {
+        cgmath::vec3(dx * 2.0 / self.width, -dy * 2.0 / self.height, 0.0)
+    }
+
+    /// Shakes what's rendered by up to `intensity` world pixels each way, dying down to nothing over `duration`
+    /// seconds. Shakes add up, so a second one hitting during the first shakes harder.
+    ///
+    /// Only the rendered view shakes: [`Camera::position`], [`Camera::visible_bounds`], and everything else about
+    /// where the camera is stays put, so following and clamping aren't thrown off by it. Shakes run on frame time,
+    /// see [`Camera::update_shake`]. An `intensity` or `duration` of 0 or less does nothing
+    pub fn shake(&mut self, intensity: f32, duration: f32) {
+        if !(intensity > 0.0 && intensity.is_finite() && duration > 0.0 && duration.is_finite()) {
+            return;
+        }
+        self.shakes.push(Shake {
+            intensity,
+            duration,
+            remaining: duration,
+        });
+    }
+
+    /// Picks the offsets shakes go through, the same seed shaking the same way every time
+    pub fn set_shake_seed(&mut self, seed: u32) {
+        // Xorshift gets stuck at 0
+        self.shake_rng = if seed == 0 { SHAKE_SEED } else { seed };
+    }
+
+    /// Where the shakes currently have the rendered view, in world pixels from the camera's position
+    pub fn shake_offset(&self) -> (f32, f32) {
+        self.shake_offset
+    }
+
+    /// Moves the shakes on by `dt` seconds and picks a new offset for them. Called every frame with the frame's
+    /// delta right before the camera goes to the gpu
+    pub fn update_shake(&mut self, dt: f32) {
+        let dt = dt.max(0.0);
+        self.shakes.retain_mut(|shake| {
+            shake.remaining -= dt;
+            shake.remaining > 0.0
+        });
+        // Each shake fades out linearly over its duration
+        let strength: f32 = self
+            .shakes
+            .iter()
+            .map(|shake| shake.intensity * shake.remaining / shake.duration)
+            .sum();
+        self.shake_offset = if strength > 0.0 {
+            (
+                strength * self.next_shake_random(),
+                strength * self.next_shake_random(),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+    }
+
+    /// Next random number from -1 to 1 for the shake offset
+    fn next_shake_random(&mut self) -> f32 {
+        let mut x = self.shake_rng;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.shake_rng = x;
+        (x as f64 / u32::MAX as f64 * 2.0 - 1.0) as f32
+    }
+
+    /// Keeps what's on screen inside the box from `min` to `max` in the world, e.g. the level, by setting
+    /// [`Camera::bounds`]. The corners can be given in either order.
+    ///
+    /// Like [`super::State::set_camera_bounds`], the camera can still be moved anywhere during the tick, and is clamped
+    /// right before rendering, accounting for the zoom at that point. A level smaller than the screen is centered
+    pub fn set_bounds(&mut self, min: (f32, f32), max: (f32, f32)) {
+        let (left, right) = (min.0.min(max.0), min.0.max(max.0));
+        let (top, bottom) = (min.1.min(max.1), min.1.max(max.1));
+        self.bounds = Some(Rect::new(left, top, right - left, bottom - top));
+    }
+
+    /// Pans the camera so that what's on screen stays inside `bounds`, if there are any.
+    /// If the visible area is wider or taller than `bounds`, `bounds` gets centered on that axis instead
+    pub fn clamp_to_bounds(&mut self) {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let visible = self.visible_bounds();
+        let clamp_axis = |start: f32, len: f32, min: f32, max: f32| {
+            if len >= max - min {
+                // Center of the bounds, minus where the view currently is
+                (min + max) / 2.0 - (start + len / 2.0)
+            } else if start < min {
+                min - start
+            } else if start + len > max {
+                max - (start + len)
+            } else {
+                0.0
+            }
+        };
+        let dx = clamp_axis(visible.x, visible.width, bounds.x, bounds.right());
+        let dy = clamp_axis(visible.y, visible.height, bounds.y, bounds.bottom());
+        if dx != 0.0 || dy != 0.0 {
+            self.move_by(dx, dy);
+        }
+    }
+
+    /// Whether any part of `rect` is on screen. Useful for skipping draws of offscreen objects
+    pub fn is_visible(&self, rect: &Rect) -> bool {
+        self.visible_bounds().overlaps(rect)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Camera;
+    use crate::graphics::rect::Rect;
+
+    #[test]
+    fn projected_point_moves_opposite_to_pan() {
+        let mut camera = Camera::new(800.0, 600.0);
+        let viewport = Rect::new(0.0, 0.0, 800.0, 600.0);
+        let (x, y) = camera.project(100.0, 100.0, &viewport);
+        let (further_x, _) = camera.project(150.0, 100.0, &viewport);
+
+        let (cx, cy) = camera.center();
+        camera.look_at(cx + 50.0, cy);
+        let (panned_x, panned_y) = camera.project(100.0, 100.0, &viewport);
+        // Panning right by 50 world pixels slides the world left by however wide 50 world pixels are on screen
+        assert!((panned_x - (x - (further_x - x))).abs() < 0.01);
+        assert!((panned_y - y).abs() < 0.01);
+    }
+
+    #[test]
+    fn screen_to_world_undoes_world_to_screen() {
+        let mut camera = Camera::new(800.0, 600.0);
+        camera.look_at(1000.0, -250.0);
+        camera.set_zoom(1.7);
+        camera.eye.x += 0.2;
+        // Letterboxed into a window that's wider than the world
+        let viewport = Rect::new(100.0, 0.0, 1024.0, 768.0);
+
+        let corners = [(100.0, 0.0), (1124.0, 0.0), (100.0, 768.0), (1124.0, 768.0)];
+        for (x, y) in corners.into_iter().chain([(612.0, 384.0), (333.0, 700.0)]) {
+            let (wx, wy) = camera.screen_to_world(x, y, &viewport);
+            let (sx, sy) = camera.world_to_screen(wx, wy, &viewport);
+            assert!((sx - x).abs() < 0.01 && (sy - y).abs() < 0.01);
+        }
+
+        // Looking straight at the world again, the middle of the viewport is what the camera's centered on
+        camera.eye.x -= 0.2;
+        let (x, y) = camera.screen_to_world(612.0, 384.0, &viewport);
+        let (cx, cy) = camera.center();
+        assert!((x - cx).abs() < 0.1 && (y - cy).abs() < 0.1);
+        // And the top left corner is the top left of what's visible
+        let (x, y) = camera.screen_to_world(100.0, 0.0, &viewport);
+        let visible = camera.visible_bounds();
+        assert!((x - visible.x).abs() < 0.1 && (y - visible.y).abs() < 0.1);
+    }
+
+    #[test]
+    fn world_per_pixel_shrinks_when_zoomed_in() {
+        let mut camera = Camera::new(800.0, 600.0);
+        let viewport = Rect::new(0.0, 0.0, 1600.0, 1200.0);
+        // Facing straight at the world, pixels are spread evenly over what's visible
+        let unzoomed = camera.world_per_pixel(&viewport);
+        let visible = camera.visible_bounds();
+        assert!((unzoomed - visible.width / viewport.width).abs() < 1e-3);
+
+        camera.set_zoom(2.0);
+        assert!((camera.world_per_pixel(&viewport) - unzoomed / 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn moving_is_in_world_pixels_at_any_zoom() {
+        let mut camera = Camera::new(800.0, 600.0);
+        camera.set_position(100.0, 100.0);
+        let (x, y) = camera.position();
+        assert!((x - 100.0).abs() < 0.1 && (y - 100.0).abs() < 0.1);
+
+        for zoom in [0.5, 1.0, 3.0] {
+            camera.set_zoom(zoom);
+            let (x, y) = camera.position();
+            camera.move_by(40.0, -25.0);
+            let (moved_x, moved_y) = camera.position();
+            assert!((moved_x - x - 40.0).abs() < 0.1 && (moved_y - y + 25.0).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn follow_closes_in_on_the_target() {
+        let mut camera = Camera::new(800.0, 600.0);
+        camera.set_position(0.0, 0.0);
+        let target = (500.0, -200.0);
+        let distance = |camera: &Camera| {
+            let (x, y) = camera.position();
+            ((x - target.0).powi(2) + (y - target.1).powi(2)).sqrt()
+        };
+
+        let mut last = distance(&camera);
+        for _ in 0..300 {
+            camera.follow(target, 5.0, 1.0 / 60.0);
+            let now = distance(&camera);
+            // A little slack for the float error in working out the position
+            assert!(now <= last + 0.01);
+            last = now;
+        }
+        assert!(last < 0.5);
+
+        // Same distance covered in one second whether it's 30 or 120 ticks of it
+        let mut slow = Camera::new(800.0, 600.0);
+        let mut fast = Camera::new(800.0, 600.0);
+        (0..30).for_each(|_| slow.follow(target, 2.0, 1.0 / 30.0));
+        (0..120).for_each(|_| fast.follow(target, 2.0, 1.0 / 120.0));
+        assert!((distance(&slow) - distance(&fast)).abs() < 0.5);
+    }
+
+    #[test]
+    fn follow_without_smoothing_or_inside_the_dead_zone_stays_put() {
+        let mut camera = Camera::new(800.0, 600.0);
+        camera.set_position(0.0, 0.0);
+        let (x, y) = camera.position();
+        camera.follow((300.0, 300.0), 0.0, 1.0);
+        let (still_x, still_y) = camera.position();
+        assert!((still_x - x).abs() < 1e-3 && (still_y - y).abs() < 1e-3);
+
+        camera.dead_zone = (50.0, 30.0);
+        camera.follow((40.0, -20.0), 100.0, 1.0);
+        let (still_x, still_y) = camera.position();
+        assert!((still_x - x).abs() < 1e-3 && (still_y - y).abs() < 1e-3);
+
+        // Out past the right edge, it catches up until the target's back on the edge
+        camera.follow((80.0, 0.0), 100.0, 1.0);
+        let (moved_x, moved_y) = camera.position();
+        assert!((moved_x - 30.0).abs() < 0.1 && (moved_y - y).abs() < 1e-3);
+    }
+
+    #[test]
+    fn visible_bounds_follow_panned_and_zoomed_camera() {
+        let mut camera = Camera::new(800.0, 600.0);
+        // Pan right by a quarter of the screen, and zoom in
+        camera.eye.x += 0.5;
+        camera.target.x += 0.5;
+        camera.eye.z = 1.5;
+
+        let bounds = camera.visible_bounds();
+        // Camera is looking at 3/4 of the way across the world
+        assert!((bounds.x + bounds.width / 2.0 - 600.0).abs() < 0.1);
+        assert!((bounds.y + bounds.height / 2.0 - 300.0).abs() < 0.1);
+
+        let y = 300.0;
+        assert!(camera.is_visible(&Rect::new(bounds.x + 1.0, y, 0.0, 0.0)));
+        assert!(!camera.is_visible(&Rect::new(bounds.x - 1.0, y, 0.0, 0.0)));
+        assert!(camera.is_visible(&Rect::new(bounds.right() - 1.0, y, 0.0, 0.0)));
+        assert!(!camera.is_visible(&Rect::new(bounds.right() + 1.0, y, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn following_a_target_stops_at_the_bounds() {
+        let mut camera = Camera::new(800.0, 600.0);
+        camera.bounds = Some(Rect::new(0.0, 0.0, 2000.0, 1000.0));
+        // Zoom in so the view is smaller than the level
+        camera.eye.z = 1.2;
+
+        camera.look_at(1500.0, 500.0);
+        camera.clamp_to_bounds();
+        let (x, y) = camera.center();
+        assert!((x - 1500.0).abs() < 0.1 && (y - 500.0).abs() < 0.1);
+
+        // Target ran off past the right & bottom edge
+        camera.look_at(2600.0, 1400.0);
+        camera.clamp_to_bounds();
+        let visible = camera.visible_bounds();
+        assert!((visible.right() - 2000.0).abs() < 0.1);
+        assert!((visible.bottom() - 1000.0).abs() < 0.1);
+
+        camera.look_at(-300.0, -300.0);
+        camera.clamp_to_bounds();
+        let visible = camera.visible_bounds();
+        assert!(visible.x.abs() < 0.1 && visible.y.abs() < 0.1);
+    }
+
+    #[test]
+    fn bounds_hold_at_any_zoom() {
+        let mut camera = Camera::new(800.0, 600.0);
+        // Corners given backwards
+        camera.set_bounds((1600.0, 1200.0), (0.0, 0.0));
+        assert_eq!(camera.bounds, Some(Rect::new(0.0, 0.0, 1600.0, 1200.0)));
+
+        camera.set_zoom(2.0);
+        camera.look_at(-500.0, 1500.0);
+        camera.clamp_to_bounds();
+        let visible = camera.visible_bounds();
+        assert!(visible.x.abs() < 0.1 && (visible.bottom() - 1200.0).abs() < 0.1);
+
+        // Zoomed out so far the whole level fits with room to spare, so it's centered
+        camera.set_zoom(0.25);
+        camera.clamp_to_bounds();
+        let (x, y) = camera.center();
+        assert!((x - 800.0).abs() < 0.1 && (y - 600.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn world_smaller_than_view_is_centered() {
+        let mut camera = Camera::new(800.0, 600.0);
+        camera.bounds = Some(Rect::new(100.0, 100.0, 200.0, 100.0));
+
+        camera.look_at(1000.0, 1000.0);
+        camera.clamp_to_bounds();
+        let (x, y) = camera.center();
+        assert!((x - 200.0).abs() < 0.1 && (y - 150.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn zoom_shrinks_the_view_around_its_center() {
+        let mut camera = Camera::new(800.0, 600.0);
+        assert!((camera.zoom() - 1.0).abs() < 1e-5);
+        camera.look_at(300.0, 200.0);
+        let before = camera.visible_bounds();
+
+        camera.set_zoom(2.0);
+        assert!((camera.zoom() - 2.0).abs() < 1e-5);
+        let after = camera.visible_bounds();
+        assert!((after.width - before.width / 2.0).abs() < 0.1);
+        assert!((after.height - before.height / 2.0).abs() < 0.1);
+        let (x, y) = camera.center();
+        assert!((x - 300.0).abs() < 0.1 && (y - 200.0).abs() < 0.1);
+
+        for bad in [0.0, -1.0, f32::NAN] {
+            camera.set_zoom(bad);
+            assert!((camera.zoom() - 2.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn shake_moves_only_the_rendered_view_and_dies_down() {
+        let mut camera = Camera::new(800.0, 600.0);
+        camera.set_position(200.0, 100.0);
+        let (x, y) = camera.position();
+        let logical = camera.build_view_projection_matrix();
+
+        camera.shake(10.0, 0.5);
+        camera.update_shake(0.1);
+        let (ox, oy) = camera.shake_offset();
+        // 0.4 of the 0.5 seconds left, so at most 8 pixels
+        assert!(ox != 0.0 && ox.abs() <= 8.0 && oy.abs() <= 8.0);
+        assert_ne!(camera.build_render_matrix(), logical);
+        assert_eq!(camera.build_view_projection_matrix(), logical);
+        let (still_x, still_y) = camera.position();
+        assert!((still_x - x).abs() < 1e-3 && (still_y - y).abs() < 1e-3);
+
+        camera.update_shake(0.4);
+        assert_eq!(camera.shake_offset(), (0.0, 0.0));
+        assert_eq!(camera.build_render_matrix(), logical);
+    }
+
+    #[test]
+    fn shakes_stack_and_repeat_with_the_same_seed() {
+        let offsets = |shakes: &[(f32, f32)]| {
+            let mut camera = Camera::new(800.0, 600.0);
+            camera.set_shake_seed(7);
+            for &(intensity, duration) in shakes {
+                camera.shake(intensity, duration);
+            }
+            (0..10)
+                .map(|_| {
+                    camera.update_shake(1.0 / 60.0);
+                    camera.shake_offset()
+                })
+                .collect::<Vec<_>>()
+        };
+        let one = offsets(&[(10.0, 1.0)]);
+        assert_eq!(one, offsets(&[(10.0, 1.0)]));
+        // Two shakes of half the intensity add up to the same thing
+        for (a, b) in one.iter().zip(offsets(&[(5.0, 1.0), (5.0, 1.0)])) {
+            assert!((a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3);
+        }
+        // And stacked on top of it, the shake's stronger
+        let stacked = offsets(&[(10.0, 1.0), (10.0, 1.0)]);
+        assert!((stacked[0].0 - one[0].0 * 2.0).abs() < 1e-3);
+        assert!(offsets(&[(0.0, 1.0), (10.0, -1.0)])
+            .iter()
+            .all(|&offset| offset == (0.0, 0.0)));
+    }
+
+    #[test]
+    fn reset_undoes_pan_zoom_and_orbit() {
+        let mut camera = Camera::new(800.0, 600.0);
+        let initial = camera.build_view_projection_matrix();
+
+        camera.look_at(100.0, 50.0);
+        camera.eye.z = 1.0;
+        camera.eye.x += 0.3;
+        camera.fov = 60.0;
+        camera.shake(10.0, 1.0);
+        camera.update_shake(0.1);
+        assert_ne!(camera.build_view_projection_matrix(), initial);
+        assert_ne!(camera.shake_offset(), (0.0, 0.0));
+
+        camera.reset();
+        assert_eq!(camera.build_view_projection_matrix(), initial);
+        assert_eq!(camera.shake_offset(), (0.0, 0.0));
+        // And the shake doesn't come back on the next frame
+        camera.update_shake(0.1);
+        assert_eq!(camera.build_render_matrix(), initial);
+    }
 }
@@ -0,0 +1,121 @@
+/// Limits the engine asks the device for. Requesting a device with limits the adapter can't meet fails,
+/// so [`missing`] checks against these first
+pub(crate) fn required_limits() -> wgpu::Limits {
+    wgpu::Limits::default()
+}
+
+/// Everything `limits` and `format` (the surface format, and what the adapter can do with it) fall short of,
+/// described well enough to put in a bug report. Empty if the adapter has everything
+pub(crate) fn missing(
+    limits: &wgpu::Limits,
+    format: Option<(wgpu::TextureFormat, wgpu::TextureUsages)>,
+) -> Vec<String> {
+    let required = required_limits();
+    let mut missing = Vec::new();
+
+    // Maximums have to be at least as big, alignments at most as coarse
+    macro_rules! check {
+        ($($max:ident),* ; $($align:ident),*) => {
+            $(
+                if limits.$max < required.$max {
+                    missing.push(format!(
+                        "{} of {} (adapter has {})",
+                        stringify!($max),
+                        required.$max,
+                        limits.$max
+                    ));
+                }
+            )*
+            $(
+                if limits.$align > required.$align {
+                    missing.push(format!(
+                        "{} of {} (adapter has {})",
+                        stringify!($align),
+                        required.$align,
+                        limits.$align
+                    ));
+                }
+            )*
+        };
+    }
+    check!(
+        max_texture_dimension_1d,
+        max_texture_dimension_2d,
+        max_texture_dimension_3d,
+        max_texture_array_layers,
+        max_bind_groups,
+        max_dynamic_uniform_buffers_per_pipeline_layout,
+        max_dynamic_storage_buffers_per_pipeline_layout,
+        max_sampled_textures_per_shader_stage,
+        max_samplers_per_shader_stage,
+        max_storage_buffers_per_shader_stage,
+        max_storage_textures_per_shader_stage,
+        max_uniform_buffers_per_shader_stage,
+        max_uniform_buffer_binding_size,
+        max_storage_buffer_binding_size,
+        max_vertex_buffers,
+        max_vertex_attributes,
+        max_vertex_buffer_array_stride,
+        max_push_constant_size,
+        max_inter_stage_shader_components,
+        max_compute_workgroup_storage_size,
+        max_compute_invocations_per_workgroup,
+        max_compute_workgroup_size_x,
+        max_compute_workgroup_size_y,
+        max_compute_workgroup_size_z;
+        min_uniform_buffer_offset_alignment,
+        min_storage_buffer_offset_alignment
+    );
+
+    if let Some((format, usages)) = format {
+        if !usages.contains(wgpu::TextureUsages::RENDER_ATTACHMENT) {
+            missing.push(format!("rendering to the surface format {:?}", format));
+        }
+    }
+
+    missing
+}
+
+#[cfg(test)]
+mod test {
+    use super::{missing, required_limits};
+
+    #[test]
+    fn capable_adapter_is_missing_nothing() {
+        assert!(missing(
+            &required_limits(),
+            Some((
+                wgpu::TextureFormat::Bgra8UnormSrgb,
+                wgpu::TextureUsages::all()
+            ))
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn weak_adapter_lists_what_it_lacks() {
+        let limits = wgpu::Limits {
+            max_texture_dimension_2d: 1024,
+            max_bind_groups: 1,
+            min_uniform_buffer_offset_alignment: 1024,
+            ..required_limits()
+        };
+        let missing = missing(
+            &limits,
+            Some((
+                wgpu::TextureFormat::Rgba16Float,
+                wgpu::TextureUsages::TEXTURE_BINDING,
+            )),
+        );
+
+        assert_eq!(
+            missing,
+            [
+                "max_texture_dimension_2d of 8192 (adapter has 1024)",
+                "max_bind_groups of 4 (adapter has 1)",
+                "min_uniform_buffer_offset_alignment of 256 (adapter has 1024)",
+                "rendering to the surface format Rgba16Float",
+            ]
+        );
+    }
+}
@@ -1,3 +1,7 @@
+use std::sync::OnceLock;
+
+use crate::error::EngineError;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Color {
     pub r: u8,
@@ -7,60 +11,247 @@ pub struct Color {
 }
 
 impl Color {
-    pub fn from_rgb(r: u8, g: u8, b: u8, a: u8) -> Self {
+    pub const WHITE: Color = Color {
+        r: 255,
+        g: 255,
+        b: 255,
+        a: 255,
+    };
+    pub const BLACK: Color = Color {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+    pub const RED: Color = Color {
+        r: 255,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+    pub const GREEN: Color = Color {
+        r: 0,
+        g: 255,
+        b: 0,
+        a: 255,
+    };
+    pub const BLUE: Color = Color {
+        r: 0,
+        g: 0,
+        b: 255,
+        a: 255,
+    };
+    pub const YELLOW: Color = Color {
+        r: 255,
+        g: 255,
+        b: 0,
+        a: 255,
+    };
+    pub const TRANSPARENT: Color = Color {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0,
+    };
+
+    pub const fn from_rgb(r: u8, g: u8, b: u8, a: u8) -> Self {
         Color { r, g, b, a }
     }
 
-    fn is_valid(c: char) -> Option<u8> {
-        match c {
-            '0' => Some(0),
-            '1' => Some(1),
-            '2' => Some(2),
-            '3' => Some(3),
-            '4' => Some(4),
-            '5' => Some(5),
-            '6' => Some(6),
-            '7' => Some(7),
-            '8' => Some(8),
-            '9' => Some(9),
-            'A' => Some(10),
-            'B' => Some(11),
-            'C' => Some(12),
-            'D' => Some(13),
-            'E' => Some(14),
-            'F' => Some(15),
-            _ => None,
+    /// Parses `#RRGGBB`, `#RRGGBBAA`, or the `#RGB` shorthand where each digit is doubled (`#fa0` is `#ffaa00`).
+    /// Either case works, and the `#` can be left off. Anything else fails with [`EngineError::ColorParse`]
+    pub fn from_hex(hex: &str) -> Result<Self, EngineError> {
+        let invalid = || EngineError::ColorParse(hex.to_string());
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        let nibbles: Vec<u8> = digits
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?;
+        let channel = |i: usize| nibbles[i * 2] * 16 + nibbles[i * 2 + 1];
+
+        match nibbles.len() {
+            3 => Ok(Color {
+                r: nibbles[0] * 17,
+                g: nibbles[1] * 17,
+                b: nibbles[2] * 17,
+                a: 255,
+            }),
+            6 => Ok(Color {
+                r: channel(0),
+                g: channel(1),
+                b: channel(2),
+                a: 255,
+            }),
+            8 => Ok(Color {
+                r: channel(0),
+                g: channel(1),
+                b: channel(2),
+                a: channel(3),
+            }),
+            _ => Err(invalid()),
         }
     }
 
-    fn next_two(chars: &mut dyn Iterator<Item = char>) -> Result<u8, Box<dyn std::error::Error>> {
-        Ok(
-            Self::is_valid(chars.next().ok_or("invalid")?).ok_or("invalid character")? * 16
-                + Self::is_valid(chars.next().ok_or("invalid")?).ok_or("invalid character")?,
-        )
+    /// Uppercase `#RRGGBBAA`, which [`Color::from_hex`] parses back to the exact same color
+    pub fn to_hex(&self) -> String {
+        format!("{}{:02X}", self.to_hex_rgb(), self.a)
+    }
+
+    /// Uppercase `#RRGGBB`, leaving alpha out. Only round trips through [`Color::from_hex`] when alpha is 255
+    pub fn to_hex_rgb(&self) -> String {
+        format!("#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
     }
 
-    pub fn from_hex(hex: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let upper = &hex[1..].to_uppercase();
-        let mut chars = upper.chars();
+    /// Hue in degrees, wrapping so `360.0` and `-360.0` are both red. Saturation, lightness and alpha go from `0.0`
+    /// to `1.0` and are clamped to it. Lightness `0.5` is the most saturated, `0.0` is black and `1.0` is white
+    pub fn from_hsl(h: f32, s: f32, l: f32, a: f32) -> Self {
+        let (s, l) = (s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Self::from_hue(h, chroma, l - chroma / 2.0, a)
+    }
 
-        let r = Self::next_two(&mut chars)?;
-        let g = Self::next_two(&mut chars)?;
-        let b = Self::next_two(&mut chars)?;
-        let a = Self::next_two(&mut chars).unwrap_or(255);
+    /// Like [`Color::from_hsl`], but value `1.0` is the most saturated instead of lightness `0.5`
+    pub fn from_hsv(h: f32, s: f32, v: f32, a: f32) -> Self {
+        let (s, v) = (s.clamp(0.0, 1.0), v.clamp(0.0, 1.0));
+        let chroma = v * s;
+        Self::from_hue(h, chroma, v - chroma, a)
+    }
 
-        Ok(Color { r, g, b, a })
+    /// Shared by hsl and hsv, `chroma` is how far the biggest channel is from the smallest, and `min` is the smallest
+    fn from_hue(h: f32, chroma: f32, min: f32, a: f32) -> Self {
+        let sector = h.rem_euclid(360.0) / 60.0;
+        let middle = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, middle, 0.0),
+            1 => (middle, chroma, 0.0),
+            2 => (0.0, chroma, middle),
+            3 => (0.0, middle, chroma),
+            4 => (middle, 0.0, chroma),
+            _ => (chroma, 0.0, middle),
+        };
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color {
+            r: channel(r + min),
+            g: channel(g + min),
+            b: channel(b + min),
+            a: channel(a),
+        }
     }
 
+    /// Same color with its alpha set to `alpha`, from `0.0` (transparent) to `1.0` (opaque). Anything outside
+    /// that is clamped to it
     pub fn fade(mut self, alpha: f32) -> Self {
-        self.a = (alpha * 256.0).floor() as u8;
+        self.a = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
         self
     }
+
+    /// Converts straight to a `wgpu::Color` as `channel / 255`, without any gamma correction.
+    /// Only correct when the surface isn't sRGB, see [`crate::graphics::State::set_gamma_correction`]
+    pub fn to_wgpu_uncorrected(self) -> wgpu::Color {
+        wgpu::Color {
+            r: self.r as f64 / 255.0,
+            g: self.g as f64 / 255.0,
+            b: self.b as f64 / 255.0,
+            a: self.a as f64 / 255.0,
+        }
+    }
+
+    /// Channels as `channel / 255`, the `0.0..=1.0` floats vertices and shaders take, without any gamma correction.
+    /// Shapes and text all convert through this, so channels never end up in a vertex as raw `u8`s
+    pub fn to_f32_array(&self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a].map(|channel| channel as f32 / 255.0)
+    }
+
+    /// Applies `f` to each pair of channels, with channels as `0.0..=1.0`
+    fn blend(self, other: Color, f: impl Fn(f32, f32) -> f32) -> Self {
+        let channel = |a: u8, b: u8| {
+            (f(a as f32 / 255.0, b as f32 / 255.0) * 255.0)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+        Color {
+            r: channel(self.r, other.r),
+            g: channel(self.g, other.g),
+            b: channel(self.b, other.b),
+            a: channel(self.a, other.a),
+        }
+    }
+
+    /// Adds each channel together, capping at 255. Brightens, good for additive tinting.
+    /// This is all done on the cpu, and is unrelated to how the gpu blends shapes
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, other: Color) -> Self {
+        Color {
+            r: self.r.saturating_add(other.r),
+            g: self.g.saturating_add(other.g),
+            b: self.b.saturating_add(other.b),
+            a: self.a.saturating_add(other.a),
+        }
+    }
+
+    /// Multiplies each channel together (as `0.0..=1.0`). Always darkens, white leaves the color as is
+    pub fn multiply(self, other: Color) -> Self {
+        self.blend(other, |a, b| a * b)
+    }
+
+    /// Blends towards `other`, with `t` of `0.0` being `self` and `1.0` being `other`. Alpha is blended too.
+    /// Channels are blended as they're stored, in sRGB, so halfway between black and white comes out darker than it
+    /// looks like it should. See [`Color::lerp_linear`] for fades
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        self.blend(other, |a, b| a + (b - a) * t)
+    }
+
+    /// Like [`Color::lerp`], but blends in linear space with the same gamma curve colors are drawn with, then
+    /// converts back to sRGB. Looks even all the way through, so it's what fades and gradients want.
+    /// Alpha is already linear, so it's blended the same as in [`Color::lerp`]
+    pub fn lerp_linear(self, other: Color, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0) as f64;
+        let table = gamma_table();
+        let channel = |a: u8, b: u8| {
+            let (a, b) = (table[a as usize], table[b as usize]);
+            ((a + (b - a) * t).powf(1.0 / 2.2) * 255.0)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+        Color {
+            r: channel(self.r, other.r),
+            g: channel(self.g, other.g),
+            b: channel(self.b, other.b),
+            a: self.lerp(other, t as f32).a,
+        }
+    }
+
+    /// Inverse of multiplying the inverses. Always brightens, black leaves the color as is
+    pub fn screen(self, other: Color) -> Self {
+        self.blend(other, |a, b| 1.0 - (1.0 - a) * (1.0 - b))
+    }
+}
+
+/// A `wgpu::Color`, already converted from a [`Color`], as the `f32`s vertices and text take
+pub(crate) fn to_f32_array(color: wgpu::Color) -> [f32; 4] {
+    [color.r, color.g, color.b, color.a].map(|channel| channel as f32)
 }
 
+/// One 8 bit sRGB channel made linear
 #[inline]
 fn cv(n: f64) -> f64 {
-    (n / 256.0).powf(2.2)
+    (n / 255.0).powf(2.2)
+}
+
+/// [`cv`] precomputed for every possible channel value, since channels are only ever `u8`.
+/// Converting a color happens for every shape drawn, so a lookup is much cheaper than a `powf`
+fn gamma_table() -> &'static [f64; 256] {
+    static TABLE: OnceLock<[f64; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [0.0; 256];
+        table
+            .iter_mut()
+            .enumerate()
+            .for_each(|(n, v)| *v = cv(n as f64));
+        table
+    })
 }
 
 /// Converts color from srgb to wgpu color, but corrects for gamma.
@@ -68,20 +259,24 @@ fn cv(n: f64) -> f64 {
 /// modify the sRGB according to the gamma curve, with an exponent of ~ 2.2
 /// See [learnopengl/gamma-correction](https://learnopengl.com/Advanced-Lighting/Gamma-Correction) & [learnwgpu/colorcorrection](https://sotrh.github.io/learn-wgpu/beginner/tutorial4-buffer/#color-correction)
 /// for more information.
+///
+/// Alpha isn't a brightness, it's already linear, so it's only scaled to `0.0..=1.0`
 impl From<Color> for wgpu::Color {
     fn from(val: Color) -> Self {
+        let table = gamma_table();
         wgpu::Color {
-            r: cv(val.r as f64),
-            g: cv(val.g as f64),
-            b: cv(val.b as f64),
-            a: cv(val.a as f64),
+            r: table[val.r as usize],
+            g: table[val.g as usize],
+            b: table[val.b as usize],
+            a: val.a as f64 / 255.0,
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Color;
+    use super::{cv, gamma_table, Color};
+    use crate::error::EngineError;
     #[test]
     fn test_color_from_hex() {
         let color = Color::from_hex("292828").unwrap();
@@ -96,16 +291,194 @@ mod test {
         );
     }
     #[test]
+    fn test_color_from_hex_forms() {
+        let white = Color::from_rgb(255, 255, 255, 255);
+        assert_eq!(Color::from_hex("#fff").unwrap(), white);
+        assert_eq!(Color::from_hex("fff").unwrap(), white);
+        assert_eq!(
+            Color::from_hex("#Fa0").unwrap(),
+            Color::from_rgb(255, 170, 0, 255)
+        );
+        assert_eq!(
+            Color::from_hex("#29282880").unwrap(),
+            Color::from_rgb(41, 40, 40, 128)
+        );
+        assert_eq!(
+            Color::from_hex("#292828").unwrap(),
+            Color::from_rgb(41, 40, 40, 255)
+        );
+    }
+    #[test]
+    fn test_color_from_hex_rejects_bad_input() {
+        for bad in ["#ff", "", "#", "#12345", "#1234567", "#gggggg", "#ééé"] {
+            assert!(
+                matches!(Color::from_hex(bad), Err(EngineError::ColorParse(_))),
+                "{} should fail",
+                bad
+            );
+        }
+    }
+    #[test]
+    fn test_color_to_hex() {
+        assert_eq!(Color::from_rgb(41, 40, 171, 255).to_hex(), "#2928ABFF");
+        assert_eq!(Color::from_rgb(41, 40, 171, 255).to_hex_rgb(), "#2928AB");
+        assert_eq!(Color::TRANSPARENT.to_hex(), "#00000000");
+
+        // Any color survives the round trip, walked with a xorshift so it's the same colors every run
+        let mut state = 0x2545_f491u32;
+        for _ in 0..10_000 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let [r, g, b, a] = state.to_le_bytes();
+            let color = Color::from_rgb(r, g, b, a);
+            assert_eq!(Color::from_hex(&color.to_hex()).unwrap(), color);
+            assert_eq!(
+                Color::from_hex(&color.to_hex_rgb()).unwrap(),
+                color.fade(1.0)
+            );
+        }
+    }
+    #[test]
+    fn test_color_constants() {
+        const ORANGE: Color = Color::from_rgb(255, 165, 0, 255);
+        assert_eq!(Color::from_hex("#FFFF00").unwrap(), Color::YELLOW);
+        assert_eq!(Color::from_hex("#0000FF").unwrap(), Color::BLUE);
+        assert_eq!(Color::from_hex("#FFA500").unwrap(), ORANGE);
+        assert_eq!(Color::TRANSPARENT.a, 0);
+    }
+    #[test]
+    fn test_color_from_hsl_and_hsv() {
+        for (h, color) in [
+            (0.0, Color::RED),
+            (120.0, Color::GREEN),
+            (240.0, Color::BLUE),
+            (60.0, Color::YELLOW),
+        ] {
+            assert_eq!(Color::from_hsl(h, 1.0, 0.5, 1.0), color);
+            assert_eq!(Color::from_hsv(h, 1.0, 1.0, 1.0), color);
+        }
+        // Hue wraps
+        assert_eq!(Color::from_hsl(360.0, 1.0, 0.5, 1.0), Color::RED);
+        assert_eq!(Color::from_hsl(-240.0, 1.0, 0.5, 1.0), Color::GREEN);
+        assert_eq!(Color::from_hsv(600.0, 1.0, 1.0, 1.0), Color::BLUE);
+
+        assert_eq!(Color::from_hsl(200.0, 0.7, 1.0, 1.0), Color::WHITE);
+        assert_eq!(Color::from_hsv(200.0, 0.7, 0.0, 1.0), Color::BLACK);
+        assert_eq!(
+            Color::from_hsl(0.0, 0.0, 0.5, 0.5),
+            Color::from_rgb(128, 128, 128, 128)
+        );
+        assert_eq!(
+            Color::from_hsv(210.0, 0.5, 0.8, 1.0),
+            Color::from_rgb(102, 153, 204, 255)
+        );
+
+        // Out of range clamps
+        assert_eq!(Color::from_hsl(0.0, 5.0, 0.5, 3.0), Color::RED);
+        assert_eq!(Color::from_hsv(0.0, 1.0, -1.0, -1.0), Color::TRANSPARENT);
+        assert_eq!(Color::from_hsl(120.0, 1.0, 2.0, 1.0), Color::WHITE);
+    }
+    #[test]
     fn test_color_to_wgpu_color() {
-        let color = Color::from_hex("292828").unwrap();
+        let color = Color::from_rgb(41, 40, 40, 255);
         assert_eq!(
             wgpu::Color::from(color),
             wgpu::Color {
-                r: 41.0 / 256.0,
-                g: 40.0 / 256.0,
-                b: 40.0 / 256.0,
+                r: (41.0f64 / 255.0).powf(2.2),
+                g: (40.0f64 / 255.0).powf(2.2),
+                b: (40.0f64 / 255.0).powf(2.2),
                 a: 1.0
             }
         );
+        // White is exactly white, and alpha isn't curved
+        let white = wgpu::Color::from(Color::from_rgb(255, 255, 255, 51));
+        assert_eq!([white.r, white.g, white.b], [1.0, 1.0, 1.0]);
+        assert_eq!(white.a, 0.2);
+    }
+    #[test]
+    fn white_is_all_ones_as_floats() {
+        assert_eq!(Color::WHITE.to_f32_array(), [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(Color::BLACK.to_f32_array(), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(
+            Color::from_rgb(51, 102, 0, 255).to_f32_array(),
+            [0.2, 0.4, 0.0, 1.0]
+        );
+    }
+    #[test]
+    fn test_gamma_table_matches_formula() {
+        let table = gamma_table();
+        for n in 0..=255u8 {
+            assert_eq!(table[n as usize], cv(n as f64));
+        }
+    }
+    #[test]
+    fn test_color_add_saturates() {
+        let a = Color::from_rgb(200, 10, 0, 255);
+        let b = Color::from_rgb(100, 10, 0, 1);
+        assert_eq!(a.add(b), Color::from_rgb(255, 20, 0, 255));
+    }
+    #[test]
+    fn test_color_multiply() {
+        let white = Color::from_rgb(255, 255, 255, 255);
+        let black = Color::from_rgb(0, 0, 0, 255);
+        let c = Color::from_rgb(41, 128, 255, 255);
+        assert_eq!(c.multiply(white), c);
+        assert_eq!(c.multiply(black), black);
+        assert_eq!(
+            Color::from_rgb(255, 255, 255, 128).multiply(Color::from_rgb(128, 0, 255, 128)),
+            Color::from_rgb(128, 0, 255, 64)
+        );
+    }
+    #[test]
+    fn test_color_screen() {
+        let white = Color::from_rgb(255, 255, 255, 255);
+        let black = Color::from_rgb(0, 0, 0, 0);
+        let c = Color::from_rgb(41, 128, 255, 255);
+        assert_eq!(c.screen(black), c);
+        assert_eq!(c.screen(white), white);
+        assert_eq!(
+            Color::from_rgb(128, 0, 0, 0).screen(Color::from_rgb(128, 0, 0, 0)),
+            Color::from_rgb(192, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_color_lerp() {
+        let black = Color::from_rgb(0, 0, 0, 255);
+        let white = Color::from_rgb(255, 255, 255, 0);
+        assert_eq!(black.lerp(white, 0.0), black);
+        assert_eq!(black.lerp(white, 1.0), white);
+        assert_eq!(black.lerp(white, 0.5), Color::from_rgb(128, 128, 128, 128));
+        assert_eq!(black.lerp(white, 2.0), white);
+    }
+
+    #[test]
+    fn test_color_lerp_linear() {
+        let black = Color::from_rgb(0, 0, 0, 255);
+        let white = Color::from_rgb(255, 255, 255, 0);
+        assert_eq!(black.lerp_linear(white, 0.0), black);
+        assert_eq!(black.lerp_linear(white, 1.0), white);
+        // Half the light is brighter than half the sRGB value, alpha still goes halfway
+        assert_eq!(
+            black.lerp_linear(white, 0.5),
+            Color::from_rgb(186, 186, 186, 128)
+        );
+        assert_eq!(black.lerp_linear(white, -1.0), black);
+        // Every stored value converts back to itself
+        for n in 0..=255 {
+            let c = Color::from_rgb(n, n, n, n);
+            assert_eq!(c.lerp_linear(Color::WHITE, 0.0), c);
+        }
+    }
+
+    #[test]
+    fn test_color_fade() {
+        let clear = Color::from_rgb(255, 255, 255, 0);
+        assert_eq!(clear.fade(1.0).a, 255);
+        assert_eq!(clear.fade(0.5).a, 128);
+        assert_eq!(clear.fade(0.0).a, 0);
+        assert_eq!(clear.fade(3.0).a, 255);
+        assert_eq!(clear.fade(-1.0).a, 0);
     }
 }
@@ -0,0 +1,282 @@
+/// Brightness, contrast and saturation applied to the whole frame, see [`super::State::set_color_grade`]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ColorGrade {
+    /// Added to every channel, 0 leaves it alone
+    pub brightness: f32,
+    /// How far colors get pushed away from middle gray, 1 leaves it alone
+    pub contrast: f32,
+    /// How far colors get pushed away from their gray, 0 is black and white and 1 leaves it alone
+    pub saturation: f32,
+}
+
+impl ColorGrade {
+    pub const IDENTITY: ColorGrade = ColorGrade {
+        brightness: 0.0,
+        contrast: 1.0,
+        saturation: 1.0,
+    };
+
+    /// Whether it leaves every color as it was. Nothing extra gets drawn then
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+}
+
+impl Default for ColorGrade {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Draws the scene into an offscreen target, then grades it onto the frame. Only used while the grade isn't
+/// the identity, the target is made the first time it's needed and again whenever the window's size changes
+pub(crate) struct ColorGradePass {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    uniform_buffer: wgpu::Buffer,
+    target: Option<Target>,
+}
+
+struct Target {
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    size: winit::dpi::PhysicalSize<u32>,
+}
+
+impl ColorGradePass {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("Color Grade Bind Group Layout"),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Color Grade Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Color Grade Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("color_grade.wgsl").into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Color Grade Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                // Replaces the frame outright, the scene's already been blended in the target
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Color Grade Sampler"),
+            ..Default::default()
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Color Grade Uniform Buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            uniform_buffer,
+            target: None,
+        }
+    }
+
+    pub fn set(&self, queue: &wgpu::Queue, grade: ColorGrade) {
+        let uniform = [grade.brightness, grade.contrast, grade.saturation, 0.0];
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&uniform));
+    }
+
+    /// Makes the offscreen target the scene gets drawn into, again if the window changed size
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        if self.target.as_ref().map(|target| target.size) != Some(size) {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Color Grade Target"),
+                size: wgpu::Extent3d {
+                    width: size.width.max(1),
+                    height: size.height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: self.uniform_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("Color Grade Bind Group"),
+            });
+            self.target = Some(Target {
+                view,
+                bind_group,
+                size,
+            });
+        }
+    }
+
+    /// The target from [`ColorGradePass::prepare`]
+    pub fn view(&self) -> Option<&wgpu::TextureView> {
+        self.target.as_ref().map(|target| &target.view)
+    }
+
+    /// Grades what was drawn into the target onto `frame`
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, frame: &wgpu::TextureView) {
+        let target = match &self.target {
+            Some(target) => target,
+            None => return,
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Color Grade Pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: frame,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Lets go of the target while grading's off
+    pub fn release(&mut self) {
+        self.target = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ColorGrade, ColorGradePass};
+    use crate::graphics::headless::{Gpu, FORMAT};
+    use crate::graphics::rect::Rect;
+
+    #[test]
+    fn identity_is_the_default() {
+        assert!(ColorGrade::default().is_identity());
+        assert!(!ColorGrade {
+            saturation: 0.0,
+            ..ColorGrade::IDENTITY
+        }
+        .is_identity());
+    }
+
+    #[test]
+    fn no_saturation_is_grayscale() {
+        let gpu = match Gpu::new() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let size = winit::dpi::PhysicalSize::new(4, 4);
+        let mut pass = ColorGradePass::new(&gpu.device, FORMAT);
+        pass.set(
+            &gpu.queue,
+            ColorGrade {
+                saturation: 0.0,
+                ..ColorGrade::IDENTITY
+            },
+        );
+        pass.prepare(&gpu.device, FORMAT, size);
+
+        // The scene is one color, with channels that are whole bytes so they're stored exactly
+        let frame = gpu.target(size.width, size.height);
+        let frame_view = frame.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let scene = wgpu::Color {
+            r: 51.0 / 255.0,
+            g: 153.0 / 255.0,
+            b: 204.0 / 255.0,
+            a: 1.0,
+        };
+        gpu.clear_pass(&mut encoder, pass.view().unwrap(), scene);
+        pass.draw(&mut encoder, &frame_view);
+        gpu.queue.submit(Some(encoder.finish()));
+
+        let image = gpu.capture(
+            &frame,
+            size.width,
+            size.height,
+            Rect::new(0.0, 0.0, 4.0, 4.0),
+        );
+        // Every channel becomes the luma, 0.2 * 0.2126 + 0.6 * 0.7152 + 0.8 * 0.0722 of the way to white
+        for pixel in image.pixels() {
+            let [r, g, b, a] = pixel.0;
+            assert_eq!((r, g), (g, b), "{:?}", pixel.0);
+            assert!((r as i32 - 135).abs() <= 1, "{:?}", pixel.0);
+            assert_eq!(a, 255);
+        }
+    }
+}
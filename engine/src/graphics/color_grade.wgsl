@@ -0,0 +1,39 @@
+// Color grading, drawn as one triangle over the whole screen that samples the scene from the offscreen target
+struct Grade {
+    brightness: f32;
+    contrast: f32;
+    saturation: f32;
+};
+
+[[group(0), binding(0)]]
+var t_scene: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_scene: sampler;
+[[group(0), binding(2)]]
+var<uniform> grade: Grade;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] tex_coords: vec2<f32>;
+};
+
+// Vertices 0, 1, 2 make a triangle big enough to cover the screen, no vertex buffer needed
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coords = uv;
+    return out;
+}
+
+// Brightness, then contrast around middle gray, then saturation towards the luma
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let color = textureSample(t_scene, s_scene, in.tex_coords);
+    var rgb = color.rgb + vec3<f32>(grade.brightness);
+    rgb = (rgb - vec3<f32>(0.5)) * grade.contrast + vec3<f32>(0.5);
+    let luma = dot(rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    rgb = mix(vec3<f32>(luma), rgb, vec3<f32>(grade.saturation));
+    return vec4<f32>(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
@@ -0,0 +1,213 @@
+use std::any::TypeId;
+
+use super::buffers::VertexLayout;
+use super::render::RenderStats;
+
+/// Handle to a shader made with [`super::State::create_material`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialHandle(pub usize);
+
+/// Which pipeline a [`CustomDraw`] goes through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CustomPipeline {
+    /// The one registered for a vertex type with [`super::State::register_vertex_layout`]
+    Layout(TypeId),
+    Material(MaterialHandle),
+}
+
+/// A run of shapes that all go through the same pipeline, drawn in one draw call
+pub(crate) struct CustomDraw {
+    pub pipeline: CustomPipeline,
+    /// The vertices as they go into the vertex buffer
+    pub vertices: Vec<u8>,
+    /// Number of vertices in `vertices`
+    pub count: usize,
+    pub indices: Vec<u32>,
+}
+
+/// Shapes pushed with [`super::State::push_custom_shape`] and [`super::State::push_shape_with_material`] this frame,
+/// in order.
+/// Like [`super::batch::Batch`], it's plain bytes and doesn't touch the gpu
+#[derive(Default)]
+pub(crate) struct CustomShapes {
+    pub draws: Vec<CustomDraw>,
+}
+
+impl CustomShapes {
+    /// Shapes pushed one after another with the same vertex type get merged into the same draw call
+    pub fn push<V: VertexLayout + 'static>(&mut self, vertices: &[V], indices: &[u16]) {
+        self.push_to(CustomPipeline::Layout(TypeId::of::<V>()), vertices, indices);
+    }
+
+    /// Shapes pushed one after another through the same `pipeline` get merged into the same draw call
+    pub fn push_to<V: bytemuck::Pod>(
+        &mut self,
+        pipeline: CustomPipeline,
+        vertices: &[V],
+        indices: &[u16],
+    ) {
+        let draw = match self.draws.last_mut() {
+            Some(draw) if draw.pipeline == pipeline => draw,
+            _ => {
+                self.draws.push(CustomDraw {
+                    pipeline,
+                    vertices: Vec::new(),
+                    count: 0,
+                    indices: Vec::new(),
+                });
+                self.draws.last_mut().unwrap()
+            }
+        };
+        let offset = draw.count as u32;
+        draw.indices
+            .extend(indices.iter().map(|&i| u32::from(i) + offset));
+        draw.vertices
+            .extend_from_slice(bytemuck::cast_slice(vertices));
+        draw.count += vertices.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.draws.clear();
+    }
+
+    /// What these add to a frame's [`RenderStats`], one draw call per [`CustomDraw`]
+    pub fn stats(&self) -> RenderStats {
+        RenderStats {
+            draw_calls: self.draws.len(),
+            vertices: self.draws.iter().map(|draw| draw.count).sum(),
+            indices: self.draws.iter().map(|draw| draw.indices.len()).sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::TypeId;
+
+    use super::{CustomPipeline, CustomShapes, MaterialHandle};
+    use crate::graphics::buffers::{Vertex, VertexLayout};
+    use crate::graphics::render::RenderStats;
+
+    /// Position and a single brightness, for something like a heat map shader
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct HeatVertex {
+        position: [f32; 2],
+        heat: f32,
+    }
+
+    // Only f32s and no padding, which is all the derive would check
+    unsafe impl bytemuck::Zeroable for HeatVertex {}
+    unsafe impl bytemuck::Pod for HeatVertex {}
+
+    impl VertexLayout for HeatVertex {
+        fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+            wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<HeatVertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &[
+                    wgpu::VertexAttribute {
+                        offset: 0,
+                        shader_location: 0,
+                        format: wgpu::VertexFormat::Float32x2,
+                    },
+                    wgpu::VertexAttribute {
+                        offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                        shader_location: 1,
+                        format: wgpu::VertexFormat::Float32,
+                    },
+                ],
+            }
+        }
+    }
+
+    fn heat(x: f32, y: f32, heat: f32) -> HeatVertex {
+        HeatVertex {
+            position: [x, y],
+            heat,
+        }
+    }
+
+    #[test]
+    fn custom_vertices_batch_by_layout() {
+        let layout = HeatVertex::desc();
+        assert_eq!(layout.array_stride, 12);
+        assert_eq!(layout.attributes.len(), 2);
+
+        let mut shapes = CustomShapes::default();
+        let triangle = [
+            heat(0.0, 0.0, 0.0),
+            heat(1.0, 0.0, 0.5),
+            heat(0.0, 1.0, 1.0),
+        ];
+        shapes.push(&triangle, &[0, 1, 2]);
+        shapes.push(&triangle, &[0, 1, 2]);
+        assert_eq!(shapes.draws.len(), 1);
+        let draw = &shapes.draws[0];
+        assert_eq!(
+            draw.pipeline,
+            CustomPipeline::Layout(TypeId::of::<HeatVertex>())
+        );
+        assert_eq!(draw.count, 6);
+        // The second triangle's indices point past the first's vertices
+        assert_eq!(draw.indices, vec![0, 1, 2, 3, 4, 5]);
+        let uploaded: &[HeatVertex] = bytemuck::cast_slice(&draw.vertices);
+        assert_eq!(uploaded[4].position, [1.0, 0.0]);
+        assert_eq!(uploaded[5].heat, 1.0);
+
+        // A different layout needs a different pipeline, so it starts a new draw
+        let vertex = Vertex {
+            position: [0.0, 0.0, 0.0],
+            color: [1.0; 4],
+            tex_coords: [0.0, 0.0],
+        };
+        shapes.push(&[vertex; 3], &[0, 1, 2]);
+        assert_eq!(shapes.draws.len(), 2);
+        assert_eq!(shapes.draws[1].indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn each_material_is_its_own_draw() {
+        let vertex = Vertex {
+            position: [0.0, 0.0, 0.0],
+            color: [1.0; 4],
+            tex_coords: [0.0, 0.0],
+        };
+        let water = CustomPipeline::Material(MaterialHandle(0));
+        let lava = CustomPipeline::Material(MaterialHandle(1));
+
+        let mut shapes = CustomShapes::default();
+        shapes.push_to(water, &[vertex; 3], &[0, 1, 2]);
+        shapes.push_to(water, &[vertex; 3], &[0, 1, 2]);
+        shapes.push_to(lava, &[vertex; 3], &[0, 1, 2]);
+        // Same vertices as the built in shapes, but the vertex type alone doesn't pick the pipeline
+        shapes.push(&[vertex; 3], &[0, 1, 2]);
+        shapes.push_to(water, &[vertex; 3], &[0, 1, 2]);
+
+        let pipelines: Vec<CustomPipeline> =
+            shapes.draws.iter().map(|draw| draw.pipeline).collect();
+        assert_eq!(
+            pipelines,
+            [
+                water,
+                lava,
+                CustomPipeline::Layout(TypeId::of::<Vertex>()),
+                water
+            ]
+        );
+        assert_eq!(shapes.draws[0].indices, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(shapes.draws[3].indices, vec![0, 1, 2]);
+
+        // Every draw is a draw call of its own in the frame's stats
+        assert_eq!(
+            shapes.stats(),
+            RenderStats {
+                draw_calls: 4,
+                vertices: 15,
+                indices: 15,
+            }
+        );
+        shapes.clear();
+        assert_eq!(shapes.stats(), RenderStats::default());
+    }
+}
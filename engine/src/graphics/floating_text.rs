@@ -0,0 +1,123 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::color::Color;
+
+/// Most floating text alive at once, pushing more drops the oldest
+pub const MAX_FLOATING_TEXT: usize = 256;
+/// How far, in pixels, floating text rises over its lifetime
+const RISE: f32 = 32.0;
+const SCALE: f32 = 20.0;
+
+struct Entry {
+    text: String,
+    x: f32,
+    y: f32,
+    color: Color,
+    duration: Duration,
+    elapsed: Duration,
+}
+
+/// Text that floats up and fades out on its own, like damage numbers or toasts.
+/// Added with [`super::State::floating_text`], drawn every frame until it expires
+#[derive(Default)]
+pub struct FloatingTexts {
+    entries: VecDeque<Entry>,
+}
+
+impl FloatingTexts {
+    pub fn push(&mut self, text: &str, x: f32, y: f32, color: Color, duration: Duration) {
+        if self.entries.len() == MAX_FLOATING_TEXT {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry {
+            text: text.to_string(),
+            x,
+            y,
+            color,
+            duration,
+            elapsed: Duration::ZERO,
+        });
+    }
+
+    /// Ages every entry by `dt`, removing the ones past their duration
+    pub fn advance(&mut self, dt: Duration) {
+        for entry in &mut self.entries {
+            entry.elapsed += dt;
+        }
+        self.entries.retain(|entry| entry.elapsed < entry.duration);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Text, position, color and scale of each entry as it should be drawn right now
+    pub fn visible(&self) -> impl Iterator<Item = (&str, f32, f32, Color, f32)> {
+        self.entries.iter().map(|entry| {
+            let t = entry.elapsed.as_secs_f32() / entry.duration.as_secs_f32();
+            // Ease out, so it pops up quickly then settles
+            let rise = (1.0 - (1.0 - t) * (1.0 - t)) * RISE;
+            let transparent = Color {
+                a: 0,
+                ..entry.color
+            };
+            (
+                entry.text.as_str(),
+                entry.x,
+                entry.y - rise,
+                entry.color.lerp(transparent, t),
+                SCALE,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{FloatingTexts, MAX_FLOATING_TEXT};
+    use crate::graphics::color::Color;
+
+    #[test]
+    fn removed_after_duration() {
+        let red = Color::from_rgb(255, 0, 0, 255);
+        let mut texts = FloatingTexts::default();
+        texts.push("-10", 50.0, 50.0, red, Duration::from_millis(500));
+        texts.push("-3", 80.0, 50.0, red, Duration::from_millis(1000));
+
+        texts.advance(Duration::from_millis(250));
+        let (_, _, y, color, _) = texts.visible().next().unwrap();
+        assert!(y < 50.0);
+        assert!(color.a < 255);
+        assert_eq!(texts.len(), 2);
+
+        texts.advance(Duration::from_millis(250));
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts.visible().next().unwrap().0, "-3");
+
+        texts.advance(Duration::from_millis(500));
+        assert!(texts.is_empty());
+    }
+
+    #[test]
+    fn oldest_dropped_past_cap() {
+        let mut texts = FloatingTexts::default();
+        for i in 0..MAX_FLOATING_TEXT + 1 {
+            texts.push(
+                &i.to_string(),
+                0.0,
+                0.0,
+                Color::from_rgb(0, 0, 0, 255),
+                Duration::from_secs(1),
+            );
+        }
+        assert_eq!(texts.len(), MAX_FLOATING_TEXT);
+        assert_eq!(texts.visible().next().unwrap().0, "1");
+    }
+}
@@ -1,39 +1,270 @@
+use std::collections::HashSet;
+
+use super::color::{self, Color};
+#[cfg(feature = "hot-reload")]
+use super::hot_reload;
 use super::State;
+use crate::error::EngineError;
 
 use wgpu_glyph::{
-    ab_glyph::{self, FontArc},
-    GlyphBrush, GlyphBrushBuilder, Section, Text,
+    ab_glyph::{self, Font, FontArc, ScaleFont},
+    GlyphBrush, GlyphBrushBuilder, GlyphCruncher, HorizontalAlign, Layout, Section, Text,
+    VerticalAlign,
 };
 
+/// Handle to a font loaded with [`State::load_font`], for [`State::draw_text_with_font`]
+pub use wgpu_glyph::FontId;
+
+/// Vertical metrics of a font drawn at some scale, in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontMetrics {
+    /// How far the tallest glyphs go above the baseline
+    pub ascent: f32,
+    /// How far glyphs go below the baseline, negative
+    pub descent: f32,
+    /// Extra space the font wants between lines
+    pub line_gap: f32,
+}
+
+impl FontMetrics {
+    fn new(font: &FontArc, scale: f32) -> Self {
+        let font = font.as_scaled(scale);
+        Self {
+            ascent: font.ascent(),
+            descent: font.descent(),
+            line_gap: font.line_gap(),
+        }
+    }
+
+    /// Distance from one line's baseline to the next
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+}
+
+/// Whether text is drawn over or under shapes, see [`State::set_text_layer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextLayer {
+    /// Over every shape, screen space ui included. The default
+    #[default]
+    AboveShapes,
+    /// Under every shape, so a shape drawn where text is hides it
+    BelowShapes,
+}
+
+/// Which part of the text goes at `x`, see [`State::draw_text_aligned`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HAlign {
+    /// Text starts at `x`, like [`State::draw_text`]
+    #[default]
+    Left,
+    Center,
+    /// Text ends at `x`
+    Right,
+}
+
+/// Which part of the text goes at `y`, see [`State::draw_text_aligned`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VAlign {
+    /// The top of the line is at `y`, like [`State::draw_text`]
+    #[default]
+    Top,
+    Center,
+    /// The bottom of the line is at `y`
+    Bottom,
+}
+
+impl From<HAlign> for HorizontalAlign {
+    fn from(align: HAlign) -> Self {
+        match align {
+            HAlign::Left => HorizontalAlign::Left,
+            HAlign::Center => HorizontalAlign::Center,
+            HAlign::Right => HorizontalAlign::Right,
+        }
+    }
+}
+
+impl From<VAlign> for VerticalAlign {
+    fn from(align: VAlign) -> Self {
+        match align {
+            VAlign::Top => VerticalAlign::Top,
+            VAlign::Center => VerticalAlign::Center,
+            VAlign::Bottom => VerticalAlign::Bottom,
+        }
+    }
+}
+
+impl TextLayer {
+    /// Whether text gets drawn before shapes. Whatever's drawn last ends up on top
+    pub(crate) fn text_first(self) -> bool {
+        self == TextLayer::BelowShapes
+    }
+}
+
+/// The first font added is the one text gets drawn with by default.
+///
+/// The embedded visitor font is only decoded when text is first queued without any font loaded,
+/// so games that [`State::load_font`] before drawing any text get their font as the default instead.
+/// Decoding takes well under a microsecond though, and the bytes are baked into the binary either way,
+/// so this mostly matters for which font ends up as the default.
+///
+/// Turning off the `embedded-font` feature leaves the visitor font out of the binary altogether.
+/// A font then has to be loaded before any text shows up, until then text is skipped (with a warning logged)
 pub struct FontInterface {
     staging_belt: wgpu::util::StagingBelt,
     glyph_brush: GlyphBrush<()>,
+    /// What text is drawn with when no font's given, the first font added
+    default_font: FontId,
+    /// Whether skipping text for lack of a font has been logged, so it's only logged once
+    #[cfg(not(feature = "embedded-font"))]
+    warned: bool,
+    /// Fonts text was drawn with that were never loaded, so each one is only warned about once
+    missing_fonts: HashSet<FontId>,
 }
 
 impl FontInterface {
     pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
-        // Default font, let's use visitor
-        let visitor = ab_glyph::FontArc::try_from_slice(include_bytes!(
-            "..\\..\\resources\\visitor2.ttf"
-        ))
-        .unwrap();
-        let glyph_brush = GlyphBrushBuilder::using_font(visitor).build(device, format);
+        let glyph_brush =
+            GlyphBrushBuilder::using_fonts(Vec::<FontArc>::new()).build(device, format);
         let staging_belt = wgpu::util::StagingBelt::new(1024);
 
         Self {
             glyph_brush,
             staging_belt,
+            default_font: FontId(0),
+            #[cfg(not(feature = "embedded-font"))]
+            warned: false,
+            missing_fonts: HashSet::new(),
         }
     }
-    pub fn add_font(&mut self, font: FontArc) {
-        self.glyph_brush.add_font(font);
+    /// Returns the `FontId` to draw text with it, which is also its index
+    pub fn add_font(&mut self, font: FontArc) -> FontId {
+        self.glyph_brush.add_font(font)
+    }
+
+    pub fn default_font(&self) -> FontId {
+        self.default_font
+    }
+
+    /// `font` if it's been added, otherwise the default font, with a warning the first time. The glyph brush
+    /// panics on fonts it doesn't have
+    fn font_or_default(&mut self, font: FontId) -> FontId {
+        if font.0 < self.glyph_brush.fonts().len() {
+            font
+        } else {
+            if self.missing_fonts.insert(font) {
+                log::warn!("No font {:?} loaded, drawing with the default font", font);
+            }
+            self.default_font
+        }
+    }
+    /// Swaps out the font at `index`, keeping its `FontId`. The glyph brush can't replace fonts, so it's
+    /// rebuilt, throwing away the glyphs cached so far
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn replace_font(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        index: usize,
+        font: FontArc,
+    ) {
+        let mut fonts = self.glyph_brush.fonts().to_vec();
+        fonts[index] = font;
+        self.glyph_brush = GlyphBrushBuilder::using_fonts(fonts).build(device, format);
+    }
+    /// Falls back to the embedded font if nothing has been loaded yet
+    #[cfg(feature = "embedded-font")]
+    fn ensure_font(&mut self) -> bool {
+        if self.glyph_brush.fonts().is_empty() {
+            // Default font, let's use visitor
+            let visitor =
+                ab_glyph::FontArc::try_from_slice(include_bytes!("../../resources/visitor2.ttf"))
+                    .unwrap();
+            self.glyph_brush.add_font(visitor);
+        }
+        true
+    }
+    /// Whether there's a font to draw with, there's nothing to fall back to without the embedded font
+    #[cfg(not(feature = "embedded-font"))]
+    fn ensure_font(&mut self) -> bool {
+        let loaded = !self.glyph_brush.fonts().is_empty();
+        if !loaded && !self.warned {
+            log::warn!("No font loaded, skipping text. Load one with `State::load_font` first");
+            self.warned = true;
+        }
+        loaded
     }
     pub fn finish(&mut self) {
         self.staging_belt.finish()
     }
     #[inline]
     pub fn queue(&mut self, section: Section) {
-        self.glyph_brush.queue(section)
+        if self.ensure_font() {
+            self.glyph_brush.queue(section)
+        }
+    }
+
+    /// Metrics of the default font at `scale`, all zero if there's no font
+    pub fn metrics(&mut self, scale: f32) -> FontMetrics {
+        if !self.ensure_font() {
+            return FontMetrics {
+                ascent: 0.0,
+                descent: 0.0,
+                line_gap: 0.0,
+            };
+        }
+        FontMetrics::new(&self.glyph_brush.fonts()[self.default_font.0], scale)
+    }
+
+    /// Queues a single line of text at `(x, y)` on screen
+    pub fn queue_text(&mut self, text: &str, x: f32, y: f32, color: wgpu::Color, scale: f32) {
+        self.queue_text_with_font(self.default_font, text, x, y, color, scale);
+    }
+
+    /// [`FontInterface::queue_text`] in `font` instead of the default font
+    pub fn queue_text_with_font(
+        &mut self,
+        font: FontId,
+        text: &str,
+        x: f32,
+        y: f32,
+        color: wgpu::Color,
+        scale: f32,
+    ) {
+        if self.ensure_font() {
+            let font = self.font_or_default(font);
+            self.glyph_brush
+                .queue(text_section(text, font, x, y, color, scale));
+        }
+    }
+
+    /// Queues `text` at `(x, y)` on screen wrapped to `max_width`, returning how tall it ends up in pixels
+    pub fn queue_text_wrapped(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        max_width: f32,
+        color: wgpu::Color,
+        scale: f32,
+    ) -> f32 {
+        if text.is_empty() || !self.ensure_font() {
+            return 0.0;
+        }
+        let section = wrapped_section(text, self.default_font, x, y, max_width, color, scale);
+        let (_, height) = size_of(self.glyph_brush.glyph_bounds(&section));
+        self.glyph_brush.queue(section);
+        height
+    }
+
+    /// Width and height in pixels `text` takes up when queued with [`FontInterface::queue_text`] at `scale`, laid
+    /// out the same way with the same font. Zero if there's nothing to draw or no font to draw it with
+    pub fn measure(&mut self, text: &str, scale: f32) -> (f32, f32) {
+        if text.is_empty() || !self.ensure_font() {
+            return (0.0, 0.0);
+        }
+        let section = text_section(text, self.default_font, 0.0, 0.0, wgpu::Color::WHITE, scale);
+        size_of(self.glyph_brush.glyph_bounds(section))
     }
 
     pub fn draw(
@@ -57,26 +288,306 @@ impl FontInterface {
 }
 
 impl State {
-    pub fn load_font(&mut self, path: &str) -> Result<(), std::io::Error> {
+    /// Loads a font from a file, returning the id to draw with it through [`State::draw_text_with_font`]. If no text
+    /// has been drawn yet and this is the first font loaded, it replaces the embedded font as the default. Without the
+    /// `embedded-font` feature, text needs this first
+    pub fn load_font(&mut self, path: &str) -> Result<FontId, EngineError> {
         let buffer = std::fs::read(path)?;
-        let font = ab_glyph::FontArc::try_from_vec(buffer).unwrap();
-        self.font_interface.add_font(font);
+        let font = ab_glyph::FontArc::try_from_vec(buffer)?;
+        let id = self.font_interface.add_font(font);
+        #[cfg(feature = "hot-reload")]
+        self.hot_reload
+            .track(std::path::Path::new(path), hot_reload::Asset::Font(id.0))?;
 
-        Ok(())
+        Ok(id)
     }
+
+    /// Font text is drawn with by [`State::draw_text`] and the rest, the first one loaded (or the embedded font if
+    /// text was drawn before any were loaded)
+    pub fn default_font(&self) -> FontId {
+        self.font_interface.default_font()
+    }
+    /// Draws `text` with `(x, y)` as the top left corner of the line, in window pixels. It stays put when the camera
+    /// moves, like a hud, see [`State::draw_text_world`] for text in the world. The top of the line is the font's ascent
+    /// above the baseline, so most glyphs start a little below `y`. See [`State::draw_text_baseline`] for lining
+    /// text up with other things.
+    ///
+    /// `color` is gamma corrected the same way shapes' colors are, so text comes out the same color as a shape
+    /// drawn with the same `Color`
     #[inline]
-    pub fn draw_text(&mut self, text: &str, x: f32, y: f32, color: wgpu::Color, scale: f32) {
-        self.font_interface.queue(Section {
-            screen_position: (x, y),
-            text: vec![Text::new(text)
-                .with_color([
-                    color.r as f32,
-                    color.g as f32,
-                    color.b as f32,
-                    color.a as f32,
-                ])
-                .with_scale(scale)],
-            ..Section::default()
-        });
+    pub fn draw_text(&mut self, text: &str, x: f32, y: f32, color: Color, scale: f32) {
+        let color = self.batch.wgpu_color(color);
+        self.font_interface.queue_text(text, x, y, color, scale);
+    }
+
+    /// Draws `text` with `(x, y)` as the top left corner like [`State::draw_text`], but breaking lines between words
+    /// wherever they'd go past `max_width` pixels, as well as at every `\n`. A word that's wider than `max_width` on its
+    /// own gets a line to itself, so a `max_width` of 0 puts every word on its own line.
+    ///
+    /// Returns how tall the text ends up in pixels, for laying out whatever goes under it
+    pub fn draw_text_wrapped(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        max_width: f32,
+        color: Color,
+        scale: f32,
+    ) -> f32 {
+        let color = self.batch.wgpu_color(color);
+        self.font_interface
+            .queue_text_wrapped(text, x, y, max_width, color, scale)
+    }
+
+    /// [`State::draw_text`] in `font`, from [`State::load_font`], instead of the default font. For mixing e.g. a title
+    /// font and a body font on the same screen. A font that was never loaded falls back to the default with a warning
+    pub fn draw_text_with_font(
+        &mut self,
+        font: FontId,
+        text: &str,
+        x: f32,
+        y: f32,
+        color: Color,
+        scale: f32,
+    ) {
+        let color = self.batch.wgpu_color(color);
+        self.font_interface
+            .queue_text_with_font(font, text, x, y, color, scale);
+    }
+
+    /// [`State::draw_text`] with a `wgpu::Color` that goes to the glyph brush as is, without any gamma correction
+    #[inline]
+    pub fn draw_text_raw(&mut self, text: &str, x: f32, y: f32, color: wgpu::Color, scale: f32) {
+        self.font_interface.queue_text(text, x, y, color, scale);
+    }
+
+    /// Draws `text` around `(x, y)` in window pixels, with `h_align` and `v_align` picking which part of it goes
+    /// there: [`HAlign::Center`] and [`VAlign::Center`] center it on the point, e.g. for a score at the top middle
+    /// of the screen, and [`HAlign::Right`] anchors it by its right edge. `Left` and `Top` are the same as
+    /// [`State::draw_text`]
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text_aligned(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        color: Color,
+        scale: f32,
+        h_align: HAlign,
+        v_align: VAlign,
+    ) {
+        let color = self.batch.wgpu_color(color);
+        let mut section = text_section(text, self.default_font(), x, y, color, scale);
+        section.layout = aligned_layout(h_align, v_align);
+        self.font_interface.queue(section);
+    }
+
+    /// Draws `text` with `(x, y)` in the world as the top left corner, so it moves with the camera like shapes do.
+    /// For labels over things in the world. Only where the text is goes through the camera, so it stays the same
+    /// size and upright however the camera's zoomed or rotated
+    pub fn draw_text_world(&mut self, text: &str, x: f32, y: f32, color: Color, scale: f32) {
+        let (x, y) = self.camera.project(x, y, &self.viewport());
+        let color = self.batch.wgpu_color(color);
+        self.font_interface.queue_text(text, x, y, color, scale);
+    }
+
+    /// Draws `text` sitting on the baseline at `baseline_y`, like text on ruled paper. Letters without
+    /// descenders end exactly at `baseline_y`, so it's the easy way to line a label up with the bottom of a box
+    pub fn draw_text_baseline(
+        &mut self,
+        text: &str,
+        x: f32,
+        baseline_y: f32,
+        color: Color,
+        scale: f32,
+    ) {
+        let color = self.batch.wgpu_color(color);
+        let metrics = self.font_interface.metrics(scale);
+        let y = top_from_baseline(baseline_y, &metrics);
+        self.font_interface.queue_text(text, x, y, color, scale);
+    }
+
+    /// Width and height in pixels `text` takes up when drawn with [`State::draw_text`] at `scale`, for centering or right
+    /// aligning it, or sizing a box around it. The height spans the glyphs' ascent to descent, not the whole line
+    pub fn measure_text(&mut self, text: &str, scale: f32) -> (f32, f32) {
+        self.font_interface.measure(text, scale)
+    }
+
+    /// Ascent, descent, and line gap of the default font at `scale`
+    pub fn font_metrics(&mut self, scale: f32) -> FontMetrics {
+        self.font_interface.metrics(scale)
+    }
+
+    /// Changes whether text goes over or under shapes. Text is always drawn in its own pass, all of it at once,
+    /// so it can't be layered in between shapes, only above all of them (the default) or below all of them.
+    /// With multisampling on (see [`crate::ContextBuilder::with_sample_count`]) text always goes over shapes, since
+    /// resolving the shapes covers whatever was drawn before them
+    pub fn set_text_layer(&mut self, layer: TextLayer) {
+        self.text_layer = layer;
+    }
+
+    pub fn text_layer(&self) -> TextLayer {
+        self.text_layer
+    }
+}
+
+/// A single line of `text` in `font`, the way all text gets queued
+fn text_section(
+    text: &str,
+    font: FontId,
+    x: f32,
+    y: f32,
+    color: wgpu::Color,
+    scale: f32,
+) -> Section<'_> {
+    Section {
+        screen_position: (x, y),
+        text: vec![Text::new(text)
+            .with_color(color::to_f32_array(color))
+            .with_scale(scale)
+            .with_font_id(font)],
+        ..Section::default()
+    }
+}
+
+/// [`text_section`] bounded to `max_width` across, which the default layout wraps words at. Nothing is clipped,
+/// it only goes on as many lines as it needs
+fn wrapped_section(
+    text: &str,
+    font: FontId,
+    x: f32,
+    y: f32,
+    max_width: f32,
+    color: wgpu::Color,
+    scale: f32,
+) -> Section<'_> {
+    text_section(text, font, x, y, color, scale).with_bounds((max_width.max(0.0), f32::INFINITY))
+}
+
+/// Layout of a single line of text, like [`text_section`]'s, anchored by `h_align` and `v_align` instead of
+/// the top left
+fn aligned_layout(h_align: HAlign, v_align: VAlign) -> Layout<wgpu_glyph::BuiltInLineBreaker> {
+    Layout::default()
+        .h_align(h_align.into())
+        .v_align(v_align.into())
+}
+
+/// Width and height of laid out text's `bounds`, no bounds being nothing to draw
+fn size_of(bounds: Option<ab_glyph::Rect>) -> (f32, f32) {
+    bounds.map_or((0.0, 0.0), |bounds| (bounds.width(), bounds.height()))
+}
+
+/// Text is positioned by the top of its line, which is `ascent` above the baseline
+fn top_from_baseline(baseline_y: f32, metrics: &FontMetrics) -> f32 {
+    baseline_y - metrics.ascent
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        aligned_layout, size_of, text_section, top_from_baseline, wrapped_section, FontId,
+        FontMetrics, HAlign, TextLayer, VAlign,
+    };
+    use wgpu_glyph::ab_glyph::{point, FontArc, Rect};
+    use wgpu_glyph::{
+        GlyphPositioner, HorizontalAlign, Layout, Section, SectionGeometry, VerticalAlign,
+    };
+
+    #[test]
+    fn baseline_offsets_by_ascent() {
+        let font = FontArc::try_from_slice(include_bytes!("../../resources/visitor2.ttf")).unwrap();
+        let metrics = FontMetrics::new(&font, 20.0);
+        assert!(metrics.ascent > 0.0);
+        assert!(metrics.descent <= 0.0);
+
+        assert_eq!(top_from_baseline(100.0, &metrics), 100.0 - metrics.ascent);
+        // Twice the scale, twice the ascent
+        let doubled = FontMetrics::new(&font, 40.0);
+        assert!((doubled.ascent - metrics.ascent * 2.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn shapes_only_cover_text_below_them() {
+        assert_eq!(TextLayer::default(), TextLayer::AboveShapes);
+        // Text drawn after shapes wins where they overlap
+        assert!(!TextLayer::AboveShapes.text_first());
+        assert!(TextLayer::BelowShapes.text_first());
+    }
+
+    #[test]
+    fn empty_text_measures_nothing() {
+        assert_eq!(size_of(None), (0.0, 0.0));
+        let bounds = Rect {
+            min: point(10.0, 5.0),
+            max: point(52.0, 25.0),
+        };
+        assert_eq!(size_of(Some(bounds)), (42.0, 20.0));
+
+        // Measured and drawn text are laid out from the same section
+        let section = text_section("", FontId(0), 0.0, 0.0, wgpu::Color::WHITE, 20.0);
+        assert_eq!(section.text.len(), 1);
+        assert_eq!(section.text[0].scale.y, 20.0);
+
+        let titled = text_section("", FontId(1), 0.0, 0.0, wgpu::Color::WHITE, 20.0);
+        assert_eq!(titled.text[0].font_id, FontId(1));
+    }
+
+    #[test]
+    fn aligned_text_is_anchored_where_asked() {
+        // Left & top is what plain text already does
+        let plain = text_section("", FontId(0), 0.0, 0.0, wgpu::Color::WHITE, 20.0);
+        assert_eq!(
+            aligned_layout(HAlign::default(), VAlign::default()),
+            plain.layout
+        );
+
+        let centered = aligned_layout(HAlign::Center, VAlign::Center);
+        assert_eq!(
+            centered,
+            Layout::default()
+                .h_align(HorizontalAlign::Center)
+                .v_align(VerticalAlign::Center)
+        );
+        let corner = aligned_layout(HAlign::Right, VAlign::Bottom);
+        assert_eq!(
+            corner,
+            Layout::default()
+                .h_align(HorizontalAlign::Right)
+                .v_align(VerticalAlign::Bottom)
+        );
+    }
+
+    #[test]
+    fn wrapped_text_breaks_at_newlines_and_the_width() {
+        let fonts =
+            [FontArc::try_from_slice(include_bytes!("../../resources/visitor2.ttf")).unwrap()];
+        let layout = |section: Section| {
+            section
+                .layout
+                .calculate_glyphs(&fonts, &SectionGeometry::from(&section), &section.text)
+        };
+        // How many lines the text gets laid out on, by where each one starts
+        let lines = |text: &str, max_width: f32| {
+            let section = wrapped_section(
+                text,
+                FontId(0),
+                0.0,
+                0.0,
+                max_width,
+                wgpu::Color::WHITE,
+                20.0,
+            );
+            let mut tops: Vec<f32> = layout(section).iter().map(|g| g.glyph.position.y).collect();
+            tops.dedup();
+            tops.len()
+        };
+        assert_eq!(lines("one two three", f32::INFINITY), 1);
+        assert_eq!(lines("one\ntwo\nthree", f32::INFINITY), 3);
+        assert_eq!(lines("one two three", 0.0), 3);
+
+        // Wide enough for "one two" but not the rest
+        let unwrapped = text_section("one two", FontId(0), 0.0, 0.0, wgpu::Color::WHITE, 20.0);
+        let last = layout(unwrapped).last().unwrap().glyph.position.x;
+        assert_eq!(lines("one two three", last + 20.0), 2);
     }
 }
@@ -0,0 +1,115 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use futures::FutureExt;
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+/// Times how long the gpu takes to render a frame, with timestamp queries written at the start
+/// and end of the frame's commands. Only exists if the adapter supports [`wgpu::Features::TIMESTAMP_QUERY`].
+///
+/// Reading the timestamps back is asynchronous, so that the cpu never waits on the gpu. The time is from a frame
+/// or two ago, and frames rendered while the last readback is still in flight aren't timed
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    /// Both timestamps get resolved straight into here, then mapped for reading
+    buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+    pending: Option<MapFuture>,
+    /// Whether timestamps were written this frame, so they need to be read back after submitting
+    writing: bool,
+    last: Option<f32>,
+}
+
+impl GpuTimer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Gpu Timer Queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Gpu Timer Buffer"),
+            size: 2 * std::mem::size_of::<u64>() as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            query_set,
+            buffer,
+            period: queue.get_timestamp_period(),
+            pending: None,
+            writing: false,
+            last: None,
+        })
+    }
+
+    /// Call before recording the frame's commands. Picks up the last readback if it's done
+    pub fn begin(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(pending) = self.pending.as_mut() {
+            device.poll(wgpu::Maintain::Poll);
+            match pending.now_or_never() {
+                Some(Ok(())) => {
+                    {
+                        let data = self.buffer.slice(..).get_mapped_range();
+                        let timestamps: &[u64] = bytemuck::cast_slice(&data);
+                        self.last = elapsed_ms(timestamps[0], timestamps[1], self.period);
+                    }
+                    self.buffer.unmap();
+                    self.pending = None;
+                }
+                Some(Err(_)) => self.pending = None,
+                // Still on its way, the buffer's busy so this frame can't be timed
+                None => return,
+            }
+        }
+        encoder.write_timestamp(&self.query_set, 0);
+        self.writing = true;
+    }
+
+    /// Call after the frame's last command, before finishing the encoder
+    pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.writing {
+            encoder.write_timestamp(&self.query_set, 1);
+            encoder.resolve_query_set(&self.query_set, 0..2, &self.buffer, 0);
+        }
+    }
+
+    /// Call after submitting, starts reading the timestamps back
+    pub fn submitted(&mut self) {
+        if self.writing {
+            self.pending = Some(Box::pin(
+                self.buffer.slice(..).map_async(wgpu::MapMode::Read),
+            ));
+            self.writing = false;
+        }
+    }
+
+    /// Milliseconds the gpu spent on the latest frame that finished being timed
+    pub fn frame_time(&self) -> Option<f32> {
+        self.last
+    }
+}
+
+/// Milliseconds between two timestamps. `None` if they're out of order, which some drivers do
+/// when the gpu changes power state in the middle of a frame
+fn elapsed_ms(start: u64, end: u64, period: f32) -> Option<f32> {
+    let ticks = end.checked_sub(start)?;
+    Some(ticks as f32 * period / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::elapsed_ms;
+
+    #[test]
+    fn timestamps_to_milliseconds() {
+        assert_eq!(elapsed_ms(1_000, 2_001_000, 1.0), Some(2.0));
+        assert_eq!(elapsed_ms(0, 1_000_000, 2.5), Some(2.5));
+        assert_eq!(elapsed_ms(5, 4, 1.0), None);
+    }
+}
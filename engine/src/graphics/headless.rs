@@ -0,0 +1,174 @@
+//! Rendering without a window, for tests that have to run the real shaders and pipelines instead of trusting a copy
+//! of their math. Plenty of CI machines have no adapter at all, not even a software one, so every test asks for a
+//! [`Gpu`] first and passes without checking anything when there isn't one
+
+use image::RgbaImage;
+use wgpu::util::DeviceExt;
+
+use super::buffers::{Uniforms, Vertex};
+use super::readback::Readback;
+use super::rect::Rect;
+
+/// Format every target is made in. Not sRGB, so what the shaders write comes back byte for byte
+pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+pub struct Gpu {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+}
+
+impl Gpu {
+    /// Any adapter that'll do, falling back to a software one, the same as the benches ask for
+    pub fn new() -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let device = [false, true]
+            .into_iter()
+            .find_map(|force_fallback_adapter| {
+                futures::executor::block_on(instance.request_adapter(
+                    &wgpu::RequestAdapterOptions {
+                        power_preference: wgpu::PowerPreference::default(),
+                        compatible_surface: None,
+                        force_fallback_adapter,
+                    },
+                ))
+            })
+            .and_then(|adapter| {
+                futures::executor::block_on(adapter.request_device(&Default::default(), None)).ok()
+            });
+        match device {
+            Some((device, queue)) => Some(Self { device, queue }),
+            None => {
+                println!("no adapter to render on, skipping");
+                None
+            }
+        }
+    }
+
+    /// A `width` by `height` texture to render into and read back out of
+    pub fn target(&self, width: u32, height: u32) -> wgpu::Texture {
+        self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        })
+    }
+
+    /// Uniforms for drawing in the pixels of a `width` by `height` target, the way screen space shapes are
+    pub fn screen_uniforms(
+        &self,
+        layout: &wgpu::BindGroupLayout,
+        width: u32,
+        height: u32,
+    ) -> wgpu::BindGroup {
+        let buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Headless Uniform Buffer"),
+                contents: bytemuck::cast_slice(&[Uniforms::screen(width as f32, height as f32)]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("Headless Uniform Bind Group"),
+        })
+    }
+
+    /// Clears `view` to `clear`, then draws `vertices` through `pipeline` with `bind_groups` bound in order
+    pub fn draw(
+        &self,
+        view: &wgpu::TextureView,
+        clear: wgpu::Color,
+        pipeline: &wgpu::RenderPipeline,
+        bind_groups: &[&wgpu::BindGroup],
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) {
+        let vertex_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Headless Vertex Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+        let index_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Headless Index Buffer"),
+                contents: bytemuck::cast_slice(indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut render_pass = self.clear_pass(&mut encoder, view, clear);
+            render_pass.set_pipeline(pipeline);
+            for (index, bind_group) in bind_groups.iter().enumerate() {
+                render_pass.set_bind_group(index as u32, bind_group, &[]);
+            }
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Records a pass clearing `view` to `clear`, to draw into after
+    pub fn clear_pass<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        view: &'a wgpu::TextureView,
+        clear: wgpu::Color,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Headless Pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        })
+    }
+
+    /// The part of `target`, a `width` by `height` one, inside `rect`. Copied out the same way
+    /// [`super::State::capture_region`] copies the frame
+    pub fn capture(
+        &self,
+        target: &wgpu::Texture,
+        width: u32,
+        height: u32,
+        rect: Rect,
+    ) -> RgbaImage {
+        let mut readback = Readback::default();
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        readback.copy(
+            &self.device,
+            &mut encoder,
+            target,
+            winit::dpi::PhysicalSize::new(width, height),
+            FORMAT,
+        );
+        self.queue.submit(Some(encoder.finish()));
+        readback
+            .read_region(&self.device, &self.queue, rect, FORMAT)
+            .expect("rendered with readback, inside the target and in an 8 bit format")
+    }
+}
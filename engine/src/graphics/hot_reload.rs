@@ -0,0 +1,193 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use super::texture::TextureId;
+use crate::error::Result;
+
+/// Something loaded from a file, that gets loaded again when the file changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Asset {
+    Texture(TextureId),
+    /// Index of the font, the same as its `FontId`
+    Font(usize),
+}
+
+/// Keeps track of which files assets came from, and once enabled, watches them for changes.
+///
+/// The directories the files are in are watched rather than the files themselves, since a lot of editors
+/// save by writing a new file and renaming it over the old one, which would stop a watch on the old file
+pub(crate) struct HotReload {
+    assets: Vec<(PathBuf, Asset)>,
+    watcher: Option<(RecommendedWatcher, Receiver<DebouncedEvent>)>,
+}
+
+impl HotReload {
+    pub fn new() -> Self {
+        Self {
+            assets: Vec::new(),
+            watcher: None,
+        }
+    }
+
+    /// Remembers that `asset` was loaded from `path`, watching it right away if enabled
+    pub fn track(&mut self, path: &Path, asset: Asset) -> Result<()> {
+        let path = path.canonicalize()?;
+        if let Some((watcher, _)) = &mut self.watcher {
+            watch_parent(watcher, &path)?;
+        }
+        self.assets.push((path, asset));
+        Ok(())
+    }
+
+    /// Starts or stops watching every tracked file
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<()> {
+        if !enabled {
+            self.watcher = None;
+            return Ok(());
+        }
+        if self.watcher.is_some() {
+            return Ok(());
+        }
+
+        let (tx, rx) = mpsc::channel();
+        // Saving can take a few writes, so wait for them to settle before reloading
+        let mut watcher = notify::watcher(tx, Duration::from_millis(100))?;
+        for (path, _) in &self.assets {
+            watch_parent(&mut watcher, path)?;
+        }
+        self.watcher = Some((watcher, rx));
+        Ok(())
+    }
+
+    /// Assets whose files changed since this was last called, along with the file
+    pub fn changed(&mut self) -> Vec<(PathBuf, Asset)> {
+        let rx = match &self.watcher {
+            Some((_, rx)) => rx,
+            None => return Vec::new(),
+        };
+
+        changed_assets(&self.assets, rx.try_iter())
+    }
+}
+
+/// Which of `assets` the files in `events` were loaded into, each asset once, in the order they changed.
+/// Renames count for the file they were renamed to, which is how editors that save to a temporary file show up
+fn changed_assets(
+    assets: &[(PathBuf, Asset)],
+    events: impl IntoIterator<Item = DebouncedEvent>,
+) -> Vec<(PathBuf, Asset)> {
+    let mut changed: Vec<(PathBuf, Asset)> = Vec::new();
+    for event in events {
+        let path = match event {
+            DebouncedEvent::Write(path)
+            | DebouncedEvent::Create(path)
+            | DebouncedEvent::Rename(_, path) => path,
+            _ => continue,
+        };
+        for (tracked, asset) in assets {
+            if *tracked == path && !changed.iter().any(|(_, a)| a == asset) {
+                changed.push((tracked.clone(), *asset));
+            }
+        }
+    }
+    changed
+}
+
+fn watch_parent(watcher: &mut RecommendedWatcher, path: &Path) -> Result<()> {
+    // Canonical paths to files always have a parent
+    let dir = path.parent().unwrap_or(path);
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    use notify::DebouncedEvent;
+
+    use super::{changed_assets, Asset, HotReload};
+    use crate::graphics::texture::TextureId;
+
+    #[test]
+    fn events_pick_out_changed_assets() {
+        let sprite = PathBuf::from("/assets/sprite.png");
+        let font = PathBuf::from("/assets/font.ttf");
+        let assets = [
+            (sprite.clone(), Asset::Texture(TextureId(3))),
+            (font.clone(), Asset::Font(1)),
+        ];
+
+        let changed = changed_assets(
+            &assets,
+            [
+                // Untracked files and removals are ignored
+                DebouncedEvent::Write(PathBuf::from("/assets/other.png")),
+                DebouncedEvent::Remove(font.clone()),
+                // Saved by writing a temporary file and renaming it over the font
+                DebouncedEvent::Rename(PathBuf::from("/assets/font.ttf~"), font.clone()),
+                DebouncedEvent::Write(sprite.clone()),
+                // Reloading twice in a row would be wasted work
+                DebouncedEvent::Write(sprite.clone()),
+            ],
+        );
+        assert_eq!(
+            changed,
+            [
+                (font, Asset::Font(1)),
+                (sprite, Asset::Texture(TextureId(3)))
+            ]
+        );
+
+        // Renaming a tracked file away doesn't change what it was loaded into
+        let renamed_away = changed_assets(
+            &assets,
+            [DebouncedEvent::Rename(
+                PathBuf::from("/assets/sprite.png"),
+                PathBuf::from("/assets/sprite-old.png"),
+            )],
+        );
+        assert!(renamed_away.is_empty());
+    }
+
+    #[test]
+    #[ignore = "waits on a real file watcher, which is slow and flaky on some machines"]
+    fn changing_a_file_reloads_its_asset() {
+        let dir =
+            std::env::temp_dir().join(format!("engine-hot-reload-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let sprite = dir.join("sprite.png");
+        let other = dir.join("other.png");
+        std::fs::write(&sprite, b"old").unwrap();
+        std::fs::write(&other, b"old").unwrap();
+
+        let mut hot_reload = HotReload::new();
+        hot_reload
+            .track(&sprite, Asset::Texture(TextureId(3)))
+            .unwrap();
+        hot_reload
+            .track(&other, Asset::Texture(TextureId(4)))
+            .unwrap();
+        // Nothing's reported while disabled
+        assert!(hot_reload.changed().is_empty());
+        hot_reload.set_enabled(true).unwrap();
+
+        std::fs::write(&sprite, b"new").unwrap();
+        let start = Instant::now();
+        let mut changed = Vec::new();
+        while changed.is_empty() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(50));
+            changed = hot_reload.changed();
+        }
+
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].1, Asset::Texture(TextureId(3)));
+        assert_eq!(changed[0].0, sprite.canonicalize().unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
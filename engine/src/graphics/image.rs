@@ -1,106 +1,109 @@
-use super::State;
-use image::GenericImageView;
-
-// "diffuse" refers to "diffuse maps", which are concerned with simple colored textures
-impl State {
-    fn import_image(&mut self, diffuse_bytes: Vec<u8>) {
-        // ... probably store data into self somewhere
-
-        // the following two lines converts byes -> image -> rgba bytes
-        let diffuse_image = image::load_from_memory(&diffuse_bytes).unwrap();
-        let diffuse_rgba = diffuse_image.as_rgba8().unwrap();
-        let dimensions = diffuse_image.dimensions();
-
-        // creating wgpu texture
-        let texture_size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1,
-        };
-        // let diffuse_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-        //     // All textures are stored as 3D, we represent our 2D texture
-        //     // by setting depth to 1.
-        //     size: texture_size,
-        //     mip_level_count: 1, // We'll talk about this a little later
-        //     sample_count: 1,
-        //     dimension: wgpu::TextureDimension::D2,
-        //     // Most images are stored using sRGB so we need to reflect that here.
-        //     format: wgpu::TextureFormat::Rgba8UnormSrgb,
-        //     // TEXTURE_BINDING tells wgpu that we want to use this texture in shaders
-        //     // COPY_DST means that we want to copy data to this texture
-        //     usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-        //     label: Some("diffuse_texture"),
-        // });
-
-        // self.queue.write_texture(
-        //     // Tells wgpu where to copy the pixel data
-        //     wgpu::ImageCopyTexture {
-        //         texture: &diffuse_texture,
-        //         mip_level: 0,
-        //         origin: wgpu::Origin3d::ZERO,
-        //         aspect: wgpu::TextureAspect::All,
-        //     },
-        //     // The actual pixel data
-        //     diffuse_rgba,
-        //     // The layout of the texture
-        //     wgpu::ImageDataLayout {
-        //         offset: 0,
-        //         bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
-        //         rows_per_image: std::num::NonZeroU32::new(dimensions.1),
-        //     },
-        //     texture_size,
-        // );
-
-        // We don't need to configure the texture view much, so let's
-        // let wgpu define it.
-        let diffuse_texture_view =
-            diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let diffuse_sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
-
-        let texture_bind_group_layout =
-            self.device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                multisampled: false,
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            count: None,
-                        },
-                    ],
-                    label: Some("texture_bind_group_layout"),
-                });
-        let diffuse_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_sampler),
-                },
-            ],
-            label: Some("diffuse_bind_group"),
-        });
+use std::io::Cursor;
+use std::time::Duration;
+
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, RgbaImage};
+
+use crate::error::Result;
+
+/// Decoded rgba pixels, not yet on the gpu. Upload with [`super::State::load_image`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    buffer: RgbaImage,
+}
+
+impl Image {
+    /// Decodes an image (png, jpg, etc.)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(Self {
+            buffer: image::load_from_memory(bytes)?.to_rgba8(),
+        })
+    }
+
+    /// Decodes every frame of an animated gif, along with how long each frame is shown for.
+    ///
+    /// Frames are fully composited and decompressed up front, so each one takes
+    /// `width * height * 4` bytes no matter how small the gif file is.
+    /// A 100 frame 256x256 gif is already ~26MB, so keep long animations short or small
+    pub fn load_gif(bytes: &[u8]) -> Result<Vec<(Image, Duration)>> {
+        let frames = GifDecoder::new(Cursor::new(bytes))?
+            .into_frames()
+            .collect_frames()?;
+
+        Ok(frames
+            .into_iter()
+            .map(|frame| {
+                let delay = Duration::from(frame.delay());
+                (
+                    Image {
+                        buffer: frame.into_buffer(),
+                    },
+                    delay,
+                )
+            })
+            .collect())
+    }
+
+    pub fn width(&self) -> u32 {
+        self.buffer.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.buffer.height()
+    }
+
+    /// Raw pixels, 4 bytes per pixel, row by row
+    pub fn rgba(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    pub(crate) fn into_dynamic(self) -> image::DynamicImage {
+        image::DynamicImage::ImageRgba8(self.buffer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, Frame, Rgba, RgbaImage};
+
+    use super::Image;
+
+    #[test]
+    fn gif_frames_and_delays() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            encoder
+                .encode_frames(vec![
+                    Frame::from_parts(
+                        RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255])),
+                        0,
+                        0,
+                        Delay::from_numer_denom_ms(100, 1),
+                    ),
+                    Frame::from_parts(
+                        RgbaImage::from_pixel(2, 2, Rgba([0, 0, 255, 255])),
+                        0,
+                        0,
+                        Delay::from_numer_denom_ms(250, 1),
+                    ),
+                ])
+                .unwrap();
+        }
+
+        let frames = Image::load_gif(&bytes).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].1, Duration::from_millis(100));
+        assert_eq!(frames[1].1, Duration::from_millis(250));
+        assert_eq!((frames[1].0.width(), frames[1].0.height()), (2, 2));
+        assert_eq!(&frames[1].0.rgba()[..4], &[0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn garbage_is_not_a_gif() {
+        assert!(Image::load_gif(&[1, 2, 3]).is_err());
     }
 }
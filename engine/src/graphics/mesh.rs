@@ -0,0 +1,70 @@
+use cgmath::{Matrix4, Vector2};
+
+/// Handle to a mesh uploaded with [`super::State::create_mesh`], the index into the uploaded meshes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshId(pub usize);
+
+/// Where and how a mesh is drawn: scaled by `scale`, rotated `rotation` radians clockwise, then moved by
+/// `translation`, all in world pixels around the mesh's origin. Same as [`super::batch::Batch::push_transform`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vector2<f32>,
+    pub rotation: f32,
+    pub scale: Vector2<f32>,
+}
+
+impl Transform {
+    /// Just moved, not rotated or scaled
+    pub fn at(x: f32, y: f32) -> Self {
+        Self {
+            translation: cgmath::vec2(x, y),
+            ..Self::default()
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation.extend(0.0))
+            * Matrix4::from_angle_z(cgmath::Rad(self.rotation))
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, 1.0)
+    }
+}
+
+impl Default for Transform {
+    /// Leaves the mesh as it is
+    fn default() -> Self {
+        Self {
+            translation: cgmath::vec2(0.0, 0.0),
+            rotation: 0.0,
+            scale: cgmath::vec2(1.0, 1.0),
+        }
+    }
+}
+
+/// Geometry that stays on the gpu, so drawing it again doesn't copy anything
+pub(crate) struct Mesh {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub vertices: usize,
+    pub indices: u32,
+}
+
+#[cfg(test)]
+mod test {
+    use super::Transform;
+
+    #[test]
+    fn transform_scales_rotates_then_moves() {
+        let transform = Transform {
+            translation: cgmath::vec2(100.0, 50.0),
+            rotation: std::f32::consts::FRAC_PI_2,
+            scale: cgmath::vec2(2.0, 1.0),
+        };
+        let point = transform.matrix() * cgmath::vec4(1.0, 0.0, 0.0, 1.0);
+        // (1, 0) stretched to (2, 0), turned a quarter clockwise (y is down) to (0, 2), then moved
+        assert!((point.x - 100.0).abs() < 1e-5);
+        assert!((point.y - 52.0).abs() < 1e-5);
+
+        let moved = Transform::at(3.0, 4.0).matrix() * cgmath::vec4(1.0, 1.0, 0.0, 1.0);
+        assert_eq!((moved.x, moved.y), (4.0, 5.0));
+    }
+}
@@ -1,13 +1,50 @@
+pub mod batch;
 pub mod buffers;
-mod camera;
+pub mod camera;
+mod capabilities;
+pub mod color_grade;
+mod custom_shape;
+pub mod floating_text;
 mod font;
+pub mod gpu_timer;
+#[cfg(test)]
+mod headless;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+pub mod mesh;
+mod msaa;
+pub mod nine_patch;
+pub mod palette;
+mod readback;
+pub mod rect;
 pub mod render;
-mod texture;
+pub mod sdf_circle;
+mod shader;
+pub mod texture;
+pub mod tilemap;
 
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use crate::error::EngineError;
 use camera::Camera;
-use wgpu::{util::DeviceExt, BufferDescriptor};
+use wgpu::util::DeviceExt;
 
-use self::buffers::{Uniforms, Vertex};
+use self::batch::{Batch, DrawStyle, DrawTextureParams, TexturedPoint};
+use self::buffers::{GrowableBuffer, Uniforms, Vertex, VertexLayout};
+use self::color_grade::{ColorGrade, ColorGradePass};
+pub use self::custom_shape::MaterialHandle;
+use self::custom_shape::{CustomPipeline, CustomShapes};
+use self::floating_text::FloatingTexts;
+pub use self::font::{FontId, FontMetrics, HAlign, TextLayer, VAlign};
+use self::gpu_timer::GpuTimer;
+use self::mesh::{Mesh, MeshId, Transform};
+use self::nine_patch::{Insets, NinePatch, NinePatchId};
+use self::palette::{PaletteId, PalettedSprites};
+use self::render::RenderStats;
+use self::sdf_circle::SdfCircles;
+use self::shader::CustomUniform;
+use self::texture::{Texture, TextureId};
 
 pub struct State {
     surface: wgpu::Surface,
@@ -16,26 +53,94 @@ pub struct State {
     queue: wgpu::Queue,
     pub size: winit::dpi::PhysicalSize<u32>,
     render_pipeline: wgpu::RenderPipeline,
+    /// Draws the world instead of `render_pipeline` once it's set, see [`State::set_shader`]
+    custom_pipeline: Option<wgpu::RenderPipeline>,
+    custom_uniform: CustomUniform,
+    /// Pipelines for each vertex type from [`State::register_vertex_layout`]
+    layout_pipelines: HashMap<TypeId, wgpu::RenderPipeline>,
+    /// Pipelines from [`State::create_material`], indexed by [`MaterialHandle`]
+    materials: Vec<wgpu::RenderPipeline>,
+    /// Shapes from [`State::push_custom_shape`] to draw this frame
+    custom_shapes: CustomShapes,
+    /// Looks sprites' colors up in a palette, see [`State::draw_sprite_paletted`]
+    palette_pipeline: wgpu::RenderPipeline,
+    /// Indexed by `PaletteId`
+    palettes: Vec<Texture>,
+    paletted_sprites: PalettedSprites,
+    /// Circles from [`State::draw_circle_sdf`], all in one draw call with their own pipeline
+    sdf_pipeline: wgpu::RenderPipeline,
+    sdf_circles: SdfCircles,
+    /// Kept between frames, see [`GrowableBuffer`]
+    paletted_vertex_buffer: GrowableBuffer,
+    paletted_index_buffer: GrowableBuffer,
+    sdf_vertex_buffer: GrowableBuffer,
+    sdf_index_buffer: GrowableBuffer,
 
-    pub vertex_buffer: wgpu::Buffer,
-    pub index_buffer: wgpu::Buffer,
+    pub vertex_buffer: GrowableBuffer,
+    pub index_buffer: GrowableBuffer,
 
-    pub vertices: Vec<buffers::Vertex>,
-    pub indices: Vec<u16>,
+    pub batch: Batch,
 
     pub camera: Camera,
 
     uniforms: Uniforms,
     uniform_buffer: wgpu::Buffer,
+    uniform_bind_group_layout: wgpu::BindGroupLayout,
     uniform_bind_group: wgpu::BindGroup,
 
+    /// Shapes in screen pixels, drawn on top of everything else without going through the camera
+    pub screen_batch: Batch,
+    screen_vertex_buffer: GrowableBuffer,
+    screen_index_buffer: GrowableBuffer,
+    screen_uniform_buffer: wgpu::Buffer,
+    screen_uniform_bind_group: wgpu::BindGroup,
+
     pub background: Background,
+    /// What [`State::set_clear_color`] was last given, kept to convert it again when gamma correction changes
+    clear_color: Option<Color>,
 
     pub font_interface: font::FontInterface,
+    /// See [`State::set_text_layer`]
+    text_layer: TextLayer,
+    floating_texts: FloatingTexts,
+
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    /// Indexed by `TextureId`, the first is always `TextureId::WHITE`
+    textures: Vec<Texture>,
+    /// Files textures and fonts were loaded from, see [`State::enable_hot_reload`]
+    #[cfg(feature = "hot-reload")]
+    hot_reload: hot_reload::HotReload,
+    /// Indexed by `NinePatchId`
+    nine_patches: Vec<NinePatch>,
+    /// Indexed by `MeshId`
+    meshes: Vec<Mesh>,
+    /// Meshes to draw this frame, in order
+    mesh_draws: Vec<(MeshId, Transform)>,
+
+    stats: RenderStats,
+    gpu_timer: Option<GpuTimer>,
+    /// See [`State::request_pixel`] and [`State::request_capture`]
+    readback: readback::Readback,
+    /// See [`State::set_color_grade`]
+    color_grade: ColorGrade,
+    color_grade_pass: ColorGradePass,
+    /// Aspect ratio the world is kept at, see [`State::set_fixed_aspect`]
+    fixed_aspect: Option<f32>,
+    /// Samples a pixel every pipeline draws with, see [`crate::ContextBuilder::with_sample_count`]
+    sample_count: u32,
+    /// What shapes get drawn into when multisampling, `None` without it
+    msaa: Option<msaa::MsaaTarget>,
 }
 
 impl State {
-    pub async fn new(window: &winit::window::Window) -> Self {
+    /// Uses `surface_format` if given, otherwise whatever the adapter prefers.
+    /// Gamma correction is turned on or off to match whether the format is sRGB.
+    /// Shapes are drawn with `sample_count` samples a pixel, either 1 or 4, or 1 if the adapter can't do 4
+    pub async fn new(
+        window: &winit::window::Window,
+        surface_format: Option<wgpu::TextureFormat>,
+        sample_count: u32,
+    ) -> Result<Self, EngineError> {
         let size = window.inner_size();
 
         // First create the wgpu instance, choosing the primary backend
@@ -45,24 +150,54 @@ impl State {
         // Create the surface to draw on (from window, which we get from winit)
         let surface = unsafe { instance.create_surface(window) };
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptionsBase {
+        let adapter = request_with_fallback(|force_fallback_adapter| {
+            instance.request_adapter(&wgpu::RequestAdapterOptionsBase {
                 power_preference: wgpu::PowerPreference::HighPerformance,
                 compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
+                force_fallback_adapter,
             })
-            .await
-            .expect("Unable to find adapter");
+        })
+        .await
+        .ok_or(EngineError::AdapterNotFound)?;
+        log::info!("Using adapter {:?}", adapter.get_info());
+        log::info!("Adapter limits {:?}", adapter.limits());
+
+        let format = match surface_format {
+            Some(format) => format,
+            // Only missing if the adapter can't draw to the surface at all
+            None => surface
+                .get_preferred_format(&adapter)
+                .ok_or(EngineError::AdapterNotFound)?,
+        };
+
+        // Check up front, since wgpu only panics or gives a vague error when the device is used
+        let missing = capabilities::missing(
+            &adapter.limits(),
+            Some((
+                format,
+                adapter.get_texture_format_features(format).allowed_usages,
+            )),
+        );
+        if !missing.is_empty() {
+            return Err(EngineError::Unsupported(missing));
+        }
 
         let (device, queue) = adapter
             // Create the device from adapter
-            .request_device(&wgpu::DeviceDescriptor::default(), None)
-            .await
-            .expect("Unable to create device");
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    // Only for timing frames, so just go without it if it's not there
+                    features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+                    limits: capabilities::required_limits(),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await?;
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_preferred_format(&adapter).unwrap(),
+            format,
             width: size.width,
             height: size.height,
             // Low latency vsync is mailbox, falls back to Fifo,
@@ -70,6 +205,24 @@ impl State {
         };
 
         surface.configure(&device, &config);
+        // wgpu 0.12 has no way to ask for a frame latency, so it's always the backend's
+        log::info!(
+            "Surface {:?} {:?}, frame latency: backend default",
+            config.format,
+            config.present_mode
+        );
+
+        let mut sample_count = msaa::sample_count(sample_count);
+        if !msaa::supported(&device, config.format, sample_count) {
+            log::warn!(
+                "{}x multisampling isn't supported for {:?}, drawing without it",
+                sample_count,
+                config.format
+            );
+            sample_count = 1;
+        }
+        let msaa = (sample_count > 1)
+            .then(|| msaa::MsaaTarget::new(&device, config.format, size, sample_count));
 
         let camera = Camera::new(config.width as f32, config.height as f32);
 
@@ -83,24 +236,7 @@ impl State {
         });
 
         // recall, bindgroup are resources that the gpu can access through specified shaders
-        let uniform_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    // layout
-                    binding: 0,
-                    // visible only to vertex stage shaders
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    // ty = type of binding
-                    ty: wgpu::BindingType::Buffer {
-                        // uniform value buffer
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("Uniform Bind Group Layout"),
-            });
+        let uniform_bind_group_layout = Uniforms::bind_group_layout(&device);
 
         // create uniform bind group
         let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -112,6 +248,23 @@ impl State {
             label: Some("Uniform Bind Group"),
         });
 
+        let screen_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Screen Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[Uniforms::screen(
+                config.width as f32,
+                config.height as f32,
+            )]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let screen_uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: screen_uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("Screen Uniform Bind Group"),
+        });
+
 
 
         let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
@@ -119,61 +272,79 @@ impl State {
             source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
         });
 
+        // Every shape samples a texture, plain ones just use a white one
+        let texture_bind_group_layout = Texture::bind_group_layout(&device);
+
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&uniform_bind_group_layout],
+                bind_group_layouts: &[&uniform_bind_group_layout, &texture_bind_group_layout],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                // Specify the entry point function for shaders, set by [[stage(fragment)]]
-                entry_point: "vs_main",
-                // We should pass in info into the shader itself, right now we're creating it in the shader for hello world
-                buffers: &[buffers::Vertex::desc()],
-            },
-            // Fragment technically opt
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                // Target color output for swap chain, replace old pixels, and write to all colors
-                targets: &[wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                }],
-            }),
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-        });
+        let render_pipeline = shader::pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            config.format,
+            Vertex::desc(),
+            sample_count,
+        );
+        let custom_uniform = CustomUniform::new(&device);
 
-        let vertices = Vec::new();
-        let indices = Vec::new();
+        let palette_pipeline = palette::pipeline(
+            &device,
+            &uniform_bind_group_layout,
+            &texture_bind_group_layout,
+            config.format,
+            sample_count,
+        );
 
-        let vertex_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some("Vertex Buffer"),
-            size: 0,
-            usage: wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        let sdf_pipeline = sdf_circle::pipeline(
+            &device,
+            &uniform_bind_group_layout,
+            config.format,
+            sample_count,
+        );
 
-        let index_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some("Index Buffer"),
-            size: 0,
-            usage: wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        let batch = Batch::new(config.format);
+        let screen_batch = Batch::new(config.format);
+
+        let vertices = wgpu::BufferUsages::VERTEX;
+        let indices = wgpu::BufferUsages::INDEX;
+        let vertex_buffer = GrowableBuffer::new(&device, "Vertex Buffer", vertices);
+        let index_buffer = GrowableBuffer::new(&device, "Index Buffer", indices);
+        let screen_vertex_buffer = GrowableBuffer::new(&device, "Screen Vertex Buffer", vertices);
+        let screen_index_buffer = GrowableBuffer::new(&device, "Screen Index Buffer", indices);
+        let paletted_vertex_buffer =
+            GrowableBuffer::new(&device, "Paletted Vertex Buffer", vertices);
+        let paletted_index_buffer = GrowableBuffer::new(&device, "Paletted Index Buffer", indices);
+        let sdf_vertex_buffer = GrowableBuffer::new(&device, "SDF Circle Vertex Buffer", vertices);
+        let sdf_index_buffer = GrowableBuffer::new(&device, "SDF Circle Index Buffer", indices);
 
         let background = Background::default();
 
         let font_interface = font::FontInterface::new(&device, config.format);
-        Self {
+
+        let white = ::image::DynamicImage::ImageRgba8(::image::RgbaImage::from_pixel(
+            1,
+            1,
+            ::image::Rgba([255, 255, 255, 255]),
+        ));
+        let white = Texture::from_image(
+            &device,
+            &queue,
+            &texture_bind_group_layout,
+            &white,
+            texture_format(&batch),
+            Some("White Texture"),
+        )?;
+        let textures = vec![white];
+
+        let gpu_timer = GpuTimer::new(&device, &queue);
+        let color_grade_pass = ColorGradePass::new(&device, config.format);
+
+        Ok(Self {
             surface,
             config,
             device,
@@ -182,21 +353,108 @@ impl State {
             camera,
             uniforms,
             uniform_buffer,
+            uniform_bind_group_layout,
             uniform_bind_group,
+            screen_batch,
+            screen_vertex_buffer,
+            screen_index_buffer,
+            screen_uniform_buffer,
+            screen_uniform_bind_group,
             render_pipeline,
-            vertices,
-            indices,
+            custom_pipeline: None,
+            custom_uniform,
+            layout_pipelines: HashMap::new(),
+            materials: Vec::new(),
+            custom_shapes: CustomShapes::default(),
+            palette_pipeline,
+            palettes: Vec::new(),
+            paletted_sprites: PalettedSprites::default(),
+            sdf_pipeline,
+            sdf_circles: SdfCircles::default(),
+            paletted_vertex_buffer,
+            paletted_index_buffer,
+            sdf_vertex_buffer,
+            sdf_index_buffer,
+            batch,
             vertex_buffer,
             index_buffer,
             background,
+            clear_color: Some(Color::BLACK),
             font_interface,
+            text_layer: TextLayer::default(),
+            floating_texts: FloatingTexts::default(),
+            texture_bind_group_layout,
+            textures,
+            #[cfg(feature = "hot-reload")]
+            hot_reload: hot_reload::HotReload::new(),
+            nine_patches: Vec::new(),
+            meshes: Vec::new(),
+            mesh_draws: Vec::new(),
+            stats: RenderStats::default(),
+            gpu_timer,
+            readback: readback::Readback::default(),
+            color_grade: ColorGrade::IDENTITY,
+            color_grade_pass,
+            fixed_aspect: None,
+            sample_count,
+            msaa,
+        })
+    }
+}
+
+/// Whether to gamma correct colors drawn to `surface_format`, see [`State::set_gamma_correction`]. `forced` is
+/// [`crate::ContextBuilder::with_gamma_correction`], which wins, but going against the format is almost always a
+/// mistake (washed out colors on an sRGB surface, too dark ones on the rest), so it gets a warning
+pub(crate) fn gamma_correction(surface_format: wgpu::TextureFormat, forced: Option<bool>) -> bool {
+    let srgb = surface_format.describe().srgb;
+    match forced {
+        Some(forced) => {
+            if forced != srgb {
+                log::warn!(
+                    "Gamma correction forced {} on a{} sRGB surface ({:?}), colors will look {}",
+                    if forced { "on" } else { "off" },
+                    if srgb { "n" } else { " non" },
+                    surface_format,
+                    if forced { "too dark" } else { "washed out" }
+                );
+            }
+            forced
         }
+        None => srgb,
+    }
+}
+
+/// Textures are sRGB just like `Color`s, so they have to be made linear the same way
+fn texture_format(batch: &Batch) -> wgpu::TextureFormat {
+    if batch.gamma_correction {
+        wgpu::TextureFormat::Rgba8UnormSrgb
+    } else {
+        wgpu::TextureFormat::Rgba8Unorm
+    }
+}
+
+/// Asks for a real adapter first, then settles for a fallback (software) one, which is sometimes all that's
+/// there on CI runners and VMs. `request` is called with whether to force the fallback adapter
+async fn request_with_fallback<T, F, Fut>(request: F) -> Option<T>
+where
+    F: Fn(bool) -> Fut,
+    Fut: std::future::Future<Output = Option<T>>,
+{
+    if let Some(adapter) = request(false).await {
+        return Some(adapter);
     }
+    log::warn!("No hardware adapter found, falling back to a software one, expect it to be slow");
+    request(true).await
 }
 
+/// What the screen is cleared to before each frame is drawn
 pub struct Background {
     pub color: wgpu::Color,
+    /// Set by `clear_background`, only lasts for the current frame
     pub should_clear: bool,
+    /// Cleared to every frame that `clear_background` isn't called, `None` keeps the last frame around.
+    /// Defaults to opaque black
+    pub clear_color: Option<wgpu::Color>,
 }
 
 impl Background {
@@ -207,6 +465,16 @@ impl Background {
     pub fn reset(&mut self) {
         self.should_clear = false;
     }
+    /// What happens to the last frame when this one starts drawing
+    pub fn load_op(&self) -> wgpu::LoadOp<wgpu::Color> {
+        if self.should_clear {
+            wgpu::LoadOp::Clear(self.color)
+        } else if let Some(color) = self.clear_color {
+            wgpu::LoadOp::Clear(color)
+        } else {
+            wgpu::LoadOp::Load
+        }
+    }
 }
 
 impl Default for Background {
@@ -214,6 +482,7 @@ impl Default for Background {
         Background {
             color: wgpu::Color::TRANSPARENT,
             should_clear: false,
+            clear_color: Some(wgpu::Color::BLACK),
         }
     }
 }
@@ -222,167 +491,908 @@ pub mod camera_controller;
 pub mod color;
 pub mod image;
 
-use std::f32::consts::PI;
 use color::Color;
 
 impl State {
     /// Takes in top left coordinate of square, width, and a `color::Color`
     pub fn draw_square(&mut self, x: f32, y: f32, width: f32, color: Color) {
-        let color = wgpu::Color::from(color);
-        let color = [
-            color.r as f32,
-            color.g as f32,
-            color.b as f32,
-            color.a as f32,
-        ];
-        // We're allowed to pass in coords straight from our game, since our view matrix
-        // will take care of transforming coords
-
-        // Z is always 0 for a 2d game
-        let vertices = &[
-            // Top left, 0
-            Vertex {
-                position: [x, y, 0.0],
-                color,
-            },
-            // Top right, 1
-            Vertex {
-                position: [x + width, y, 0.0],
-                color,
-            },
-            // Bot left, 2
-            Vertex {
-                position: [x, y + width, 0.0],
-                color,
-            },
-            // bot right, 3
-            Vertex {
-                position: [x + width, y + width, 0.0],
-                color,
-            },
-        ];
-
-        let indices = &[
-            0, 2, 3, // Top triangle
-            3, 1, 0, // Bot triangle
-        ];
-
-        self.push_shape(vertices, indices);
+        self.batch.draw_square(x, y, width, color);
     }
 
     pub fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
-        let color = wgpu::Color::from(color);
-        let color = [
-            color.r as f32,
-            color.g as f32,
-            color.b as f32,
-            color.a as f32,
-        ];
-        // We're allowed to pass in coords straight from our game, since our view matrix
-        // will take care of transforming coords
-
-        // Z is always 0 for a 2d game
-        let vertices = &[
-            // Top left, 0
-            Vertex {
-                position: [x, y, 0.0],
-                color,
-            },
-            // Top right, 1
-            Vertex {
-                position: [x + width, y, 0.0],
-                color,
-            },
-            // Bot left, 2
-            Vertex {
-                position: [x, y + height, 0.0],
-                color,
-            },
-            // bot right, 3
-            Vertex {
-                position: [x + width, y + height, 0.0],
-                color,
-            },
-        ];
-
-        let indices = &[
-            0, 2, 3, // Top triangle
-            3, 1, 0, // Bot triangle
-        ];
-
-        self.push_shape(vertices, indices);
+        self.batch.draw_rectangle(x, y, width, height, color);
     }
 
     pub fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32, color: Color) {
-        let color = wgpu::Color::from(color);
-        let color = [
-            color.r as f32,
-            color.g as f32,
-            color.b as f32,
-            color.a as f32,
-        ];
-        // Get angle of line
-        let angle = ((y2 - y1) / (x2 - x1)).atan();
-        // Get perpendicular upper angle of line
-        let pangle = angle + PI / 2.0;
-        let r = thickness / 2.0;
-        // Get diffs
-        let pdx = pangle.cos() * r;
-        let pdy = pangle.sin() * r;
-
-        let vertices = &[
-            // Top left, 0
-            Vertex {
-                position: [x2 + pdx, y2 + pdy, 0.0],
-                color,
-            },
-            // Top right, 1
-            Vertex {
-                position: [x1 + pdx, y1 + pdy, 0.0],
-                color,
-            },
-            // bot right, 3
-            Vertex {
-                position: [x2 - pdx, y2 - pdy, 0.0],
-                color,
-            },
-            // Bot left, 2
-            Vertex {
-                position: [x1 - pdx, y1 - pdy, 0.0],
-                color,
-            },
-        ];
-
-
-        let indices = &[
-            0, 2, 3, // Top triangle
-            3, 1, 0, // Bot triangle
-        ];
-
-        self.push_shape(vertices, indices);
+        self.batch.draw_line(x1, y1, x2, y2, thickness, color);
+    }
+
+    /// Line with softened edges, see [`Batch::draw_line_smooth`]. The fringe is one window pixel wide at the
+    /// camera's current zoom, so it stays as sharp zoomed in as out
+    pub fn draw_line_smooth(
+        &mut self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        thickness: f32,
+        color: Color,
+    ) {
+        let feather = self.camera.world_per_pixel(&self.viewport());
+        self.batch
+            .draw_line_smooth(x1, y1, x2, y2, thickness, feather, color);
+    }
+
+    /// Draws a line with a triangular head at `(x2, y2)`, useful for debugging vectors
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_arrow(
+        &mut self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        thickness: f32,
+        head_size: f32,
+        color: Color,
+    ) {
+        self.batch
+            .draw_arrow(x1, y1, x2, y2, thickness, head_size, color);
+    }
+
+    /// Line with rounded ends, see [`Batch::draw_capsule`]
+    pub fn draw_capsule(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32, color: Color) {
+        self.batch.draw_capsule(x1, y1, x2, y2, radius, color);
+    }
+
+    /// Border inside the rectangle, see [`Batch::draw_rectangle_outline`]
+    pub fn draw_rectangle_outline(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        thickness: f32,
+        color: Color,
+    ) {
+        self.batch
+            .draw_rectangle_outline(x, y, width, height, thickness, color);
+    }
+
+    /// Filled triangle between three points in any order, see [`Batch::draw_triangle`]
+    pub fn draw_triangle(&mut self, p1: (f32, f32), p2: (f32, f32), p3: (f32, f32), color: Color) {
+        self.batch.draw_triangle(p1, p2, p3, color);
+    }
+
+    /// Filled convex polygon with a color per point, see [`Batch::draw_polygon_colored`]
+    pub fn draw_polygon_colored(&mut self, points: &[((f32, f32), Color)]) {
+        self.batch.draw_polygon_colored(points);
+    }
+
+    /// Circle centered on `(x, y)`. Picks how many segments to use from how big it'll be on screen,
+    /// so it looks round when zoomed in without wasting vertices when it's tiny. [`State::draw_circle_ex`]
+    /// picks the number of segments by hand
+    pub fn draw_circle(&mut self, x: f32, y: f32, radius: f32, color: Color) {
+        let segments = self.screen_segments(x, y, radius);
+        self.batch.draw_circle_ex(x, y, radius, segments, color);
+    }
+
+    /// Ring `thickness` wide centered on `radius`, with the same segments as [`State::draw_circle`] picks so it
+    /// lines up exactly with the filled circle, see [`Batch::draw_circle_outline_ex`]
+    pub fn draw_circle_outline(
+        &mut self,
+        x: f32,
+        y: f32,
+        radius: f32,
+        thickness: f32,
+        color: Color,
+    ) {
+        let segments = self.screen_segments(x, y, radius);
+        self.batch
+            .draw_circle_outline_ex(x, y, radius, thickness, segments, color);
+    }
+
+    /// Segments a circle needs to look round at the size it'll be on screen
+    fn screen_segments(&self, x: f32, y: f32, radius: f32) -> u32 {
+        let viewport = self.viewport();
+        let center = self.camera.project(x, y, &viewport);
+        let edge = self.camera.project(x + radius, y, &viewport);
+        let screen_radius = (edge.0 - center.0).hypot(edge.1 - center.1);
+        batch::circle_segments(screen_radius)
+    }
+
+    /// Hexagons, pentagons and the like, see [`Batch::draw_polygon`]
+    pub fn draw_polygon(
+        &mut self,
+        x: f32,
+        y: f32,
+        radius: f32,
+        sides: u32,
+        rotation: f32,
+        color: Color,
+    ) {
+        self.batch
+            .draw_polygon(x, y, radius, sides, rotation, color);
+    }
+
+    /// Circle made of exactly `segments` edges, see [`Batch::draw_circle_ex`]
+    pub fn draw_circle_ex(&mut self, x: f32, y: f32, radius: f32, segments: u32, color: Color) {
+        self.batch.draw_circle_ex(x, y, radius, segments, color);
+    }
+
+    /// Circle with a perfectly smooth, antialiased edge at any size, from one quad whose pixels each work out how
+    /// far they are from the edge in the shader. Tessellated circles from [`State::draw_circle`] look faceted when
+    /// they're big and spend a lot of vertices when they're small, these are always 4.
+    ///
+    /// The catch is they need their own pipeline, so they're drawn all together after the rest of the world
+    /// (paletted sprites included) in one extra draw call, not in between the shapes they were drawn with. Worth
+    /// it once there are many circles, or big ones; for a couple of small circles under other shapes,
+    /// [`State::draw_circle`] keeps the order. Always filled, and they don't go through [`State::push_transform`]
+    pub fn draw_circle_sdf(&mut self, x: f32, y: f32, radius: f32, color: Color) {
+        let color = self.batch.vertex_color(color);
+        self.sdf_circles.push(x, y, radius, color);
+    }
+
+    /// Uploads shapes to the gpu once, to be drawn as many times as needed with [`State::draw_mesh`].
+    /// For geometry that never changes, like background art, which would otherwise be rebuilt and copied
+    /// over every frame. `indices` index into `vertices`, which are in world pixels but get moved
+    /// by the transform they're drawn with. Meshes are always plain colored, they don't sample a texture
+    pub fn create_mesh(&mut self, vertices: &[Vertex], indices: &[u16]) -> MeshId {
+        let vertex_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Mesh Vertex Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+        let index_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Mesh Index Buffer"),
+                contents: bytemuck::cast_slice(indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+        self.meshes.push(Mesh {
+            vertex_buffer,
+            index_buffer,
+            vertices: vertices.len(),
+            indices: indices.len() as u32,
+        });
+        MeshId(self.meshes.len() - 1)
+    }
+
+    /// Draws a mesh from [`State::create_mesh`] this frame, through the camera after `transform`.
+    /// Each draw is its own draw call, and meshes are drawn under every other shape, in the order they're drawn
+    pub fn draw_mesh(&mut self, id: MeshId, transform: Transform) {
+        self.mesh_draws.push((id, transform));
+    }
+
+    /// Triangles fanning out from `center`, see [`Batch::draw_triangle_fan`]
+    pub fn draw_triangle_fan(&mut self, center: (f32, f32), rim: &[(f32, f32)], color: Color) {
+        self.batch.draw_triangle_fan(center, rim, color);
+    }
+
+    /// Triangles each sharing an edge with the last, see [`Batch::draw_triangle_strip`]
+    pub fn draw_triangle_strip(&mut self, points: &[(f32, f32)], color: Color) {
+        self.batch.draw_triangle_strip(points, color);
+    }
+
+    /// Decodes an image (png, jpg, etc.) and uploads it to the gpu
+    pub fn load_texture(&mut self, bytes: &[u8]) -> Result<TextureId, EngineError> {
+        let texture = Texture::from_bytes(
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            texture_format(&self.batch),
+            bytes,
+            "Texture",
+        )?;
+        self.textures.push(texture);
+        Ok(TextureId(self.textures.len() - 1))
+    }
+
+    /// Uploads an already decoded image, e.g. a frame from [`image::Image::load_gif`]
+    pub fn load_image(&mut self, image: image::Image) -> Result<TextureId, EngineError> {
+        let texture = Texture::from_image(
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            &image.into_dynamic(),
+            texture_format(&self.batch),
+            Some("Texture"),
+        )?;
+        self.textures.push(texture);
+        Ok(TextureId(self.textures.len() - 1))
+    }
+
+    /// Same as [`State::load_texture`], reading the image from `path`. With the `hot-reload` feature,
+    /// the texture can be reloaded when the file changes, see [`State::enable_hot_reload`]
+    pub fn load_texture_file(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<TextureId, EngineError> {
+        let bytes = std::fs::read(path.as_ref())?;
+        let id = self.load_texture(&bytes)?;
+        #[cfg(feature = "hot-reload")]
+        self.hot_reload
+            .track(path.as_ref(), hot_reload::Asset::Texture(id))?;
+        Ok(id)
+    }
+
+    /// Turns reloading textures and fonts when their files change on or off. They keep their `TextureId`s
+    /// and `FontId`s, so the new version just shows up in place of the old. Only textures loaded with
+    /// [`State::load_texture_file`] and fonts loaded with [`State::load_font`] are watched.
+    ///
+    /// Meant for development, so assets can be tweaked without restarting the game. Needs the `hot-reload`
+    /// feature, which only works on desktop. If a changed file can't be loaded (e.g. it's half written),
+    /// the old version is kept and a warning is logged
+    #[cfg(feature = "hot-reload")]
+    pub fn enable_hot_reload(&mut self, enabled: bool) -> Result<(), EngineError> {
+        self.hot_reload.set_enabled(enabled)
+    }
+
+    /// Reloads whatever changed on disk since the last frame, called before every render
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn reload_changed_assets(&mut self) {
+        for (path, asset) in self.hot_reload.changed() {
+            if let Err(e) = self.reload_asset(&path, asset) {
+                log::warn!("Unable to reload {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    #[cfg(feature = "hot-reload")]
+    fn reload_asset(
+        &mut self,
+        path: &std::path::Path,
+        asset: hot_reload::Asset,
+    ) -> Result<(), EngineError> {
+        let bytes = std::fs::read(path)?;
+        match asset {
+            hot_reload::Asset::Texture(id) => {
+                self.textures[id.0] = Texture::from_bytes(
+                    &self.device,
+                    &self.queue,
+                    &self.texture_bind_group_layout,
+                    texture_format(&self.batch),
+                    &bytes,
+                    "Texture",
+                )?;
+            }
+            hot_reload::Asset::Font(index) => {
+                let font = wgpu_glyph::ab_glyph::FontArc::try_from_vec(bytes)?;
+                self.font_interface
+                    .replace_font(&self.device, self.config.format, index, font);
+            }
+        }
+        log::info!("Reloaded {}", path.display());
+        Ok(())
+    }
+
+    /// Loads an image to draw as a nine patch with [`State::draw_nine_patch`], keeping `border`
+    /// (in pixels of the image) around so it doesn't have to be passed on every draw
+    pub fn load_nine_patch(
+        &mut self,
+        bytes: &[u8],
+        border: Insets,
+    ) -> Result<NinePatchId, EngineError> {
+        let texture = self.load_texture(bytes)?;
+        let (width, height) = self.texture_size(texture);
+        self.nine_patches.push(NinePatch {
+            texture,
+            width,
+            height,
+            border,
+        });
+        Ok(NinePatchId(self.nine_patches.len() - 1))
+    }
+
+    /// Draws a nine patch stretched over `dst`, see [`NinePatch`]
+    pub fn draw_nine_patch(&mut self, id: NinePatchId, dst: rect::Rect, color: Color) {
+        self.nine_patches[id.0].draw_into(&mut self.batch, dst, color);
+    }
+
+    /// Width and height in pixels of a loaded texture
+    pub fn texture_size(&self, id: TextureId) -> (u32, u32) {
+        let texture = &self.textures[id.0];
+        (texture.width, texture.height)
+    }
+
+    /// Decodes a sprite to draw with [`State::draw_sprite_paletted`], where each pixel's red channel is the index
+    /// of its color in the palette instead of a color itself (so a sprite with 3 colors is drawn with reds 0, 1
+    /// and 2). It's never gamma corrected, since the indices have to come through exactly
+    pub fn load_paletted_sprite(&mut self, bytes: &[u8]) -> Result<TextureId, EngineError> {
+        let texture = Texture::from_bytes(
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            wgpu::TextureFormat::Rgba8Unorm,
+            bytes,
+            "Paletted Sprite",
+        )?;
+        self.textures.push(texture);
+        Ok(TextureId(self.textures.len() - 1))
+    }
+
+    /// Uploads colors for paletted sprites to look up, index 0 first. Only the first [`palette::MAX_COLORS`]
+    /// can be reached by an index
+    pub fn load_palette(&mut self, colors: &[Color]) -> Result<PaletteId, EngineError> {
+        let texture = Texture::from_image(
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            &::image::DynamicImage::ImageRgba8(palette::palette_image(colors)),
+            texture_format(&self.batch),
+            Some("Palette"),
+        )?;
+        self.palettes.push(texture);
+        Ok(PaletteId(self.palettes.len() - 1))
+    }
+
+    /// Draws a sprite from [`State::load_paletted_sprite`] at its size in pixels with its top left at `x`, `y`,
+    /// with every pixel's color looked up in `palette`. One sprite with many palettes is how retro games do team
+    /// colors or flashing when hit, without a copy of the sprite for each.
+    ///
+    /// Paletted sprites have their own shader, so they're drawn after all the other world shapes (and before
+    /// custom shapes and screen space ones), one draw call each, and don't go through [`State::push_transform`].
+    /// Indices past the end of the palette get its last color
+    pub fn draw_sprite_paletted(&mut self, id: TextureId, palette: PaletteId, x: f32, y: f32) {
+        let (width, height) = self.texture_size(id);
+        let color = self.batch.vertex_color(Color::WHITE);
+        self.paletted_sprites.push(
+            id,
+            palette,
+            rect::Rect::new(x, y, width as f32, height as f32),
+            color,
+        );
+    }
+
+    /// Draws a texture from [`State::load_texture`] at its size in pixels with its top left at `x`, `y`, tinted by
+    /// `color`. Use [`Color::WHITE`] to draw it as is
+    pub fn draw_texture(&mut self, id: TextureId, x: f32, y: f32, color: Color) {
+        let (width, height) = self.texture_size(id);
+        self.batch.draw_texture_rect(
+            id,
+            rect::Rect::new(x, y, width as f32, height as f32),
+            color,
+        );
+    }
+
+    /// Draws part of a texture, scaled, rotated or flipped, see [`DrawTextureParams`]. The core of sprite sheets and
+    /// tilemaps, where every frame or tile is a `source` rect out of one texture
+    pub fn draw_texture_ex(
+        &mut self,
+        id: TextureId,
+        x: f32,
+        y: f32,
+        color: Color,
+        params: DrawTextureParams,
+    ) {
+        let size = self.texture_size(id);
+        self.batch.draw_texture_ex(id, size, x, y, color, params);
+    }
+
+    /// Arbitrary textured triangles, see [`Batch::draw_textured_polygon`]
+    pub fn draw_textured_polygon(
+        &mut self,
+        id: TextureId,
+        verts: &[TexturedPoint],
+        indices: &[u16],
+        color: Color,
+    ) {
+        self.batch.draw_textured_polygon(id, verts, indices, color);
     }
 
     /// Pushes a shape into the vector of shapes. These shapes are copied into the vertex and index buffer
     /// in the `render()` function, to be batch rendered.
-    /// Internally, updates `num_indices` and `num_vertices`, as well as converts `indices` on shape based off of previous `num_indices`
+    /// Internally, converts `indices` on shape based off of previous number of vertices
     pub fn push_shape(&mut self, vertices: &[Vertex], indices: &[u16]) {
-        let len = self.vertices.len() as u16;
+        self.batch.push_shape(vertices, indices);
+    }
+
+    /// Every vertex drawn so far this frame, in the order they were pushed, for effects that move or recolor
+    /// them all at once on the cpu. Meant for [`crate::Runnable::pre_render`], when the frame's drawing is done.
+    ///
+    /// Shapes are made out of indices into this, so vertices can be changed but not added or taken away.
+    /// Moving one moves it in every triangle that uses it, and changing `tex_coords` can sample outside the texture
+    pub fn vertices_mut(&mut self) -> &mut [Vertex] {
+        &mut self.batch.vertices
+    }
 
-        // Not sure which implementation is better/faster
-        // indices.iter_mut().map(|i| *i += len);
-        // self.state.indices.extend_from_slice(indices);
-        // The reason is because while for_each avoids iterating over the
-        // array twice, push() might increase/decrease array len
-        // Need to benchmark
+    /// See [`Batch::push_transform`]
+    pub fn push_transform(
+        &mut self,
+        translation: cgmath::Vector2<f32>,
+        rotation: f32,
+        scale: cgmath::Vector2<f32>,
+    ) {
+        self.batch.push_transform(translation, rotation, scale);
+    }
 
-        indices.iter().for_each(|i| {
-            self.indices.push(*i + len);
-        });
+    pub fn pop_transform(&mut self) {
+        self.batch.pop_transform();
+    }
 
-        self.vertices.extend_from_slice(vertices);
+    /// Text at `(x, y)` on screen that floats up and fades out over `duration`, then goes away on its own.
+    /// Handy for damage numbers and notifications. Only [`floating_text::MAX_FLOATING_TEXT`] are kept at once,
+    /// adding more than that drops the oldest
+    pub fn floating_text(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        color: Color,
+        duration: std::time::Duration,
+    ) {
+        self.floating_texts.push(text, x, y, color, duration);
+    }
+
+    /// Ages floating text by the time since the last frame, and queues what's left to be drawn
+    pub(crate) fn draw_floating_text(&mut self, dt: std::time::Duration) {
+        self.floating_texts.advance(dt);
+        for (text, x, y, color, scale) in self.floating_texts.visible() {
+            let color = self.batch.wgpu_color(color);
+            self.font_interface.queue_text(text, x, y, color, scale);
+        }
     }
 
     pub fn clear_background(&mut self, color: color::Color) {
-        self.background.clear(wgpu::Color::from(color));
+        let color = self.batch.wgpu_color(color);
+        self.background.clear(color);
+    }
+
+    /// Changes what the screen is cleared to on frames where `clear_background` isn't called, opaque black by default.
+    /// `None` stops clearing altogether, so that whatever was drawn last frame stays, see [`State::fade_screen`]
+    pub fn set_clear_color(&mut self, color: Option<color::Color>) {
+        self.clear_color = color;
+        self.background.clear_color = color.map(|color| self.batch.wgpu_color(color));
+    }
+
+    /// For trails and motion blur: instead of clearing, covers everything the camera can see with a
+    /// translucent `color`, so what was drawn in earlier frames fades out a bit more every frame.
+    /// The lower `color`'s alpha, the longer the trails.
+    ///
+    /// Call it at the start of the frame, before drawing anything, or the new shapes get faded too.
+    /// The previous frame is only kept if there's no clear color (`set_clear_color(None)`) and
+    /// `clear_background` isn't called, so this does nothing on frames where the background is cleared.
+    /// Calling `clear_background` after this in the same frame still clears everything.
+    ///
+    /// This relies on the surface keeping its contents between frames, which wgpu doesn't guarantee;
+    /// with some backends and present modes the "previous" frame is an older one
+    pub fn fade_screen(&mut self, color: color::Color) {
+        if self.background.load_op() != wgpu::LoadOp::Load {
+            return;
+        }
+        let bounds = self.camera.visible_bounds();
+        self.batch.fade(bounds, color);
+    }
+
+    /// Whether `Color`s get gamma corrected when drawn. By default this matches the surface format,
+    /// on for sRGB surfaces and off for the rest.
+    ///
+    /// `Color`s are sRGB, but sRGB surfaces expect linear colors and convert them back to sRGB by themselves,
+    /// which is why colors are corrected for those. Turn it off if you're passing in colors that are already linear,
+    /// otherwise everything comes out too dark.
+    ///
+    /// The clear color is converted again to match. Textures aren't: they're decoded as sRGB or not when they're
+    /// loaded, so textures loaded before the change keep the old behavior. Set it with
+    /// [`crate::ContextBuilder::with_gamma_correction`] instead to have every texture match
+    pub fn set_gamma_correction(&mut self, gamma_correction: bool) {
+        self.batch.gamma_correction = gamma_correction;
+        self.screen_batch.gamma_correction = gamma_correction;
+        self.set_clear_color(self.clear_color);
+    }
+
+    /// Samples a pixel shapes are drawn with, 4 with multisampling and 1 without, see
+    /// [`crate::ContextBuilder::with_sample_count`]
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn gamma_correction(&self) -> bool {
+        self.batch.gamma_correction
+    }
+
+    /// Changes whether squares, rectangles, circles and polygons are filled or outlined from now on.
+    /// Handy for debug views. Text and textures ignore this
+    pub fn set_draw_style(&mut self, style: DrawStyle) {
+        self.batch.style = style;
+    }
+
+    pub fn draw_style(&self) -> DrawStyle {
+        self.batch.style
+    }
+
+    /// World shapes drawn from now on go in order of `key`, lowest first, instead of the order they're drawn in.
+    /// Resets to 0 every frame. Frames that never set a key don't get sorted at all. See [`Batch::sort_draws`]
+    pub fn set_sort_key(&mut self, key: i32) {
+        self.batch.set_sort_key(key);
+    }
+
+    /// Bucket shapes by texture before rendering, so each texture is bound only once a frame.
+    /// Off by default, since shapes of different textures no longer overlap in the order they
+    /// were drawn. See [`Batch::bucket_by_texture`]
+    pub fn set_texture_batching(&mut self, texture_batching: bool) {
+        self.batch.texture_batching = texture_batching;
+    }
+
+    pub fn texture_batching(&self) -> bool {
+        self.batch.texture_batching
+    }
+
+    /// What the last rendered frame submitted
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// Milliseconds the gpu took to render a recent frame, see [`GpuTimer`].
+    /// Always `None` if the adapter doesn't support `wgpu::Features::TIMESTAMP_QUERY`,
+    /// and `None` for the first frame or two while the first timing is read back
+    pub fn gpu_frame_time(&self) -> Option<f32> {
+        self.gpu_timer.as_ref().and_then(GpuTimer::frame_time)
+    }
+
+    /// Draws the world (shapes, textures and meshes, but not text or screen space ui) with a custom wgsl shader,
+    /// or the built in one again for `None`. It takes the same vertices and bindings as the built in shader, so
+    /// start from a copy of `shader.wgsl`: `vs_main` and `fs_main`, the camera uniforms at `[[group(0), binding(0)]]`
+    /// and the texture and sampler at `[[group(1), binding(0)]]` and `[[group(1), binding(1)]]`.
+    /// Data from [`State::set_custom_uniform`] is at `[[group(2), binding(0)]]`, e.g.
+    ///
+    /// ```wgsl
+    /// struct Effect {
+    ///     time: f32;
+    /// };
+    /// [[group(2), binding(0)]]
+    /// var<uniform> effect: Effect;
+    /// ```
+    ///
+    /// Fails with [`EngineError::ShaderCompile`] if the shader doesn't compile or doesn't fit the bindings, and
+    /// the shader that was in use is kept
+    pub fn set_shader(&mut self, wgsl: Option<&str>) -> Result<(), EngineError> {
+        self.custom_pipeline = match wgsl {
+            Some(wgsl) => Some(self.compile_pipeline(wgsl, Vertex::desc())?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Lets shapes be drawn with vertices of type `V` through [`State::push_custom_shape`], for shaders that need
+    /// something other than the built in [`Vertex`]'s position, color and texture coordinates, like normals or
+    /// extra per vertex data. `wgsl` is the shader they're drawn with, which takes `V`'s attributes at the
+    /// locations [`VertexLayout::desc`] gives them, and has the same bindings as the ones for [`State::set_shader`].
+    /// Calling it again for the same `V` replaces its shader.
+    ///
+    /// Fails with [`EngineError::ShaderCompile`] if the shader doesn't compile or doesn't match `V`
+    pub fn register_vertex_layout<V: VertexLayout + 'static>(
+        &mut self,
+        wgsl: &str,
+    ) -> Result<(), EngineError> {
+        let pipeline = self.compile_pipeline(wgsl, V::desc())?;
+        self.layout_pipelines.insert(TypeId::of::<V>(), pipeline);
+        Ok(())
+    }
+
+    /// Draws a shape of `V` vertices, with the shader from [`State::register_vertex_layout`]. The vertices go in
+    /// as they are, without gamma correction or [`State::push_transform`], since the engine doesn't know what's
+    /// in them. The shader gets the same camera uniforms as the built in one to place them with.
+    ///
+    /// Custom shapes go in their own batch, drawn after every built in world shape and before screen space ones,
+    /// so they don't interleave with built in shapes no matter the order they're pushed in. Each run of shapes
+    /// pushed in a row with the same `V` is one draw call, so mixing layouts back and forth costs a draw call
+    /// (and a pipeline switch) every time the layout changes. Shapes of a layout that wasn't registered are skipped
+    pub fn push_custom_shape<V: VertexLayout + 'static>(
+        &mut self,
+        vertices: &[V],
+        indices: &[u16],
+    ) {
+        if !self.layout_pipelines.contains_key(&TypeId::of::<V>()) {
+            log::warn!(
+                "Skipping shape of unregistered vertex layout {}",
+                std::any::type_name::<V>()
+            );
+            return;
+        }
+        self.custom_shapes.push(vertices, indices);
+    }
+
+    /// Makes a shader for drawing some shapes differently from the rest, like water or a force field, through
+    /// [`State::push_shape_with_material`]. Any number of them can be made and used in the same frame, unlike
+    /// [`State::set_shader`] which replaces the shader for the whole world. `wgsl` takes the built in [`Vertex`] and
+    /// has the same bindings as the shader for [`State::set_shader`], so start from a copy of `shader.wgsl`.
+    ///
+    /// Fails with [`EngineError::ShaderCompile`] if the shader doesn't compile or doesn't fit the bindings
+    pub fn create_material(&mut self, wgsl: &str) -> Result<MaterialHandle, EngineError> {
+        let pipeline = self.compile_pipeline(wgsl, Vertex::desc())?;
+        self.materials.push(pipeline);
+        Ok(MaterialHandle(self.materials.len() - 1))
+    }
+
+    /// Draws a shape with the shader from [`State::create_material`]. Like [`State::push_custom_shape`], the
+    /// vertices go in as they are, without gamma correction or [`State::push_transform`], and are drawn after every
+    /// built in world shape. Shapes pushed in a row with the same material are one draw call.
+    /// Shapes with a material that was never made are skipped
+    pub fn push_shape_with_material(
+        &mut self,
+        material: MaterialHandle,
+        vertices: &[Vertex],
+        indices: &[u16],
+    ) {
+        if material.0 >= self.materials.len() {
+            log::warn!("Skipping shape with unknown material {:?}", material);
+            return;
+        }
+        self.custom_shapes
+            .push_to(CustomPipeline::Material(material), vertices, indices);
+    }
+
+    /// Compiles `wgsl` into a pipeline with the custom shader bindings, see [`State::set_shader`]
+    fn compile_pipeline(
+        &self,
+        wgsl: &str,
+        vertex: wgpu::VertexBufferLayout,
+    ) -> Result<wgpu::RenderPipeline, EngineError> {
+        let layout = self
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Custom Pipeline Layout"),
+                bind_group_layouts: &[
+                    &self.uniform_bind_group_layout,
+                    &self.texture_bind_group_layout,
+                    &self.custom_uniform.layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        // Catch what's wrong with the shader instead of letting wgpu panic over it
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = self
+            .device
+            .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: Some("Custom Shader"),
+                source: wgpu::ShaderSource::Wgsl(wgsl.into()),
+            });
+        let pipeline = shader::pipeline(
+            &self.device,
+            &layout,
+            &module,
+            self.config.format,
+            vertex,
+            self.sample_count,
+        );
+        match futures::executor::block_on(self.device.pop_error_scope()) {
+            Some(e) => Err(EngineError::ShaderCompile(e.to_string())),
+            None => Ok(pipeline),
+        }
+    }
+
+    /// Uploads `data` for the shader from [`State::set_shader`] to read at `[[group(2), binding(0)]]`, e.g. the
+    /// time for an animated effect. Call it as often as needed, it stays until it's set again.
+    /// `data` has to be laid out like the wgsl struct: `#[repr(C)]`, with fields lined up the way wgsl wants,
+    /// e.g. a `vec3<f32>` starting on a multiple of 16 bytes. It's padded out at the end to the 16 bytes wgsl rounds to
+    pub fn set_custom_uniform<T: bytemuck::Pod>(&mut self, data: T) {
+        self.custom_uniform
+            .set(&self.device, &self.queue, &shader::uniform_bytes(&data));
+    }
+
+    /// Grades the whole finished frame, text and ui included: `brightness` is added to every channel (0 leaves it
+    /// alone), `contrast` pushes colors away from middle gray (1 leaves it alone), and `saturation` pushes them
+    /// away from their own gray (0 is black and white, 1 leaves it alone).
+    ///
+    /// The scene is drawn into an offscreen target the size of the window, then drawn onto the frame through the
+    /// grading shader, which costs a full screen pass and the target's memory. Setting everything back to neutral
+    /// turns the pass off and frees the target
+    pub fn set_color_grade(&mut self, brightness: f32, contrast: f32, saturation: f32) {
+        self.color_grade = ColorGrade {
+            brightness,
+            contrast,
+            saturation,
+        };
+        if self.color_grade.is_identity() {
+            self.color_grade_pass.release();
+        } else {
+            self.color_grade_pass.set(&self.queue, self.color_grade);
+        }
+    }
+
+    pub fn color_grade(&self) -> ColorGrade {
+        self.color_grade
+    }
+
+    /// Turns vsync off or back on. Off presents frames as soon as they're done instead of waiting for the screen, which
+    /// tears and renders as fast as the gpu can go, so pair it with a cap, see [`crate::ContextBuilder::with_uncapped_fps`].
+    /// Surfaces that can't present without vsync fall back to it
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.config.present_mode = if vsync {
+            wgpu::PresentMode::Fifo
+        } else {
+            wgpu::PresentMode::Immediate
+        };
+        self.surface.configure(&self.device, &self.config);
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.config.present_mode == wgpu::PresentMode::Fifo
+    }
+
+    /// Lets pixels be read back out of rendered frames with [`State::request_pixel`], [`State::read_pixel`],
+    /// [`State::request_capture`] and [`State::capture_region`]. Off by default, since the frames have to be created
+    /// copyable, which some drivers make a little slower to draw into, and each one's copied for the blocking reads
+    pub fn enable_pixel_readback(&mut self, enabled: bool) {
+        self.config.usage = if enabled {
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC
+        } else {
+            self.readback.forget_frame();
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+        };
+        self.surface.configure(&self.device, &self.config);
+    }
+
+    /// Reads back the pixel at `x`, `y` (window pixels, from the top left) at the end of the next render, for
+    /// [`State::last_pixel`]. Needs [`State::enable_pixel_readback`].
+    ///
+    /// This is slow: the cpu stops and waits for the gpu to finish that whole frame before carrying on, instead of
+    /// the gpu running a frame behind like usual. Fine for a click, too slow to do every frame
+    pub fn request_pixel(&mut self, x: u32, y: u32) {
+        self.readback.request_pixel(x, y);
+    }
+
+    /// Color the pixel from the last [`State::request_pixel`] was drawn with, once a frame's been rendered after it.
+    /// `None` if the pixel was outside the window or the surface's format isn't 8 bit rgba or bgra.
+    ///
+    /// With gamma correction on, a handful of channel values (mostly very dark ones) can't be told apart from a
+    /// neighbour once they're on an sRGB surface: 1 to 5, 7, 8, 10, 15 and 238. Colors used for picking should stay
+    /// clear of them
+    pub fn last_pixel(&self) -> Option<Color> {
+        self.readback.last_pixel()
+    }
+
+    /// Color the pixel at `x`, `y` (window pixels, from the top left) in the last rendered frame was drawn with, read
+    /// right now, with the same caveat about dark channels as [`State::last_pixel`]. Needs [`State::enable_pixel_readback`], and [`Color::TRANSPARENT`] if nothing's been rendered with it
+    /// on yet, the pixel's outside the window or the surface's format isn't 8 bit rgba or bgra.
+    ///
+    /// This blocks until the gpu's finished everything queued so far, so it stalls even harder than
+    /// [`State::request_pixel`], which is the one to use if the answer can wait a frame
+    pub fn read_pixel(&self, x: u32, y: u32) -> Color {
+        self.readback
+            .read_region(
+                &self.device,
+                &self.queue,
+                rect::Rect::new(x as f32, y as f32, 1.0, 1.0),
+                self.config.format,
+            )
+            .map(|image| {
+                let [r, g, b, a] = image.get_pixel(0, 0).0;
+                readback::drawn_color(
+                    Color::from_rgb(r, g, b, a),
+                    self.config.format,
+                    self.batch.gamma_correction,
+                )
+            })
+            .unwrap_or(Color::TRANSPARENT)
+    }
+
+    /// Copies the part of the window inside `rect` (window pixels) out of the next rendered frame, for
+    /// [`State::take_capture`], e.g. for a save slot thumbnail. `rect` is clamped to the window, and partly covered
+    /// pixels on its edges are kept. Needs [`State::enable_pixel_readback`], and stalls the same way
+    /// [`State::request_pixel`] does
+    pub fn request_capture(&mut self, rect: rect::Rect) {
+        self.readback.request_capture(rect);
+    }
+
+    /// The image from the last [`State::request_capture`], once a frame's been rendered after it. Each capture is
+    /// only handed out once. `None` if the rect was entirely outside the window or, like [`State::last_pixel`],
+    /// the surface's format isn't 8 bit rgba or bgra
+    pub fn take_capture(&mut self) -> Option<::image::RgbaImage> {
+        self.readback.take_capture()
+    }
+
+    /// The part of the last rendered frame inside `rect` (window pixels), copied out right now, clamped to the
+    /// window the same way [`State::request_capture`] is. Needs [`State::enable_pixel_readback`], and comes back
+    /// empty if nothing's been rendered with it on yet, the rect's outside the window or the surface's format isn't
+    /// 8 bit rgba or bgra.
+    ///
+    /// Blocks like [`State::read_pixel`], so [`State::request_capture`] is better when it doesn't have to be now
+    pub fn capture_region(&self, rect: rect::Rect) -> ::image::RgbaImage {
+        self.readback
+            .read_region(&self.device, &self.queue, rect, self.config.format)
+            .unwrap_or_default()
+    }
+
+    /// Snaps the camera back to how it started, e.g. when changing scenes. See [`Camera::reset`]
+    pub fn reset_camera(&mut self) {
+        self.camera.reset();
+        self.update();
     }
-}
\ No newline at end of file
+
+    /// Keeps the camera from showing anything outside `bounds`, e.g. past the edges of the level.
+    /// The camera is clamped right before rendering, so it can be moved freely during the tick. `None` lets it go anywhere
+    pub fn set_camera_bounds(&mut self, bounds: Option<rect::Rect>) {
+        self.camera.bounds = bounds;
+    }
+
+    /// Keeps the world at `aspect` (width / height) no matter the window's shape. The world is scaled up or down to
+    /// fit as big as it can in the window, and the leftover bars on the sides are left the clear color.
+    /// `None`, the default, stretches the world to fill the whole window instead.
+    ///
+    /// Only the world is letterboxed: shapes drawn through the camera keep the same coordinates whatever
+    /// the window size. Text and the [`crate::ui::Ui`] are still in window pixels, from the top left of the window
+    pub fn set_fixed_aspect(&mut self, aspect: Option<f32>) {
+        self.fixed_aspect = aspect;
+        self.update_aspect_ratio();
+    }
+
+    /// Part of the window the world gets drawn in, in pixels. The whole window, unless the aspect is fixed
+    pub fn viewport(&self) -> rect::Rect {
+        let (width, height) = (self.size.width as f32, self.size.height as f32);
+        match self.fixed_aspect {
+            Some(aspect) => rect::Rect::letterbox(width, height, aspect),
+            None => rect::Rect::new(0.0, 0.0, width, height),
+        }
+    }
+
+    /// Where window pixel `(x, y)`, like the mouse position, is in the world, see [`Camera::screen_to_world`]
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        self.camera.screen_to_world(x, y, &self.viewport())
+    }
+
+    /// Where `(x, y)` in the world is in window pixels, see [`Camera::world_to_screen`]
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        self.camera.world_to_screen(x, y, &self.viewport())
+    }
+
+    /// Format of the surface being drawn to
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{gamma_correction, request_with_fallback, Background};
+
+    #[test]
+    fn gamma_correction_follows_the_surface_unless_forced() {
+        use wgpu::TextureFormat::{Bgra8Unorm, Bgra8UnormSrgb, Rgba16Float};
+
+        assert!(gamma_correction(Bgra8UnormSrgb, None));
+        assert!(!gamma_correction(Bgra8Unorm, None));
+        // Float surfaces are linear, but don't encode to sRGB either
+        assert!(!gamma_correction(Rgba16Float, None));
+
+        // Forcing wins either way, even against the format
+        assert!(!gamma_correction(Bgra8UnormSrgb, Some(false)));
+        assert!(gamma_correction(Bgra8Unorm, Some(true)));
+        assert!(gamma_correction(Bgra8UnormSrgb, Some(true)));
+    }
+
+    #[test]
+    fn falls_back_when_no_hardware_adapter() {
+        let requested = std::cell::RefCell::new(Vec::new());
+        let adapter = futures::executor::block_on(request_with_fallback(|fallback| {
+            requested.borrow_mut().push(fallback);
+            // Pretend only the software adapter is there
+            std::future::ready(if fallback { Some("software") } else { None })
+        }));
+        assert_eq!(adapter, Some("software"));
+        assert_eq!(*requested.borrow(), [false, true]);
+
+        let none: Option<()> =
+            futures::executor::block_on(request_with_fallback(|_| std::future::ready(None)));
+        assert_eq!(none, None);
+    }
+
+    #[test]
+    fn background_clears_to_black_by_default() {
+        let mut background = Background::default();
+        assert_eq!(
+            background.load_op(),
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+        );
+
+        background.clear(wgpu::Color::RED);
+        assert_eq!(background.load_op(), wgpu::LoadOp::Clear(wgpu::Color::RED));
+        background.reset();
+
+        background.clear_color = None;
+        assert_eq!(background.load_op(), wgpu::LoadOp::Load);
+    }
+}
@@ -0,0 +1,106 @@
+/// Sample count shapes get drawn with for `requested` samples a pixel, see
+/// [`crate::ContextBuilder::with_sample_count`]. Only 1 (no antialiasing) and 4 are supported, since 4 is what
+/// every adapter that can multisample at all can do, so anything above 1 is 4
+pub(crate) fn sample_count(requested: u32) -> u32 {
+    if requested > 1 {
+        4
+    } else {
+        1
+    }
+}
+
+/// Whether the device can draw `format` with `sample_count` samples a pixel. wgpu 0.12 can't be asked, so this
+/// tries making a tiny multisampled target and sees if it's rejected
+pub(crate) fn supported(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> bool {
+    if sample_count == 1 {
+        return true;
+    }
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    create_view(
+        device,
+        format,
+        winit::dpi::PhysicalSize::new(1, 1),
+        sample_count,
+    );
+    futures::executor::block_on(device.pop_error_scope()).is_none()
+}
+
+/// Multisampled texture the world and screen space shapes get drawn into, then resolved onto the frame from in
+/// the same pass. Text is drawn onto the frame after it, since the glyph brush can't multisample
+pub(crate) struct MsaaTarget {
+    view: wgpu::TextureView,
+    size: winit::dpi::PhysicalSize<u32>,
+    sample_count: u32,
+}
+
+impl MsaaTarget {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+        sample_count: u32,
+    ) -> Self {
+        Self {
+            view: create_view(device, format, size, sample_count),
+            size,
+            sample_count,
+        }
+    }
+
+    /// Makes the target again if the window changed size, it has to match the frame it's resolved onto
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        if self.size != size {
+            *self = Self::new(device, format, size, self.sample_count);
+        }
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+}
+
+fn create_view(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    size: winit::dpi::PhysicalSize<u32>,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("Multisample Target"),
+            size: wgpu::Extent3d {
+                width: size.width.max(1),
+                height: size.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::sample_count;
+
+    #[test]
+    fn sample_count_is_one_or_four() {
+        assert_eq!(sample_count(0), 1);
+        assert_eq!(sample_count(1), 1);
+        assert_eq!(sample_count(2), 4);
+        assert_eq!(sample_count(4), 4);
+        assert_eq!(sample_count(16), 4);
+    }
+}
@@ -0,0 +1,143 @@
+use super::batch::{Batch, TexturedPoint};
+use super::color::Color;
+use super::rect::Rect;
+use super::texture::TextureId;
+
+/// Handle to a nine patch loaded with [`super::State::load_nine_patch`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NinePatchId(pub usize);
+
+/// Widths of the edges of a nine patch, in pixels of the texture
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Insets {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Insets {
+    /// Same width on every side
+    pub fn uniform(width: f32) -> Self {
+        Self {
+            left: width,
+            top: width,
+            right: width,
+            bottom: width,
+        }
+    }
+}
+
+/// Texture cut into nine pieces by its `border`, for panels and buttons that can be any size.
+/// The corners are drawn as is, the edges stretch along their length, and the middle stretches both ways
+pub struct NinePatch {
+    pub texture: TextureId,
+    /// Size of the texture in pixels
+    pub width: u32,
+    pub height: u32,
+    pub border: Insets,
+}
+
+impl NinePatch {
+    /// Pushes the nine pieces stretched over `dst`. If `dst` is too small to fit the borders,
+    /// they're shrunk to fit
+    pub fn draw_into(&self, batch: &mut Batch, dst: Rect, color: Color) {
+        let (left, right) = fit(self.border.left, self.border.right, dst.width);
+        let (top, bottom) = fit(self.border.top, self.border.bottom, dst.height);
+        let xs = [dst.x, dst.x + left, dst.right() - right, dst.right()];
+        let ys = [dst.y, dst.y + top, dst.bottom() - bottom, dst.bottom()];
+
+        let (width, height) = (self.width as f32, self.height as f32);
+        let us = [
+            0.0,
+            self.border.left / width,
+            1.0 - self.border.right / width,
+            1.0,
+        ];
+        let vs = [
+            0.0,
+            self.border.top / height,
+            1.0 - self.border.bottom / height,
+            1.0,
+        ];
+
+        let mut points: Vec<TexturedPoint> = Vec::with_capacity(16);
+        for row in 0..4 {
+            for column in 0..4 {
+                points.push(((xs[column], ys[row]), (us[column], vs[row])));
+            }
+        }
+        let mut indices = Vec::with_capacity(54);
+        for row in 0..3 {
+            for column in 0..3 {
+                let top_left = row * 4 + column;
+                let (top_right, bot_left, bot_right) = (top_left + 1, top_left + 4, top_left + 5);
+                indices.extend_from_slice(&[
+                    top_left, bot_left, bot_right, bot_right, top_right, top_left,
+                ]);
+            }
+        }
+
+        batch.draw_textured_polygon(self.texture, &points, &indices, color);
+    }
+}
+
+/// Both borders along one axis, scaled down together if they don't fit in `length`
+fn fit(start: f32, end: f32, length: f32) -> (f32, f32) {
+    let total = start + end;
+    if total > length && total > 0.0 {
+        let scale = length / total;
+        (start * scale, end * scale)
+    } else {
+        (start, end)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Insets, NinePatch};
+    use crate::graphics::batch::Batch;
+    use crate::graphics::color::Color;
+    use crate::graphics::rect::Rect;
+    use crate::graphics::texture::TextureId;
+
+    fn panel() -> NinePatch {
+        NinePatch {
+            texture: TextureId(1),
+            width: 32,
+            height: 32,
+            border: Insets::uniform(8.0),
+        }
+    }
+
+    #[test]
+    fn stored_border_keeps_corners_unstretched() {
+        let mut batch = Batch::default();
+        panel().draw_into(
+            &mut batch,
+            Rect::new(100.0, 50.0, 200.0, 80.0),
+            Color::from_rgb(255, 255, 255, 255),
+        );
+
+        assert_eq!(batch.vertices.len(), 16);
+        assert_eq!(batch.indices.len(), 54);
+        // Second vertex is where the top left corner ends, 8 pixels in just like in the texture
+        assert_eq!(batch.vertices[1].position, [108.0, 50.0, 0.0]);
+        assert_eq!(batch.vertices[1].tex_coords, [0.25, 0.0]);
+        // Inner corner of the bottom right piece
+        assert_eq!(batch.vertices[10].position, [292.0, 122.0, 0.0]);
+        assert_eq!(batch.vertices[10].tex_coords, [0.75, 0.75]);
+    }
+
+    #[test]
+    fn borders_shrink_when_too_small() {
+        let mut batch = Batch::default();
+        panel().draw_into(
+            &mut batch,
+            Rect::new(0.0, 0.0, 8.0, 100.0),
+            Color::from_rgb(255, 255, 255, 255),
+        );
+        assert_eq!(batch.vertices[1].position[0], 4.0);
+        assert_eq!(batch.vertices[2].position[0], 4.0);
+    }
+}
@@ -0,0 +1,226 @@
+use std::ops::Range;
+
+use image::RgbaImage;
+
+use super::buffers::Vertex;
+use super::color::Color;
+use super::rect::Rect;
+use super::texture::TextureId;
+
+/// Handle to a palette loaded with [`super::State::load_palette`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PaletteId(pub usize);
+
+/// Most colors a palette can have, since an index has to fit in a sprite's red channel
+pub const MAX_COLORS: usize = 256;
+
+/// Sprite drawn with [`super::State::draw_sprite_paletted`] this frame
+pub(crate) struct PalettedDraw {
+    pub texture: TextureId,
+    pub palette: PaletteId,
+    pub indices: Range<u32>,
+}
+
+/// Paletted sprites to draw this frame, in order. They need their own pipeline, so they're kept out of the
+/// [`super::batch::Batch`] and drawn together after it
+#[derive(Default)]
+pub(crate) struct PalettedSprites {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub draws: Vec<PalettedDraw>,
+}
+
+impl PalettedSprites {
+    /// The whole sprite stretched over `dst`, tinted by `color`
+    pub fn push(&mut self, texture: TextureId, palette: PaletteId, dst: Rect, color: [f32; 4]) {
+        let offset = self.vertices.len() as u32;
+        let corners = [
+            ((dst.x, dst.y), (0.0, 0.0)),
+            ((dst.right(), dst.y), (1.0, 0.0)),
+            ((dst.x, dst.bottom()), (0.0, 1.0)),
+            ((dst.right(), dst.bottom()), (1.0, 1.0)),
+        ];
+        self.vertices
+            .extend(corners.iter().map(|&((x, y), (u, v))| Vertex {
+                position: [x, y, 0.0],
+                color,
+                tex_coords: [u, v],
+            }));
+        let start = self.indices.len() as u32;
+        self.indices
+            .extend([0, 2, 3, 3, 1, 0].iter().map(|i| i + offset));
+        self.draws.push(PalettedDraw {
+            texture,
+            palette,
+            indices: start..self.indices.len() as u32,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.draws.clear();
+    }
+}
+
+/// The palette as the one pixel tall texture the shader looks colors up in. Colors past [`MAX_COLORS`] can't be
+/// indexed so they're left out, and an empty palette gets a single transparent color so there's something to look up
+pub(crate) fn palette_image(colors: &[Color]) -> RgbaImage {
+    let colors = match colors.len() {
+        0 => &[Color::from_rgb(0, 0, 0, 0)][..],
+        len => &colors[..len.min(MAX_COLORS)],
+    };
+    let mut image = RgbaImage::new(colors.len() as u32, 1);
+    for (pixel, color) in image.pixels_mut().zip(colors) {
+        *pixel = image::Rgba([color.r, color.g, color.b, color.a]);
+    }
+    image
+}
+
+/// Pipeline paletted sprites are drawn with. The sprite is bound like any other texture, and its palette,
+/// bound just the same, one group after it
+pub(crate) fn pipeline(
+    device: &wgpu::Device,
+    uniform_layout: &wgpu::BindGroupLayout,
+    texture_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+        label: Some("Palette Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("palette.wgsl").into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Palette Pipeline Layout"),
+        bind_group_layouts: &[uniform_layout, texture_layout, texture_layout],
+        push_constant_ranges: &[],
+    });
+    super::shader::pipeline(
+        device,
+        &layout,
+        &shader,
+        format,
+        Vertex::desc(),
+        sample_count,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::{palette_image, pipeline, PaletteId, PalettedSprites, MAX_COLORS};
+    use crate::graphics::buffers::Uniforms;
+    use crate::graphics::color::Color;
+    use crate::graphics::headless::{Gpu, FORMAT};
+    use crate::graphics::rect::Rect;
+    use crate::graphics::texture::{Texture, TextureId};
+
+    /// `sprite` drawn over the whole of a target its size with `palette`, on the real pipeline
+    fn render(gpu: &Gpu, sprite: &RgbaImage, palette: &[Color]) -> RgbaImage {
+        let (width, height) = sprite.dimensions();
+        let uniform_layout = Uniforms::bind_group_layout(&gpu.device);
+        let texture_layout = Texture::bind_group_layout(&gpu.device);
+        let pipeline = pipeline(&gpu.device, &uniform_layout, &texture_layout, FORMAT, 1);
+        // Neither is gamma corrected, so the indices and colors come through exactly
+        let upload = |image: RgbaImage| {
+            Texture::from_image(
+                &gpu.device,
+                &gpu.queue,
+                &texture_layout,
+                &DynamicImage::ImageRgba8(image),
+                wgpu::TextureFormat::Rgba8Unorm,
+                None,
+            )
+            .unwrap()
+        };
+        let sprite = upload(sprite.clone());
+        let palette = upload(palette_image(palette));
+
+        let mut sprites = PalettedSprites::default();
+        sprites.push(
+            TextureId(1),
+            PaletteId(0),
+            Rect::new(0.0, 0.0, width as f32, height as f32),
+            [1.0; 4],
+        );
+        let target = gpu.target(width, height);
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let uniforms = gpu.screen_uniforms(&uniform_layout, width, height);
+        gpu.draw(
+            &view,
+            wgpu::Color::TRANSPARENT,
+            &pipeline,
+            &[&uniforms, &sprite.bind_group, &palette.bind_group],
+            &sprites.vertices,
+            &sprites.indices,
+        );
+        gpu.capture(
+            &target,
+            width,
+            height,
+            Rect::new(0.0, 0.0, width as f32, height as f32),
+        )
+    }
+
+    #[test]
+    fn swapping_palettes_swaps_colors() {
+        let gpu = match Gpu::new() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        // Index 0 on the left half, 1 on the right
+        let sprite = RgbaImage::from_fn(4, 2, |x, _| Rgba([(x / 2) as u8, 0, 0, 255]));
+        let red = Color::from_rgb(255, 0, 0, 255);
+        let blue = Color::from_rgb(0, 0, 255, 255);
+
+        let home = render(&gpu, &sprite, &[red, blue]);
+        let away = render(&gpu, &sprite, &[blue, red]);
+        assert_eq!(home.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+        assert_eq!(home.get_pixel(3, 1), &Rgba([0, 0, 255, 255]));
+        assert_eq!(away.get_pixel(0, 0), &Rgba([0, 0, 255, 255]));
+        assert_eq!(away.get_pixel(3, 1), &Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn indices_past_the_palette_get_the_last_color() {
+        let gpu = match Gpu::new() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let sprite = RgbaImage::from_pixel(1, 1, Rgba([7, 0, 0, 255]));
+        let green = Color::from_rgb(0, 255, 0, 255);
+        let drawn = render(&gpu, &sprite, &[Color::BLACK, green]);
+        assert_eq!(drawn.get_pixel(0, 0), &Rgba([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn palette_texture_is_one_row_of_at_most_256() {
+        assert_eq!(palette_image(&[]).dimensions(), (1, 1));
+        assert_eq!(palette_image(&[]).get_pixel(0, 0), &Rgba([0, 0, 0, 0]));
+        let big = vec![Color::WHITE; 300];
+        assert_eq!(palette_image(&big).dimensions(), (MAX_COLORS as u32, 1));
+    }
+
+    #[test]
+    fn each_sprite_is_its_own_draw() {
+        let mut sprites = PalettedSprites::default();
+        sprites.push(
+            TextureId(1),
+            PaletteId(0),
+            Rect::new(0.0, 0.0, 16.0, 16.0),
+            [1.0; 4],
+        );
+        sprites.push(
+            TextureId(1),
+            PaletteId(1),
+            Rect::new(32.0, 0.0, 16.0, 16.0),
+            [1.0; 4],
+        );
+        assert_eq!(sprites.vertices.len(), 8);
+        assert_eq!(sprites.draws[1].indices, 6..12);
+        assert_eq!(sprites.indices[6..], [4, 6, 7, 7, 5, 4]);
+        assert_eq!(sprites.vertices[7].position, [48.0, 16.0, 0.0]);
+        assert_eq!(sprites.vertices[7].tex_coords, [1.0, 1.0]);
+    }
+}
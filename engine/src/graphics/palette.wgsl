@@ -0,0 +1,45 @@
+// Palette swapped sprites, the sprite's red channel is an index into the palette instead of a color
+struct Uniforms {
+    view: mat4x4<f32>;
+    model: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniforms: Uniforms;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] color: vec4<f32>;
+    [[location(2)]] tex_coords: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = uniforms.view * uniforms.model * vec4<f32>(model.position, 1.0);
+    out.color = model.color;
+    out.tex_coords = model.tex_coords;
+    return out;
+}
+
+[[group(1), binding(0)]]
+var t_index: texture_2d<f32>;
+[[group(1), binding(1)]]
+var s_index: sampler;
+// One pixel tall, a color per index
+[[group(2), binding(0)]]
+var t_palette: texture_2d<f32>;
+
+// Indices past the end of the palette get its last color
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let index = i32(round(textureSample(t_index, s_index, in.tex_coords).r * 255.0));
+    let last = textureDimensions(t_palette).x - 1;
+    return textureLoad(t_palette, vec2<i32>(min(index, last), 0), 0) * in.color;
+}
@@ -0,0 +1,510 @@
+use super::color::Color;
+use super::rect::Rect;
+
+/// Copies pixels out of rendered frames: single pixels for picking things by color (draw each one in its own
+/// color, then read back the pixel under the mouse to see which it was), and whole regions for screenshots.
+///
+/// The copies are made right before the frame is presented, and the cpu waits for the gpu to finish the whole
+/// frame before reading them, so every read stalls the pipeline for that frame. Fine for a click, not every frame.
+/// Each frame is also kept in `last_frame`, so it can be read from straight away with [`Readback::read_region`]
+#[derive(Default)]
+pub(crate) struct Readback {
+    pixel: Option<(u32, u32)>,
+    capture: Option<Rect>,
+    /// Copies recorded this frame, read after submitting
+    pending: Vec<Pending>,
+    last_pixel: Option<Color>,
+    captured: Option<image::RgbaImage>,
+    /// Copy of the last rendered frame and its size
+    last_frame: Option<(wgpu::Texture, winit::dpi::PhysicalSize<u32>)>,
+}
+
+/// A region being copied into `buffer` this frame
+struct Pending {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    /// A read for [`Readback::last_pixel`] instead of a capture
+    pixel: bool,
+}
+
+impl Readback {
+    pub fn request_pixel(&mut self, x: u32, y: u32) {
+        self.pixel = Some((x, y));
+    }
+
+    pub fn request_capture(&mut self, rect: Rect) {
+        self.capture = Some(rect);
+    }
+
+    /// Color from the last finished pixel read
+    pub fn last_pixel(&self) -> Option<Color> {
+        self.last_pixel
+    }
+
+    /// The last finished capture, handed over so it doesn't have to be cloned
+    pub fn take_capture(&mut self) -> Option<image::RgbaImage> {
+        self.captured.take()
+    }
+
+    /// Records copying what was requested out of `texture`, a `size` frame, and keeping the whole frame for
+    /// [`Readback::read_region`]. Call after everything's been drawn
+    pub fn copy(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        size: winit::dpi::PhysicalSize<u32>,
+        format: wgpu::TextureFormat,
+    ) {
+        if !matches!(&self.last_frame, Some((_, last_size)) if *last_size == size) {
+            self.last_frame = Some((create_frame_copy(device, size, format), size));
+        }
+        if let Some((last_frame, _)) = &self.last_frame {
+            encoder.copy_texture_to_texture(
+                texture.as_image_copy(),
+                last_frame.as_image_copy(),
+                wgpu::Extent3d {
+                    width: size.width,
+                    height: size.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        if let Some((x, y)) = self.pixel.take() {
+            match clamp_region(Rect::new(x as f32, y as f32, 1.0, 1.0), size) {
+                Some(region) => self
+                    .pending
+                    .push(copy_region(device, encoder, texture, region, true)),
+                None => self.last_pixel = None,
+            }
+        }
+        if let Some(rect) = self.capture.take() {
+            match clamp_region(rect, size) {
+                Some(region) => self
+                    .pending
+                    .push(copy_region(device, encoder, texture, region, false)),
+                None => self.captured = None,
+            }
+        }
+    }
+
+    /// Drops the copy of the last frame, when readback's turned off
+    pub fn forget_frame(&mut self) {
+        self.last_frame = None;
+    }
+
+    /// Reads the part of the last rendered frame inside `rect` right away, waiting for the gpu to finish copying it.
+    /// `None` if nothing's been rendered with readback enabled yet, `rect` is outside the frame, or `format` isn't
+    /// 8 bit rgba or bgra
+    pub fn read_region(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rect: Rect,
+        format: wgpu::TextureFormat,
+    ) -> Option<image::RgbaImage> {
+        let (texture, size) = self.last_frame.as_ref()?;
+        let region = clamp_region(rect, *size)?;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        let pending = copy_region(device, &mut encoder, texture, region, false);
+        queue.submit(Some(encoder.finish()));
+
+        let mapped = pending.buffer.slice(..).map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        futures::executor::block_on(mapped).ok()?;
+        let image = decode_region(
+            &pending.buffer.slice(..).get_mapped_range(),
+            pending.width,
+            pending.height,
+            format,
+        );
+        image
+    }
+
+    /// Call after submitting. Waits for the copies, if there were any, and reads the pixels out of them.
+    /// `gamma_correction` is whether the frame's shapes were drawn gamma corrected, see [`drawn_color`]
+    pub fn finish(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        gamma_correction: bool,
+    ) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let mapping: Vec<_> = self
+            .pending
+            .iter()
+            .map(|pending| pending.buffer.slice(..).map_async(wgpu::MapMode::Read))
+            .collect();
+        device.poll(wgpu::Maintain::Wait);
+
+        for (pending, mapped) in self.pending.drain(..).zip(mapping) {
+            let image = match futures::executor::block_on(mapped) {
+                Ok(()) => decode_region(
+                    &pending.buffer.slice(..).get_mapped_range(),
+                    pending.width,
+                    pending.height,
+                    format,
+                ),
+                Err(_) => None,
+            };
+            if pending.pixel {
+                self.last_pixel = image.map(|image| {
+                    let [r, g, b, a] = image.get_pixel(0, 0).0;
+                    drawn_color(Color::from_rgb(r, g, b, a), format, gamma_correction)
+                });
+            } else {
+                self.captured = image;
+            }
+        }
+    }
+}
+
+/// `(x, y, width, height)` of the whole pixels `rect` covers inside a `size` frame, `None` if that's none of them
+pub(crate) fn clamp_region(
+    rect: Rect,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> Option<(u32, u32, u32, u32)> {
+    let left = rect.x.floor().max(0.0);
+    let top = rect.y.floor().max(0.0);
+    let right = (rect.x + rect.width).ceil().min(size.width as f32);
+    let bottom = (rect.y + rect.height).ceil().min(size.height as f32);
+    if right <= left || bottom <= top {
+        return None;
+    }
+    Some((
+        left as u32,
+        top as u32,
+        (right - left) as u32,
+        (bottom - top) as u32,
+    ))
+}
+
+fn create_frame_copy(
+    device: &wgpu::Device,
+    size: winit::dpi::PhysicalSize<u32>,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Readback Frame"),
+        size: wgpu::Extent3d {
+            width: size.width.max(1),
+            height: size.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+    })
+}
+
+fn copy_region(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    (x, y, width, height): (u32, u32, u32, u32),
+    pixel: bool,
+) -> Pending {
+    let bytes_per_row = padded_bytes_per_row(width, 4);
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: bytes_per_row as u64 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d { x, y, z: 0 },
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    Pending {
+        buffer,
+        width,
+        height,
+        pixel,
+    }
+}
+
+/// Bytes per row of a texture to buffer copy, padded up to the 256 bytes wgpu needs them aligned to
+pub(crate) fn padded_bytes_per_row(width: u32, bytes_per_pixel: u32) -> u32 {
+    let unpadded = width * bytes_per_pixel;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    unpadded.div_ceil(align) * align
+}
+
+/// The first pixel in `bytes`, stored as `format`, as it shows on screen. Only 8 bit rgba and bgra are understood.
+/// This is what screenshots want, but not always the `Color` it was drawn with, see [`drawn_color`]
+pub(crate) fn decode_pixel(bytes: &[u8], format: wgpu::TextureFormat) -> Option<Color> {
+    use wgpu::TextureFormat::*;
+    let pixel = bytes.get(..4)?;
+    match format {
+        Rgba8Unorm | Rgba8UnormSrgb => {
+            Some(Color::from_rgb(pixel[0], pixel[1], pixel[2], pixel[3]))
+        }
+        Bgra8Unorm | Bgra8UnormSrgb => {
+            Some(Color::from_rgb(pixel[2], pixel[1], pixel[0], pixel[3]))
+        }
+        _ => None,
+    }
+}
+
+/// The `Color` a pixel that shows as `shown` on a `format` surface was drawn with, for picking.
+///
+/// With gamma correction on, colors are made linear with a 2.2 power curve on the way into the vertices, but an
+/// sRGB surface encodes them back with the sRGB curve, which isn't quite the same. This undoes both. All but 10
+/// channel values come back exactly, the ones that don't (1 to 5, 7, 8, 10, 15 and 238) land on the same 8 bits as
+/// a neighbour on the way out, so colors for picking should stay clear of them. Only a surface that isn't sRGB,
+/// with [`crate::ContextBuilder::with_gamma_correction`] off, stores every channel as is
+pub(crate) fn drawn_color(
+    shown: Color,
+    format: wgpu::TextureFormat,
+    gamma_correction: bool,
+) -> Color {
+    let srgb = format.describe().srgb;
+    let channel = |c: u8| {
+        let stored = c as f64 / 255.0;
+        let linear = if srgb { srgb_to_linear(stored) } else { stored };
+        let drawn = if gamma_correction {
+            linear.powf(1.0 / 2.2)
+        } else {
+            linear
+        };
+        (drawn * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+    Color::from_rgb(
+        channel(shown.r),
+        channel(shown.g),
+        channel(shown.b),
+        shown.a,
+    )
+}
+
+/// The sRGB transfer function an sRGB surface stores colors with, undone
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// A `width` by `height` image out of rows padded to [`padded_bytes_per_row`], stored as `format`
+pub(crate) fn decode_region(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> Option<image::RgbaImage> {
+    let bytes_per_row = padded_bytes_per_row(width, 4) as usize;
+    let mut image = image::RgbaImage::new(width, height);
+    for (y, row) in bytes
+        .chunks(bytes_per_row)
+        .take(height as usize)
+        .enumerate()
+    {
+        for x in 0..width {
+            let color = decode_pixel(row.get(x as usize * 4..)?, format)?;
+            image.put_pixel(
+                x,
+                y as u32,
+                image::Rgba([color.r, color.g, color.b, color.a]),
+            );
+        }
+    }
+    Some(image)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{clamp_region, decode_pixel, decode_region, drawn_color, padded_bytes_per_row};
+    use crate::graphics::batch::Batch;
+    use crate::graphics::buffers::{Uniforms, Vertex};
+    use crate::graphics::color::Color;
+    use crate::graphics::headless::{Gpu, FORMAT};
+    use crate::graphics::rect::Rect;
+    use crate::graphics::texture::Texture;
+    use winit::dpi::PhysicalSize;
+
+    #[test]
+    fn rows_pad_to_copy_alignment() {
+        assert_eq!(padded_bytes_per_row(1, 4), 256);
+        assert_eq!(padded_bytes_per_row(64, 4), 256);
+        assert_eq!(padded_bytes_per_row(65, 4), 512);
+    }
+
+    /// What an sRGB surface stores for a linear `c`
+    fn linear_to_srgb(c: f64) -> f64 {
+        if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    }
+
+    #[test]
+    fn picked_pixel_is_the_color_it_was_drawn_with() {
+        let drawn = Color::from_rgb(255, 128, 64, 200);
+        // Gamma corrected into the vertices, then encoded by the sRGB surface
+        let linear = wgpu::Color::from(drawn);
+        let stored = |c: f64| (linear_to_srgb(c) * 255.0).round() as u8;
+        let (r, g, b) = (stored(linear.r), stored(linear.g), stored(linear.b));
+        // The two curves don't quite cancel out, so what's on screen isn't what was drawn
+        assert_ne!((r, g, b), (255, 128, 64));
+
+        // A 3x2 bgra frame laid out the way a copy to a buffer leaves it, each row padded to 256 bytes, with the
+        // pixel at (2, 1) drawn
+        let row = padded_bytes_per_row(3, 4) as usize;
+        let mut bytes = vec![0u8; row * 2];
+        let offset = row + 2 * 4;
+        bytes[offset..offset + 4].copy_from_slice(&[b, g, r, 200]);
+
+        let format = wgpu::TextureFormat::Bgra8UnormSrgb;
+        let shown = decode_pixel(&bytes[offset..], format).unwrap();
+        assert_eq!(shown, Color::from_rgb(r, g, b, 200));
+        assert_eq!(drawn_color(shown, format, true), drawn);
+        assert_eq!(
+            decode_pixel(&bytes[offset..], wgpu::TextureFormat::Rgba8Unorm),
+            Some(Color::from_rgb(b, g, r, 200))
+        );
+        assert_eq!(
+            decode_pixel(&bytes[offset..], wgpu::TextureFormat::Rgba16Float),
+            None
+        );
+
+        // A plain surface with gamma correction off stores colors as they are
+        assert_eq!(
+            drawn_color(drawn, wgpu::TextureFormat::Rgba8Unorm, false),
+            drawn
+        );
+    }
+
+    #[test]
+    fn captures_known_region_out_of_padded_rows() {
+        // The frame's 800x600, and the region sticks out past the bottom right, so only 70x20 of it is kept
+        let size = PhysicalSize::new(800, 600);
+        let (x, y, width, height) =
+            clamp_region(Rect::new(730.0, 580.0, 100.0, 100.0), size).unwrap();
+        assert_eq!((x, y, width, height), (730, 580, 70, 20));
+        assert_eq!(
+            clamp_region(Rect::new(-10.5, -10.0, 11.0, 20.0), size),
+            Some((0, 0, 1, 10))
+        );
+        assert_eq!(clamp_region(Rect::new(900.0, 0.0, 10.0, 10.0), size), None);
+
+        // 70 pixels wide is 280 bytes, padded to 512: green everywhere but a red pixel at (69, 19)
+        let row = padded_bytes_per_row(width, 4) as usize;
+        assert_eq!(row, 512);
+        let mut bytes = vec![0u8; row * height as usize];
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let offset = y * row + x * 4;
+                bytes[offset..offset + 4].copy_from_slice(&[0, 255, 0, 255]);
+            }
+        }
+        let last = 19 * row + 69 * 4;
+        bytes[last..last + 4].copy_from_slice(&[0, 0, 255, 255]);
+
+        let image =
+            decode_region(&bytes, width, height, wgpu::TextureFormat::Bgra8UnormSrgb).unwrap();
+        assert_eq!(image.dimensions(), (70, 20));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(68, 19).0, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(69, 19).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn captures_a_drawn_quad() {
+        let gpu = match Gpu::new() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let (width, height) = (64, 48);
+        let uniform_layout = Uniforms::bind_group_layout(&gpu.device);
+        let texture_layout = Texture::bind_group_layout(&gpu.device);
+        let shader = gpu
+            .device
+            .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+            });
+        let layout = gpu
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&uniform_layout, &texture_layout],
+                push_constant_ranges: &[],
+            });
+        let pipeline = crate::graphics::shader::pipeline(
+            &gpu.device,
+            &layout,
+            &shader,
+            FORMAT,
+            Vertex::desc(),
+            1,
+        );
+        let white = Texture::from_image(
+            &gpu.device,
+            &gpu.queue,
+            &texture_layout,
+            &image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                1,
+                1,
+                image::Rgba([255; 4]),
+            )),
+            FORMAT,
+            None,
+        )
+        .unwrap();
+        let uniforms = gpu.screen_uniforms(&uniform_layout, width, height);
+        let target = gpu.target(width, height);
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // A red quad from (10, 8) to (30, 20) on green, not gamma corrected so the colors come through exactly
+        let mut batch = Batch::default();
+        batch.gamma_correction = false;
+        batch.draw_rectangle(10.0, 8.0, 20.0, 12.0, Color::RED);
+        gpu.draw(
+            &view,
+            wgpu::Color::GREEN,
+            &pipeline,
+            &[&uniforms, &white.bind_group],
+            &batch.vertices,
+            &batch.indices,
+        );
+
+        // 70 wide is more than a padded row of 256 bytes, and it hangs off the right, so it's clamped to 59x30
+        let image = gpu.capture(&target, width, height, Rect::new(5.0, 4.0, 70.0, 30.0));
+        assert_eq!(image.dimensions(), (59, 30));
+        let red = [255, 0, 0, 255];
+        let green = [0, 255, 0, 255];
+        // The quad's corners, 5 and 4 in from the region's
+        assert_eq!(image.get_pixel(5, 4).0, red);
+        assert_eq!(image.get_pixel(24, 15).0, red);
+        assert_eq!(image.get_pixel(4, 4).0, green);
+        assert_eq!(image.get_pixel(25, 15).0, green);
+        assert_eq!(image.get_pixel(5, 16).0, green);
+        assert_eq!(image.get_pixel(58, 29).0, green);
+    }
+}
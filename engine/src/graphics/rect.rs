@@ -0,0 +1,72 @@
+/// Axis aligned rectangle, with `(x, y)` being the top left corner
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Largest rectangle with the aspect ratio `aspect` (width / height) that fits centered in a `width` x `height`
+    /// area, e.g. the part of a window a fixed aspect game is drawn in, with the leftover bars on either side
+    pub fn letterbox(width: f32, height: f32, aspect: f32) -> Self {
+        let (fit_width, fit_height) = if width / height > aspect {
+            (height * aspect, height)
+        } else {
+            (width, width / aspect)
+        };
+        Rect::new(
+            (width - fit_width) / 2.0,
+            (height - fit_height) / 2.0,
+            fit_width,
+            fit_height,
+        )
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    /// Whether the point lies inside the rectangle, edges included
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x <= self.right() && y >= self.y && y <= self.bottom()
+    }
+
+    /// Whether the two rectangles share any area, touching edges count
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.x <= other.right()
+            && other.x <= self.right()
+            && self.y <= other.bottom()
+            && other.y <= self.bottom()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Rect;
+
+    #[test]
+    fn wide_game_in_narrow_window_letterboxes() {
+        let viewport = Rect::letterbox(800.0, 600.0, 16.0 / 9.0);
+        assert_eq!(viewport, Rect::new(0.0, 75.0, 800.0, 450.0));
+        // Scaled the same on both axes, so nothing gets stretched
+        assert_eq!(viewport.width / 16.0, viewport.height / 9.0);
+
+        let pillarboxed = Rect::letterbox(1600.0, 600.0, 4.0 / 3.0);
+        assert_eq!(pillarboxed, Rect::new(400.0, 0.0, 800.0, 600.0));
+    }
+}
@@ -1,8 +1,22 @@
 use wgpu::util::DeviceExt;
 
+use super::buffers::Uniforms;
+use super::custom_shape::CustomPipeline;
+use super::texture::TextureId;
 use super::State;
+
+/// Numbers about a rendered frame, from [`State::stats`]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct RenderStats {
+    /// Number of batches, i.e. draw calls, the shapes were submitted in
+    pub draw_calls: usize,
+    pub vertices: usize,
+    pub indices: usize,
+}
+
 impl State {
     pub fn update(&mut self) {
+        self.camera.clamp_to_bounds();
         self.uniforms.update_view_proj(&self.camera);
         self.queue.write_buffer(
             &self.uniform_buffer,
@@ -12,25 +26,48 @@ impl State {
     }
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let frame = self.surface.get_current_texture()?;
-        let view = frame
+        let frame_view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        // Graded frames get drawn offscreen first, then graded onto the frame at the end
+        let grading = !self.color_grade.is_identity();
+        if grading {
+            self.color_grade_pass
+                .prepare(&self.device, self.config.format, self.size);
+        }
+        let view = match self.color_grade_pass.view() {
+            Some(target) if grading => target,
+            _ => &frame_view,
+        };
+        // Multisampled shapes are drawn into their own target, and resolved onto the view at the end of the pass
+        if let Some(msaa) = &mut self.msaa {
+            msaa.prepare(&self.device, self.config.format, self.size);
+        }
+        let (target, resolve_target) = match &self.msaa {
+            Some(msaa) => (msaa.view(), Some(view)),
+            None => (view, None),
+        };
+        // Resolving writes over the whole view, so text can only go under shapes without multisampling
+        let text_first = self.text_layer.text_first() && self.msaa.is_none();
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.begin(&self.device, &mut encoder);
+        }
 
         {
             {
-                if self.background.should_clear {
+                if let wgpu::LoadOp::Clear(color) = self.background.load_op() {
                     encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                         label: None,
                         color_attachments: &[wgpu::RenderPassColorAttachment {
-                            view: &view,
+                            view: target,
                             resolve_target: None,
                             ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(self.background.color),
+                                load: wgpu::LoadOp::Clear(color),
                                 store: true,
                             },
                         }],
@@ -38,33 +75,138 @@ impl State {
                     });
                     self.background.reset();
                 }
+                if text_first {
+                    self.font_interface
+                        .draw(&self.device, &mut encoder, self.size, view);
+                }
+
+                self.batch.sort_draws();
+                if self.batch.texture_batching {
+                    self.batch.bucket_by_texture();
+                }
+                let custom = self.custom_shapes.stats();
+                self.stats = RenderStats {
+                    draw_calls: self.batch.draws.len()
+                        + self.screen_batch.draws.len()
+                        + self.mesh_draws.len()
+                        + self.paletted_sprites.draws.len()
+                        + usize::from(!self.sdf_circles.indices.is_empty())
+                        + custom.draw_calls,
+                    vertices: self.batch.vertices.len()
+                        + self.screen_batch.vertices.len()
+                        + self.paletted_sprites.vertices.len()
+                        + self.sdf_circles.vertices.len()
+                        + custom.vertices,
+                    indices: self.batch.indices.len()
+                        + self.screen_batch.indices.len()
+                        + self.paletted_sprites.indices.len()
+                        + self.sdf_circles.indices.len()
+                        + custom.indices,
+                };
+                for (id, _) in &self.mesh_draws {
+                    let mesh = &self.meshes[id.0];
+                    self.stats.vertices += mesh.vertices;
+                    self.stats.indices += mesh.indices as usize;
+                }
 
-                {
-                    // Not sure which one is better
-                    self.vertex_buffer =
-                        self.device
-                            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                                label: None,
-                                contents: bytemuck::cast_slice(&self.vertices),
-                                usage: wgpu::BufferUsages::VERTEX,
-                            });
+                // Each mesh draw gets its own transform, which the shader only takes through the uniforms
+                let mesh_bind_groups: Vec<wgpu::BindGroup> = self
+                    .mesh_draws
+                    .iter()
+                    .map(|(_, transform)| {
+                        let uniforms = self.uniforms.transformed(transform.matrix());
+                        let buffer =
+                            self.device
+                                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                    label: Some("Mesh Uniform Buffer"),
+                                    contents: bytemuck::cast_slice(&[uniforms]),
+                                    usage: wgpu::BufferUsages::UNIFORM,
+                                });
+                        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                            layout: &self.uniform_bind_group_layout,
+                            entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: buffer.as_entire_binding(),
+                            }],
+                            label: Some("Mesh Uniform Bind Group"),
+                        })
+                    })
+                    .collect();
+
+                // Written into the buffers from last frame, which are only reallocated when they're outgrown
+                let (device, queue) = (&self.device, &self.queue);
+                if !self.paletted_sprites.draws.is_empty() {
+                    let sprites = &self.paletted_sprites;
+                    self.paletted_vertex_buffer.write(
+                        device,
+                        queue,
+                        bytemuck::cast_slice(&sprites.vertices),
+                    );
+                    self.paletted_index_buffer.write(
+                        device,
+                        queue,
+                        bytemuck::cast_slice(&sprites.indices),
+                    );
                 }
-                {
-                    // Not sure which one is better
-                    self.index_buffer =
-                        self.device
-                            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                                label: None,
-                                contents: bytemuck::cast_slice(&self.indices),
-                                usage: wgpu::BufferUsages::INDEX,
-                            });
+                if !self.sdf_circles.indices.is_empty() {
+                    let circles = &self.sdf_circles;
+                    self.sdf_vertex_buffer.write(
+                        device,
+                        queue,
+                        bytemuck::cast_slice(&circles.vertices),
+                    );
+                    self.sdf_index_buffer.write(
+                        device,
+                        queue,
+                        bytemuck::cast_slice(&circles.indices),
+                    );
+                }
+
+                let custom_buffers: Vec<(wgpu::Buffer, wgpu::Buffer)> = self
+                    .custom_shapes
+                    .draws
+                    .iter()
+                    .map(|draw| {
+                        let vertices =
+                            self.device
+                                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                    label: Some("Custom Vertex Buffer"),
+                                    contents: &draw.vertices,
+                                    usage: wgpu::BufferUsages::VERTEX,
+                                });
+                        let indices =
+                            self.device
+                                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                    label: Some("Custom Index Buffer"),
+                                    contents: bytemuck::cast_slice(&draw.indices),
+                                    usage: wgpu::BufferUsages::INDEX,
+                                });
+                        (vertices, indices)
+                    })
+                    .collect();
+
+                self.vertex_buffer
+                    .write(device, queue, self.batch.vertex_bytes());
+                self.index_buffer
+                    .write(device, queue, bytemuck::cast_slice(&self.batch.indices));
+                if !self.screen_batch.draws.is_empty() {
+                    self.screen_vertex_buffer.write(
+                        device,
+                        queue,
+                        self.screen_batch.vertex_bytes(),
+                    );
+                    self.screen_index_buffer.write(
+                        device,
+                        queue,
+                        bytemuck::cast_slice(&self.screen_batch.indices),
+                    );
                 }
 
                 let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                     label: Some("Render Pass"),
                     color_attachments: &[wgpu::RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
+                        view: target,
+                        resolve_target,
                         ops: wgpu::Operations {
                             load: wgpu::LoadOp::Load,
                             store: true,
@@ -73,25 +215,165 @@ impl State {
                     depth_stencil_attachment: None,
                 });
 
-                render_pass.set_pipeline(&self.render_pipeline);
-                // render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
-                // Index is 1 since it's the second
+                match &self.custom_pipeline {
+                    Some(pipeline) => {
+                        render_pass.set_pipeline(pipeline);
+                        render_pass.set_bind_group(2, &self.custom_uniform.bind_group, &[]);
+                    }
+                    None => render_pass.set_pipeline(&self.render_pipeline),
+                }
+                render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+                let viewport = self.viewport();
+                if self.fixed_aspect.is_some() && viewport.width >= 1.0 && viewport.height >= 1.0 {
+                    render_pass.set_viewport(
+                        viewport.x,
+                        viewport.y,
+                        viewport.width,
+                        viewport.height,
+                        0.0,
+                        1.0,
+                    );
+                }
+
+                // Meshes go under the batch, since they're usually the static background
+                render_pass.set_bind_group(1, &self.textures[TextureId::WHITE.0].bind_group, &[]);
+                for ((id, _), bind_group) in self.mesh_draws.iter().zip(&mesh_bind_groups) {
+                    let mesh = &self.meshes[id.0];
+                    render_pass.set_bind_group(0, bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                    render_pass
+                        .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                    render_pass.draw_indexed(0..mesh.indices, 0, 0..1);
+                }
                 render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
 
-                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-                render_pass
-                    .set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.draw_indexed(0..self.indices.len() as u32, 0, 0..1);
+                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice());
+                render_pass.set_index_buffer(self.index_buffer.slice(), wgpu::IndexFormat::Uint32);
+                for draw in &self.batch.draws {
+                    // Index is 1 since it's the second
+                    render_pass.set_bind_group(1, &self.textures[draw.texture.0].bind_group, &[]);
+                    render_pass.draw_indexed(draw.indices.clone(), 0, 0..1);
+                }
+
+                // Paletted sprites right after the world they're part of, see `State::draw_sprite_paletted`
+                if !self.paletted_sprites.draws.is_empty() {
+                    render_pass.set_pipeline(&self.palette_pipeline);
+                    render_pass.set_vertex_buffer(0, self.paletted_vertex_buffer.slice());
+                    render_pass.set_index_buffer(
+                        self.paletted_index_buffer.slice(),
+                        wgpu::IndexFormat::Uint32,
+                    );
+                    for draw in &self.paletted_sprites.draws {
+                        render_pass.set_bind_group(
+                            1,
+                            &self.textures[draw.texture.0].bind_group,
+                            &[],
+                        );
+                        render_pass.set_bind_group(
+                            2,
+                            &self.palettes[draw.palette.0].bind_group,
+                            &[],
+                        );
+                        render_pass.draw_indexed(draw.indices.clone(), 0, 0..1);
+                    }
+                }
+
+                // Every sdf circle in one go, see `State::draw_circle_sdf`
+                if !self.sdf_circles.indices.is_empty() {
+                    render_pass.set_pipeline(&self.sdf_pipeline);
+                    render_pass.set_vertex_buffer(0, self.sdf_vertex_buffer.slice());
+                    render_pass
+                        .set_index_buffer(self.sdf_index_buffer.slice(), wgpu::IndexFormat::Uint32);
+                    render_pass.draw_indexed(0..self.sdf_circles.indices.len() as u32, 0, 0..1);
+                }
+
+                // Custom vertex layouts and materials after all the built in shapes, each with its own pipeline
+                if !self.custom_shapes.draws.is_empty() {
+                    render_pass.set_bind_group(
+                        1,
+                        &self.textures[TextureId::WHITE.0].bind_group,
+                        &[],
+                    );
+                    render_pass.set_bind_group(2, &self.custom_uniform.bind_group, &[]);
+                    for (draw, (vertices, indices)) in
+                        self.custom_shapes.draws.iter().zip(&custom_buffers)
+                    {
+                        let pipeline = match draw.pipeline {
+                            CustomPipeline::Layout(layout) => &self.layout_pipelines[&layout],
+                            CustomPipeline::Material(material) => &self.materials[material.0],
+                        };
+                        render_pass.set_pipeline(pipeline);
+                        render_pass.set_vertex_buffer(0, vertices.slice(..));
+                        render_pass.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint32);
+                        render_pass.draw_indexed(0..draw.indices.len() as u32, 0, 0..1);
+                    }
+                }
+
+                // Screen space shapes go on top, with pixels straight to the screen instead of through the camera
+                if !self.screen_batch.draws.is_empty() {
+                    render_pass.set_pipeline(&self.render_pipeline);
+                    render_pass.set_viewport(
+                        0.0,
+                        0.0,
+                        self.size.width as f32,
+                        self.size.height as f32,
+                        0.0,
+                        1.0,
+                    );
+                    render_pass.set_bind_group(0, &self.screen_uniform_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, self.screen_vertex_buffer.slice());
+                    render_pass.set_index_buffer(
+                        self.screen_index_buffer.slice(),
+                        wgpu::IndexFormat::Uint32,
+                    );
+                    for draw in &self.screen_batch.draws {
+                        render_pass.set_bind_group(
+                            1,
+                            &self.textures[draw.texture.0].bind_group,
+                            &[],
+                        );
+                        render_pass.draw_indexed(draw.indices.clone(), 0, 0..1);
+                    }
+                }
                 // Clear buffer
-                self.indices.clear();
-                self.vertices.clear();
+                self.batch.clear();
+                self.mesh_draws.clear();
+                self.custom_shapes.clear();
+                self.paletted_sprites.clear();
+                self.sdf_circles.clear();
+                self.screen_batch.clear();
             }
 
-            self.font_interface
-                .draw(&self.device, &mut encoder, self.size, &view);
+            if !text_first {
+                self.font_interface
+                    .draw(&self.device, &mut encoder, self.size, view);
+            }
             self.font_interface.finish();
         }
+        if grading {
+            self.color_grade_pass.draw(&mut encoder, &frame_view);
+        }
+        if self.config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+            self.readback.copy(
+                &self.device,
+                &mut encoder,
+                &frame.texture,
+                self.size,
+                self.config.format,
+            );
+        }
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.end(&mut encoder);
+        }
         self.queue.submit(Some(encoder.finish()));
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.submitted();
+        }
+        self.readback.finish(
+            &self.device,
+            self.config.format,
+            self.batch.gamma_correction,
+        );
         frame.present();
         Ok(())
     }
@@ -103,20 +385,37 @@ impl State {
 }
 
 impl State {
+    /// Matches the camera to the shape of the viewport
+    pub(crate) fn update_aspect_ratio(&mut self) {
+        let viewport = self.viewport();
+        self.camera.aspect_ratio = (viewport.width / viewport.height) / 2.0;
+    }
+
     fn update_config(&mut self) {
         self.config.width = self.size.width;
         self.config.height = self.size.height;
 
         self.surface.configure(&self.device, &self.config)
     }
+    /// Reconfigures the surface and both cameras for a window that's now `size`. A minimized window is 0x0,
+    /// which the surface can't be configured to, so that's skipped and the last size is kept until it's restored
     pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
         self.size = size;
         // Update swap chain description based off new size
         self.update_config();
         // update swap chain based of new swap description
         // self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
         // Update other
-        self.camera.aspect_ratio = (size.width as f32 / size.height as f32) / 2.0;
+        self.update_aspect_ratio();
+        // Screen space always matches the window's pixels
+        self.queue.write_buffer(
+            &self.screen_uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[Uniforms::screen(size.width as f32, size.height as f32)]),
+        );
         // println!("{}x{}, AR: {}", self.size.width, self.size.height, self.size.width as f32 / self.size.height as f32);
         // println!("CAMERA AR: {}", self.camera.aspect_ratio);
     }
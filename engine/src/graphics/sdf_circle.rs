@@ -0,0 +1,117 @@
+use super::buffers::Vertex;
+
+/// Circles drawn with [`super::State::draw_circle_sdf`] this frame. Every one is a quad with its corners'
+/// positions around the center in `tex_coords`, so the shader can tell how far each pixel is from the edge
+#[derive(Default)]
+pub(crate) struct SdfCircles {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl SdfCircles {
+    pub fn push(&mut self, x: f32, y: f32, radius: f32, color: [f32; 4]) {
+        let offset = self.vertices.len() as u32;
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)];
+        self.vertices.extend(corners.iter().map(|&(u, v)| Vertex {
+            position: [x + u * radius, y + v * radius, 0.0],
+            color,
+            tex_coords: [u, v],
+        }));
+        self.indices
+            .extend([0, 2, 3, 3, 1, 0].iter().map(|i| i + offset));
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+}
+
+/// Pipeline the circles are drawn with. The shader fades each one out over the last pixel inside its edge, however
+/// big that pixel is in radii, which is what keeps the edge smooth at any size
+pub(crate) fn pipeline(
+    device: &wgpu::Device,
+    uniform_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+        label: Some("SDF Circle Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("sdf_circle.wgsl").into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("SDF Circle Pipeline Layout"),
+        bind_group_layouts: &[uniform_layout],
+        push_constant_ranges: &[],
+    });
+    super::shader::pipeline(
+        device,
+        &layout,
+        &shader,
+        format,
+        Vertex::desc(),
+        sample_count,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pipeline, SdfCircles};
+    use crate::graphics::buffers::Uniforms;
+    use crate::graphics::headless::{Gpu, FORMAT};
+    use crate::graphics::rect::Rect;
+
+    #[test]
+    fn edge_fades_over_one_pixel() {
+        let gpu = match Gpu::new() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let uniform_layout = Uniforms::bind_group_layout(&gpu.device);
+        let pipeline = pipeline(&gpu.device, &uniform_layout, FORMAT, 1);
+        let uniforms = gpu.screen_uniforms(&uniform_layout, 40, 40);
+        let target = gpu.target(40, 40);
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // White on black, so each pixel's red is how much of it the circle covers
+        let mut circles = SdfCircles::default();
+        circles.push(20.0, 20.0, 16.0, [1.0; 4]);
+        gpu.draw(
+            &view,
+            wgpu::Color::BLACK,
+            &pipeline,
+            &[&uniforms],
+            &circles.vertices,
+            &circles.indices,
+        );
+        let image = gpu.capture(&target, 40, 40, Rect::new(0.0, 0.0, 40.0, 40.0));
+
+        // Out from the center along the middle row and column
+        let rows: [Vec<u8>; 2] = [
+            (20..40).map(|x| image.get_pixel(x, 20).0[0]).collect(),
+            (20..40).map(|y| image.get_pixel(20, y).0[0]).collect(),
+        ];
+        for reds in rows {
+            assert_eq!(reds[0], 255);
+            assert_eq!(*reds.last().unwrap(), 0);
+            assert!(reds.windows(2).all(|pair| pair[1] <= pair[0]), "{:?}", reds);
+            // The pixel centered 15.5 from the middle is half a pixel inside the edge, so about half covered,
+            // and the fade is only that one pixel wide
+            let partly: Vec<u8> = reds.iter().copied().filter(|&r| r > 0 && r < 255).collect();
+            assert_eq!(partly.len(), 1, "{:?}", reds);
+            assert!((partly[0] as i32 - 128).abs() <= 16, "{:?}", reds);
+        }
+    }
+
+    #[test]
+    fn one_quad_per_circle() {
+        let mut circles = SdfCircles::default();
+        circles.push(0.0, 0.0, 5.0, [1.0; 4]);
+        circles.push(100.0, 50.0, 10.0, [1.0; 4]);
+        assert_eq!(circles.vertices.len(), 8);
+        assert_eq!(circles.indices.len(), 12);
+        assert_eq!(circles.vertices[4].position, [90.0, 40.0, 0.0]);
+        assert_eq!(circles.vertices[7].position, [110.0, 60.0, 0.0]);
+        assert_eq!(circles.vertices[7].tex_coords, [1.0, 1.0]);
+    }
+}
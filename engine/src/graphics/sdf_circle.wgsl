@@ -0,0 +1,40 @@
+// Circles drawn as one quad each, with the edge worked out per pixel from the distance to the center
+struct Uniforms {
+    view: mat4x4<f32>;
+    model: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniforms: Uniforms;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] color: vec4<f32>;
+    // Where the corner is around the center, from -1 to 1 on each axis
+    [[location(2)]] local: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+    [[location(1)]] local: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = uniforms.view * uniforms.model * vec4<f32>(model.position, 1.0);
+    out.color = model.color;
+    out.local = model.local;
+    return out;
+}
+
+// Fades out over the last pixel inside the edge, with a pixel's size in radii from how fast the distance changes
+// across it
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let distance = length(in.local);
+    let pixel = max(fwidth(distance), 0.00001);
+    let coverage = clamp((1.0 - distance) / pixel, 0.0, 1.0);
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
@@ -0,0 +1,140 @@
+/// Builds a pipeline drawing vertices laid out like `vertex` with `module`'s `vs_main` and `fs_main`, into a target
+/// with `sample_count` samples a pixel
+pub(crate) fn pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    vertex: wgpu::VertexBufferLayout,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module,
+            // Specify the entry point function for shaders, set by [[stage(fragment)]]
+            entry_point: "vs_main",
+            // We should pass in info into the shader itself, right now we're creating it in the shader for hello world
+            buffers: &[vertex],
+        },
+        // Fragment technically opt
+        fragment: Some(wgpu::FragmentState {
+            module,
+            entry_point: "fs_main",
+            // Target color output for swap chain, replace old pixels, and write to all colors
+            targets: &[wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            }],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
+
+/// The user's own uniform data for a custom shader, bound at `[[group(2), binding(0)]]`,
+/// see [`super::State::set_custom_uniform`]
+pub(crate) struct CustomUniform {
+    pub layout: wgpu::BindGroupLayout,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    /// Bytes the buffer was made with, it's only made again when the data changes size
+    size: u64,
+}
+
+impl CustomUniform {
+    /// Starts out as 16 zeroed bytes, so a custom shader can be used before any data's been set
+    pub fn new(device: &wgpu::Device) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("Custom Uniform Bind Group Layout"),
+        });
+        let (buffer, bind_group) = Self::create(device, &layout, UNIFORM_ALIGNMENT);
+        Self {
+            layout,
+            buffer,
+            bind_group,
+            size: UNIFORM_ALIGNMENT,
+        }
+    }
+
+    pub fn set(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, bytes: &[u8]) {
+        let size = bytes.len() as u64;
+        if size != self.size {
+            let (buffer, bind_group) = Self::create(device, &self.layout, size);
+            self.buffer = buffer;
+            self.bind_group = bind_group;
+            self.size = size;
+        }
+        queue.write_buffer(&self.buffer, 0, bytes);
+    }
+
+    fn create(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        size: u64,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Custom Uniform Buffer"),
+            size,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("Custom Uniform Bind Group"),
+        });
+        (buffer, bind_group)
+    }
+}
+
+/// Uniform structs in wgsl are always a multiple of 16 bytes big
+const UNIFORM_ALIGNMENT: u64 = 16;
+
+/// `data`'s bytes, zero padded up to a multiple of 16 so they fill the wgsl struct they're read as
+pub(crate) fn uniform_bytes<T: bytemuck::Pod>(data: &T) -> Vec<u8> {
+    let mut bytes = bytemuck::bytes_of(data).to_vec();
+    let size = (bytes.len() as u64).max(1).div_ceil(UNIFORM_ALIGNMENT) * UNIFORM_ALIGNMENT;
+    bytes.resize(size as usize, 0);
+    bytes
+}
+
+#[cfg(test)]
+mod test {
+    use super::uniform_bytes;
+
+    #[test]
+    fn uploaded_data_is_padded_to_sixteen_bytes() {
+        // time, strength, and an rgb tint
+        let effect: [f32; 5] = [1.5, 0.25, 1.0, 0.5, 0.0];
+        let bytes = uniform_bytes(&effect);
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(&bytes[..20], bytemuck::bytes_of(&effect));
+        assert!(bytes[20..].iter().all(|&b| b == 0));
+
+        let floats: &[f32] = bytemuck::cast_slice(&bytes);
+        assert_eq!(&floats[..5], &effect);
+        // Already a multiple of 16, so nothing's added
+        assert_eq!(uniform_bytes(&[0.0f32; 4]).len(), 16);
+    }
+}
@@ -14,11 +14,13 @@ var<uniform> uniforms: Uniforms;
 struct VertexInput {
     [[location(0)]] position: vec3<f32>;
     [[location(1)]] color: vec4<f32>;
+    [[location(2)]] tex_coords: vec2<f32>;
 };
 
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     [[location(0)]] color: vec4<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
 };
 
 // Marks this func as an entry point for vertex shader
@@ -34,12 +36,19 @@ fn vs_main (
     // model converts screen coords from 0-[max screensize] (e.g., (400, 599)) to normalized coords from 0-1 (e.g., (0.5122123, 0.99231))
     out.clip_position = uniforms.view * uniforms.model * vec4<f32>(model.position, 1.0);
     out.color = model.color;
+    out.tex_coords = model.tex_coords;
 
     return out;
 }
 
 // Fragment shader
+// Plain colored shapes are drawn with a 1x1 white texture, so this is just the color for them
+[[group(1), binding(0)]]
+var t_diffuse: texture_2d<f32>;
+[[group(1), binding(1)]]
+var s_diffuse: sampler;
+
 [[stage(fragment)]]
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-    return in.color;
+    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.color;
 }
\ No newline at end of file
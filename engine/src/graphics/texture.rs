@@ -1,33 +1,48 @@
 use image::GenericImageView;
-use anyhow::Result;
+use crate::error::Result;
 use wgpu::TextureFormat;
 
+/// Handle to a texture loaded with [`super::State::load_texture`], the index into the loaded textures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureId(pub usize);
+
+impl TextureId {
+    /// 1x1 white texture that's always loaded first, plain colored shapes are drawn with it
+    pub(crate) const WHITE: TextureId = TextureId(0);
+}
+
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
-    pub sampler: wgpu::Sampler
+    pub sampler: wgpu::Sampler,
+    pub bind_group: wgpu::BindGroup,
+    pub width: u32,
+    pub height: u32,
 }
 
 impl Texture {
     pub fn from_bytes(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
         format: TextureFormat,
         bytes: &[u8],
         label: &str
     ) -> Result<Self> {
         let img = image::load_from_memory(bytes)?;
-        Self::from_image(device, queue, &img, format, Some(label))
+        Self::from_image(device, queue, layout, &img, format, Some(label))
     }
 
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
         img: &image::DynamicImage,
         format: TextureFormat,
         label: Option<&str>
     ) -> Result<Self> {
-        let rgba = img.as_rgba8().unwrap();
+        // Not every image is stored as rgba (e.g. rgb pngs), so convert instead of assuming
+        let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
 
         let size = wgpu::Extent3d {
@@ -37,7 +52,7 @@ impl Texture {
         };
 
         let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("diffuse_texture"),
+            label,
             // 1d/2d texture, sizes will be 1? 2d array textures size is # of 2d textures
             size,
             // mipmaps are like downscaled textures that are used more at a distance, to reduce cpu & gpu load and reduce effects
@@ -61,7 +76,7 @@ impl Texture {
                 // TODO
                 aspect: wgpu::TextureAspect::All,
             },
-            rgba,
+            &rgba,
             wgpu::ImageDataLayout {
                 // For non-compressed = 1
                 offset: 0,
@@ -92,7 +107,53 @@ impl Texture {
             ..Default::default()
         });
 
-        Ok(Self {texture, view, sampler})
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label,
+        });
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+            bind_group,
+            width: dimensions.0,
+            height: dimensions.1,
+        })
+    }
 
+    /// Layout of the bind group every texture gets, group(1) in the shader
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("Texture Bind Group Layout"),
+        })
     }
 }
\ No newline at end of file
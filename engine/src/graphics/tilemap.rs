@@ -0,0 +1,215 @@
+use super::batch::Batch;
+use super::color::Color;
+use super::rect::Rect;
+use super::texture::TextureId;
+use super::State;
+
+/// Grid of tiles, all cut out of one tileset texture.
+///
+/// The tileset is split into `columns` x `rows` equally sized tiles, numbered left to right, top to bottom,
+/// starting from 0. Each cell of the map holds the number of the tile drawn there, or `None` to leave it empty
+pub struct TileMap {
+    pub tileset: TextureId,
+    columns: u32,
+    rows: u32,
+    /// Width and height a tile is drawn at, in world pixels
+    pub tile_size: f32,
+    width: usize,
+    height: usize,
+    tiles: Vec<Option<u32>>,
+}
+
+impl TileMap {
+    /// An empty `width` x `height` map. `tileset_grid` is how many tiles across and down the tileset has.
+    ///
+    /// Panics if the tileset grid has no columns or no rows
+    pub fn new(
+        tileset: TextureId,
+        tileset_grid: (u32, u32),
+        width: usize,
+        height: usize,
+        tile_size: f32,
+    ) -> Self {
+        assert!(
+            tileset_grid.0 > 0 && tileset_grid.1 > 0,
+            "tileset grid must have at least one column and row, got {}x{}",
+            tileset_grid.0,
+            tileset_grid.1
+        );
+        Self {
+            tileset,
+            columns: tileset_grid.0,
+            rows: tileset_grid.1,
+            tile_size,
+            width,
+            height,
+            tiles: vec![None; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Tile at `(x, y)`, `None` if it's empty or outside the map
+    pub fn get(&self, x: usize, y: usize) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles[y * self.width + x]
+    }
+
+    /// How many tiles the tileset has, valid tile numbers are `0..tile_count()`
+    pub fn tile_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// Panics if `(x, y)` is outside the map, or `tile` isn't in the tileset. Past the last tile would
+    /// otherwise wrap around into rows below the tileset and sample off the bottom of the texture
+    pub fn set(&mut self, x: usize, y: usize, tile: Option<u32>) {
+        assert!(
+            x < self.width && y < self.height,
+            "tile ({}, {}) is outside the {}x{} map",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        if let Some(tile) = tile {
+            assert!(
+                tile < self.tile_count(),
+                "tile {} is outside the {}x{} tileset",
+                tile,
+                self.columns,
+                self.rows
+            );
+        }
+        self.tiles[y * self.width + x] = tile;
+    }
+
+    /// Draws the map with its top left corner at `origin`. Only tiles the camera can see are drawn,
+    /// and since they all share the tileset they go out in a single draw call
+    pub fn draw(&self, state: &mut State, origin: (f32, f32)) {
+        let bounds = state.camera.visible_bounds();
+        self.draw_into(&mut state.batch, origin, bounds);
+    }
+
+    /// Pushes the tiles overlapping `bounds` into `batch`
+    pub fn draw_into(&self, batch: &mut Batch, origin: (f32, f32), bounds: Rect) {
+        let (x0, x1) = visible_range(
+            bounds.x,
+            bounds.right(),
+            origin.0,
+            self.tile_size,
+            self.width,
+        );
+        let (y0, y1) = visible_range(
+            bounds.y,
+            bounds.bottom(),
+            origin.1,
+            self.tile_size,
+            self.height,
+        );
+
+        let white = Color::from_rgb(255, 255, 255, 255);
+        let (tile_u, tile_v) = (1.0 / self.columns as f32, 1.0 / self.rows as f32);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let tile = match self.tiles[y * self.width + x] {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+                let u = (tile % self.columns) as f32 * tile_u;
+                let v = (tile / self.columns) as f32 * tile_v;
+                let left = origin.0 + x as f32 * self.tile_size;
+                let top = origin.1 + y as f32 * self.tile_size;
+                let (right, bottom) = (left + self.tile_size, top + self.tile_size);
+                batch.draw_textured_polygon(
+                    self.tileset,
+                    &[
+                        ((left, top), (u, v)),
+                        ((right, top), (u + tile_u, v)),
+                        ((left, bottom), (u, v + tile_v)),
+                        ((right, bottom), (u + tile_u, v + tile_v)),
+                    ],
+                    &[0, 2, 3, 3, 1, 0],
+                    white,
+                );
+            }
+        }
+    }
+}
+
+/// Range of tile indices along one axis that overlap `start..end`, clamped to the map
+fn visible_range(start: f32, end: f32, origin: f32, tile_size: f32, len: usize) -> (usize, usize) {
+    let first = ((start - origin) / tile_size).floor().max(0.0) as usize;
+    let last = ((end - origin) / tile_size).ceil().max(0.0) as usize;
+    (first.min(len), last.min(len))
+}
+
+#[cfg(test)]
+mod test {
+    use super::TileMap;
+    use crate::graphics::batch::Batch;
+    use crate::graphics::rect::Rect;
+    use crate::graphics::texture::TextureId;
+
+    #[test]
+    fn off_screen_tiles_are_not_drawn() {
+        let mut map = TileMap::new(TextureId(1), (4, 4), 100, 100, 16.0);
+        for y in 0..100 {
+            for x in 0..100 {
+                map.set(x, y, Some(5));
+            }
+        }
+        map.set(1, 1, None);
+
+        let mut batch = Batch::default();
+        // Covers tiles 0..=3 across, 0..=2 down, the edge tiles only partly
+        map.draw_into(&mut batch, (0.0, 0.0), Rect::new(8.0, 0.0, 50.0, 40.0));
+
+        // 12 visible tiles minus the empty one
+        assert_eq!(batch.vertices.len(), 11 * 4);
+        assert_eq!(batch.draws.len(), 1);
+        assert!(batch
+            .vertices
+            .iter()
+            .all(|v| v.position[0] <= 64.0 && v.position[1] <= 48.0));
+        // Tile 5 in a 4x4 tileset is the second one on the second row
+        assert_eq!(batch.vertices[0].tex_coords, [0.25, 0.25]);
+        assert_eq!(batch.vertices[3].tex_coords, [0.5, 0.5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "tileset grid must have at least one column and row")]
+    fn empty_tileset_grid_is_rejected() {
+        TileMap::new(TextureId(1), (0, 4), 10, 10, 16.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "tile 16 is outside the 4x4 tileset")]
+    fn tile_past_the_tileset_is_rejected() {
+        let mut map = TileMap::new(TextureId(1), (4, 4), 10, 10, 16.0);
+        map.set(0, 0, Some(15));
+        map.set(0, 0, Some(16));
+    }
+
+    #[test]
+    fn map_entirely_off_screen() {
+        let mut map = TileMap::new(TextureId(1), (1, 1), 10, 10, 16.0);
+        map.set(0, 0, Some(0));
+
+        let mut batch = Batch::default();
+        map.draw_into(
+            &mut batch,
+            (500.0, 500.0),
+            Rect::new(0.0, 0.0, 100.0, 100.0),
+        );
+        map.draw_into(&mut batch, (-500.0, 0.0), Rect::new(0.0, 0.0, 100.0, 100.0));
+        assert!(batch.vertices.is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use super::keyboard::Keyboard;
+use super::mouse::Mouse;
+
+/// All the input a tick sees, frozen when the tick starts. Read it with [`crate::context::Context::input_snapshot`]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputState {
+    pub keyboard: Keyboard,
+    pub mouse: MouseState,
+}
+
+impl InputState {
+    pub fn is_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keyboard.is_pressed(key)
+    }
+
+    /// Whether `button` is held. Only left, right and middle are tracked
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        match button {
+            MouseButton::Left => self.mouse.left,
+            MouseButton::Right => self.mouse.right,
+            MouseButton::Middle => self.mouse.middle,
+            MouseButton::Other(_) => false,
+        }
+    }
+}
+
+/// Where the cursor is, in physical pixels from the top left of the window, and which buttons are held, as of the
+/// start of a tick. Double clicks and how long buttons have been held aren't kept, since replays go by ticks rather
+/// than the wall clock those are timed with
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MouseState {
+    pub x: f32,
+    pub y: f32,
+    pub left: bool,
+    pub right: bool,
+    pub middle: bool,
+}
+
+impl From<&Mouse> for MouseState {
+    fn from(mouse: &Mouse) -> Self {
+        Self {
+            x: mouse.x,
+            y: mouse.y,
+            left: mouse.is_button_down(MouseButton::Left),
+            right: mouse.is_button_down(MouseButton::Right),
+            middle: mouse.is_button_down(MouseButton::Middle),
+        }
+    }
+}
+
+/// Takes the snapshot for each tick, either from the live keyboard and mouse or from a recording being played back
+#[derive(Debug, Default)]
+pub struct TickInput {
+    current: InputState,
+    /// Last tick's snapshot, for telling when a key went down or came up
+    previous: InputState,
+    playback: VecDeque<InputState>,
+}
+
+impl TickInput {
+    /// Called right before every tick
+    pub(crate) fn begin_tick(&mut self, keyboard: &Keyboard, mouse: &Mouse) {
+        self.previous = self.current;
+        self.current = self.playback.pop_front().unwrap_or(InputState {
+            keyboard: *keyboard,
+            mouse: mouse.into(),
+        });
+    }
+
+    pub fn current(&self) -> InputState {
+        self.current
+    }
+
+    /// Whether `key` is held this tick
+    pub fn is_key_down(&self, key: VirtualKeyCode) -> bool {
+        self.current.is_pressed(key)
+    }
+
+    /// Whether `key` went down this tick, so it's only true for the one tick no matter how long it's held
+    pub fn is_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.current.is_pressed(key) && !self.previous.is_pressed(key)
+    }
+
+    /// Whether `key` came up this tick, having been held last tick
+    pub fn is_key_released(&self, key: VirtualKeyCode) -> bool {
+        !self.current.is_pressed(key) && self.previous.is_pressed(key)
+    }
+
+    /// Whether `button` is held this tick
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.current.is_button_down(button)
+    }
+
+    /// Whether `button` went down this tick, like [`TickInput::is_key_pressed`]
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.current.is_button_down(button) && !self.previous.is_button_down(button)
+    }
+
+    /// Whether `button` came up this tick, having been held last tick
+    pub fn is_button_released(&self, button: MouseButton) -> bool {
+        !self.current.is_button_down(button) && self.previous.is_button_down(button)
+    }
+
+    /// Where the cursor is this tick
+    pub fn mouse_position(&self) -> (f32, f32) {
+        (self.current.mouse.x, self.current.mouse.y)
+    }
+
+    /// Queues up recorded snapshots, one per tick. The live keyboard and mouse are ignored until they run out
+    pub fn play_back(&mut self, inputs: impl IntoIterator<Item = InputState>) {
+        self.playback.extend(inputs);
+    }
+
+    /// Whether there are recorded snapshots left to play back
+    pub fn is_playing_back(&self) -> bool {
+        !self.playback.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use winit::event::{MouseButton, VirtualKeyCode};
+
+    use super::{InputState, MouseState, TickInput};
+    use crate::keyboard::Keyboard;
+    use crate::mouse::Mouse;
+
+    #[test]
+    fn playback_overrides_live_input_until_it_runs_out() {
+        let mut live = Keyboard::new();
+        let mouse = Mouse::new();
+        live.space = true;
+
+        let mut recorded = InputState::default();
+        recorded.keyboard.left = true;
+
+        let mut input = TickInput::default();
+        input.play_back([recorded, InputState::default()]);
+
+        input.begin_tick(&live, &mouse);
+        assert!(input.current().is_pressed(VirtualKeyCode::Left));
+        assert!(!input.current().is_pressed(VirtualKeyCode::Space));
+
+        input.begin_tick(&live, &mouse);
+        assert_eq!(input.current(), InputState::default());
+        assert!(!input.is_playing_back());
+
+        input.begin_tick(&live, &mouse);
+        assert!(input.current().is_pressed(VirtualKeyCode::Space));
+    }
+
+    #[test]
+    fn presses_and_releases_last_one_tick() {
+        let mut live = Keyboard::new();
+        let mouse = Mouse::new();
+        let mut input = TickInput::default();
+
+        live.space = true;
+        input.begin_tick(&live, &mouse);
+        assert!(input.is_key_down(VirtualKeyCode::Space));
+        assert!(input.is_key_pressed(VirtualKeyCode::Space));
+
+        input.begin_tick(&live, &mouse);
+        assert!(input.is_key_down(VirtualKeyCode::Space));
+        assert!(!input.is_key_pressed(VirtualKeyCode::Space));
+        assert!(!input.is_key_released(VirtualKeyCode::Space));
+
+        live.space = false;
+        input.begin_tick(&live, &mouse);
+        assert!(!input.is_key_down(VirtualKeyCode::Space));
+        assert!(input.is_key_released(VirtualKeyCode::Space));
+
+        input.begin_tick(&live, &mouse);
+        assert!(!input.is_key_released(VirtualKeyCode::Space));
+    }
+
+    #[test]
+    fn snapshot_is_frozen_during_tick() {
+        let mut live = Keyboard::new();
+        let mouse = Mouse::new();
+        let mut input = TickInput::default();
+
+        input.begin_tick(&live, &mouse);
+        live.up = true;
+        assert!(live.is_pressed(VirtualKeyCode::Up));
+        assert!(!input.current().is_pressed(VirtualKeyCode::Up));
+    }
+
+    #[test]
+    fn mouse_is_recorded_and_played_back() {
+        let live = Keyboard::new();
+        let mut mouse = Mouse::new();
+        let mut input = TickInput::default();
+
+        mouse.x = 120.0;
+        mouse.y = 80.0;
+        mouse.set_button(MouseButton::Left, true, Instant::now());
+        input.begin_tick(&live, &mouse);
+        assert_eq!(input.mouse_position(), (120.0, 80.0));
+        assert!(input.is_button_down(MouseButton::Left));
+        assert!(input.is_button_pressed(MouseButton::Left));
+        let recorded = input.current();
+        assert_eq!(
+            recorded.mouse,
+            MouseState {
+                x: 120.0,
+                y: 80.0,
+                left: true,
+                right: false,
+                middle: false,
+            }
+        );
+
+        mouse.set_button(MouseButton::Left, false, Instant::now());
+        input.begin_tick(&live, &mouse);
+        assert!(input.is_button_released(MouseButton::Left));
+
+        // Played back, the recording wins over wherever the live mouse is
+        mouse.x = 0.0;
+        input.play_back([recorded]);
+        input.begin_tick(&live, &mouse);
+        assert_eq!(input.mouse_position(), (120.0, 80.0));
+        assert!(input.is_button_pressed(MouseButton::Left));
+        assert!(!input.is_button_down(MouseButton::Right));
+    }
+}
@@ -1,5 +1,7 @@
 use winit::event::{ElementState, VirtualKeyCode, WindowEvent};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Keyboard {
     pub up: bool,
     pub down: bool,
@@ -15,6 +17,8 @@ pub struct Keyboard {
     pub z: bool,
     pub c: bool,
     pub space: bool,
+    /// One bit per held scancode, see [`Keyboard::is_scancode_down`]
+    scancodes: [u64; 8],
 }
 
 impl Keyboard {
@@ -34,10 +38,40 @@ impl Keyboard {
             z: false,
             c: false,
             space: false,
+            scancodes: [0; 8],
         }
     }
 
-    pub fn is_pressed(&mut self, key: VirtualKeyCode) -> bool {
+    /// Whether the key at this physical position is held down, whatever the keyboard layout says it types.
+    ///
+    /// Use this (or [`Keyboard::is_physical_key_down`]) for controls tied to where keys are, like WASD,
+    /// so that they're in the same spot on AZERTY and Dvorak. Use `VirtualKeyCode`s and the named fields
+    /// for anything tied to what the key means, like text or "press R to restart".
+    /// Scancodes are platform specific, and ones of 512 and above aren't tracked
+    pub fn is_scancode_down(&self, scancode: u32) -> bool {
+        let scancode = scancode as usize;
+        scancode < 512 && self.scancodes[scancode / 64] & (1 << (scancode % 64)) != 0
+    }
+
+    /// Same as [`Keyboard::is_scancode_down`], but without having to know the platform's scancodes
+    pub fn is_physical_key_down(&self, key: PhysicalKey) -> bool {
+        self.is_scancode_down(key.scancode())
+    }
+
+    fn set_scancode(&mut self, scancode: u32, down: bool) {
+        let scancode = scancode as usize;
+        if scancode >= 512 {
+            return;
+        }
+        let bit = 1 << (scancode % 64);
+        if down {
+            self.scancodes[scancode / 64] |= bit;
+        } else {
+            self.scancodes[scancode / 64] &= !bit;
+        }
+    }
+
+    pub fn is_pressed(&self, key: VirtualKeyCode) -> bool {
         match key {
             VirtualKeyCode::Up => self.up,
             VirtualKeyCode::Down => self.down,
@@ -58,6 +92,10 @@ impl Keyboard {
     }
 
     pub fn input(&mut self, event: &WindowEvent) -> bool {
+        // Every key is tracked physically, even the ones that don't have a field
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            self.set_scancode(input.scancode, input.state == ElementState::Pressed);
+        }
         match event {
             WindowEvent::KeyboardInput {
                 input:
@@ -139,3 +177,177 @@ impl Default for Keyboard {
         Self::new()
     }
 }
+
+/// Keys by where they are on a US QWERTY keyboard, rather than what they type.
+/// `PhysicalKey::KeyW` is the key above `KeyS` everywhere, even where it types Z (AZERTY) or comma (Dvorak)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhysicalKey {
+    KeyA,
+    KeyB,
+    KeyC,
+    KeyD,
+    KeyE,
+    KeyF,
+    KeyG,
+    KeyH,
+    KeyI,
+    KeyJ,
+    KeyK,
+    KeyL,
+    KeyM,
+    KeyN,
+    KeyO,
+    KeyP,
+    KeyQ,
+    KeyR,
+    KeyS,
+    KeyT,
+    KeyU,
+    KeyV,
+    KeyW,
+    KeyX,
+    KeyY,
+    KeyZ,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    Digit0,
+    Escape,
+    Tab,
+    Space,
+    ShiftLeft,
+    ControlLeft,
+}
+
+impl PhysicalKey {
+    /// Scancode winit reports for the key on this platform.
+    /// Windows and Linux agree on these (PC set 1 and evdev), macOS has its own
+    #[cfg(not(target_os = "macos"))]
+    pub fn scancode(self) -> u32 {
+        use PhysicalKey::*;
+        match self {
+            Escape => 1,
+            Digit1 => 2,
+            Digit2 => 3,
+            Digit3 => 4,
+            Digit4 => 5,
+            Digit5 => 6,
+            Digit6 => 7,
+            Digit7 => 8,
+            Digit8 => 9,
+            Digit9 => 10,
+            Digit0 => 11,
+            Tab => 15,
+            KeyQ => 16,
+            KeyW => 17,
+            KeyE => 18,
+            KeyR => 19,
+            KeyT => 20,
+            KeyY => 21,
+            KeyU => 22,
+            KeyI => 23,
+            KeyO => 24,
+            KeyP => 25,
+            ControlLeft => 29,
+            KeyA => 30,
+            KeyS => 31,
+            KeyD => 32,
+            KeyF => 33,
+            KeyG => 34,
+            KeyH => 35,
+            KeyJ => 36,
+            KeyK => 37,
+            KeyL => 38,
+            ShiftLeft => 42,
+            KeyZ => 44,
+            KeyX => 45,
+            KeyC => 46,
+            KeyV => 47,
+            KeyB => 48,
+            KeyN => 49,
+            KeyM => 50,
+            Space => 57,
+        }
+    }
+
+    /// Scancode winit reports for the key on this platform, the `kVK_` virtual keycodes
+    #[cfg(target_os = "macos")]
+    pub fn scancode(self) -> u32 {
+        use PhysicalKey::*;
+        match self {
+            KeyA => 0x00,
+            KeyS => 0x01,
+            KeyD => 0x02,
+            KeyF => 0x03,
+            KeyH => 0x04,
+            KeyG => 0x05,
+            KeyZ => 0x06,
+            KeyX => 0x07,
+            KeyC => 0x08,
+            KeyV => 0x09,
+            KeyB => 0x0B,
+            KeyQ => 0x0C,
+            KeyW => 0x0D,
+            KeyE => 0x0E,
+            KeyR => 0x0F,
+            KeyY => 0x10,
+            KeyT => 0x11,
+            Digit1 => 0x12,
+            Digit2 => 0x13,
+            Digit3 => 0x14,
+            Digit4 => 0x15,
+            Digit6 => 0x16,
+            Digit5 => 0x17,
+            Digit9 => 0x19,
+            Digit7 => 0x1A,
+            Digit8 => 0x1C,
+            Digit0 => 0x1D,
+            KeyO => 0x1F,
+            KeyU => 0x20,
+            KeyI => 0x22,
+            KeyP => 0x23,
+            KeyL => 0x25,
+            KeyJ => 0x26,
+            KeyK => 0x28,
+            KeyN => 0x2D,
+            KeyM => 0x2E,
+            Tab => 0x30,
+            Space => 0x31,
+            Escape => 0x35,
+            ShiftLeft => 0x38,
+            ControlLeft => 0x3B,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use winit::event::VirtualKeyCode;
+
+    use super::{Keyboard, PhysicalKey};
+
+    #[test]
+    fn scancodes_are_tracked_per_bit() {
+        let mut keyboard = Keyboard::new();
+        keyboard.set_scancode(PhysicalKey::KeyW.scancode(), true);
+        keyboard.set_scancode(300, true);
+        keyboard.set_scancode(9000, true);
+
+        assert!(keyboard.is_physical_key_down(PhysicalKey::KeyW));
+        assert!(!keyboard.is_physical_key_down(PhysicalKey::KeyS));
+        assert!(keyboard.is_scancode_down(300));
+        assert!(!keyboard.is_scancode_down(9000));
+        // Physical keys don't touch the layout dependent ones
+        assert!(!keyboard.is_pressed(VirtualKeyCode::W));
+
+        keyboard.set_scancode(PhysicalKey::KeyW.scancode(), false);
+        assert!(!keyboard.is_physical_key_down(PhysicalKey::KeyW));
+        assert!(keyboard.is_scancode_down(300));
+    }
+}
@@ -5,50 +5,74 @@ inspiration from [ggez](https://github.com/ggez/ggez) and [raylib](https://githu
 
 ## Getting Started
 Create a struct that implements the [`Runnable`] trait, a context using [`ContextBuilder`],
-Then start the game loop with [`main::run`]
+Then start the game loop with [`main::run`]. The example only gets compiled, since it opens a window
 
-```
-use thomas::Color;
+```no_run
+use engine::context::Context;
+use engine::graphics::color::Color;
+use engine::Runnable;
 
 struct Game {
     playerx: f32,
-    playery: f32
+    playery: f32,
 }
 
 impl Runnable for Game {
-    fn tick(&mut self, _ctx: &mut Context) {
-        self.playerx += 1;
-        self.playery += 1;
+    fn tick(&mut self, ctx: &mut Context) {
+        // Pixels a second
+        let speed = 140.0;
+        self.playerx += speed * ctx.delta_time();
+        self.playery += speed * ctx.delta_time();
     }
-    fn render(&self, ctx: &mut Context) {
-        ctx.graphics.clear_background(Color::from_hex("#000000"));
-        ctx.graphics.draw_square(playerx, playery, Color::from_hex("#FFFFFF"));
+    fn render(&self, ctx: &mut Context, _alpha: f32) {
+        ctx.graphics.clear_background(Color::BLACK);
+        ctx.graphics
+            .draw_square(self.playerx, self.playery, 100.0, Color::WHITE);
     }
 }
 
-let game = Game {playerx: 0.0, playery: 0.0};
+let game = Game {
+    playerx: 0.0,
+    playery: 0.0,
+};
 
-let (event_loop, ctx) = thomas::ContextBuilder::new()
+let (event_loop, ctx) = engine::ContextBuilder::new()
     .with_title("Booboo")
     .build();
 
-thomas::main::run(event_loop, ctx, universe);
+engine::main::run(event_loop, ctx, game);
 ```
+
+## Features
+- `embedded-font` (on by default): a default font to draw text with. Without it, text needs a font loaded first
+- `clipboard`: reading and writing the system clipboard from [`context::Context`]
+- `serde`: serializable input snapshots, and [`storage::Storage`] for saving game state
+- `hot-reload`: reloading textures and fonts when their files change, see [`graphics::State::enable_hot_reload`]
 */
 
 pub mod context;
+pub mod error;
 use audio::Audio;
 use context::Context;
+use input::TickInput;
+pub use error::EngineError;
+pub use keyboard::PhysicalKey;
 pub mod graphics;
+pub mod input;
 mod keyboard;
-use image::GenericImageView;
+pub mod monitor;
+pub mod mouse;
 use resource::ResourceManager;
 pub use rodio;
 pub use winit;
 pub mod audio;
 mod resource;
+#[cfg(feature = "serde")]
+pub mod storage;
+pub mod time;
+pub mod ui;
 
-use winit::dpi::PhysicalPosition;
+use winit::dpi::PhysicalSize;
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
@@ -59,12 +83,27 @@ use std::time::Instant;
 pub use wgpu::Color;
 
 /// Contains parameters that are used by [`main::run`]
+#[derive(Clone)]
 pub struct Config {
     pub ticks: u32,
+    /// Only render when asked to, see [`ContextBuilder::with_redraw_on_demand`]
+    pub redraw_on_demand: bool,
+    /// Most frames to render a second, see [`ContextBuilder::with_uncapped_fps`]
+    pub max_fps: Option<u32>,
+    /// Times a second [`Runnable::fixed_update`] runs, see [`ContextBuilder::with_fixed_update_rate`]
+    pub fixed_update_rate: u32,
+    /// Most fixed updates run in one frame, see [`ContextBuilder::with_max_fixed_steps`]
+    pub max_fixed_steps: u32,
 }
 impl Default for Config {
     fn default() -> Self {
-        Self { ticks: 140 }
+        Self {
+            ticks: 140,
+            redraw_on_demand: false,
+            max_fps: None,
+            fixed_update_rate: 60,
+            max_fixed_steps: 5,
+        }
     }
 }
 /// Builder for a [`Context`]
@@ -74,6 +113,18 @@ pub struct ContextBuilder {
     icon: Option<PathBuf>,
     resource_mgr: PathBuf,
     config: Config,
+    gamma_correction: Option<bool>,
+    surface_format: Option<wgpu::TextureFormat>,
+    clear_color: graphics::color::Color,
+    fixed_aspect: Option<f32>,
+    max_delta: f32,
+    frame_latency: Option<u32>,
+    decorations: bool,
+    always_on_top: bool,
+    vsync: bool,
+    monitor: Option<usize>,
+    double_click_interval: std::time::Duration,
+    sample_count: u32,
 }
 
 impl ContextBuilder {
@@ -82,6 +133,17 @@ impl ContextBuilder {
     /// `Game` title
     /// No icon
     /// Default config
+    /// Adapter's preferred surface format
+    /// Gamma correction on only if the surface format is sRGB
+    /// Cleared to opaque black every frame
+    /// World stretched to fill the window
+    /// Frame deltas clamped to `0.1` seconds
+    /// Backend's own frame latency
+    /// Decorated window, not always on top
+    /// Vsync on and no frame cap
+    /// Opens on whichever monitor the platform picks
+    /// Clicks `300` ms apart or less are double clicks
+    /// No multisampling
     pub fn new() -> Self {
         Self {
             title: String::from("Game"),
@@ -89,6 +151,18 @@ impl ContextBuilder {
             icon: None,
             resource_mgr: PathBuf::new(),
             config: Config::default(),
+            gamma_correction: None,
+            surface_format: None,
+            clear_color: graphics::color::Color::from_rgb(0, 0, 0, 255),
+            fixed_aspect: None,
+            max_delta: 0.1,
+            frame_latency: None,
+            decorations: true,
+            always_on_top: false,
+            vsync: true,
+            monitor: None,
+            double_click_interval: mouse::DEFAULT_DOUBLE_CLICK_INTERVAL,
+            sample_count: 1,
         }
     }
     /// Changes title of [`winit::window::Window`]
@@ -101,6 +175,43 @@ impl ContextBuilder {
         self.config.ticks = ticks;
         self
     }
+    /// Changes how many times a second [`Runnable::fixed_update`] runs, 60 by default
+    pub fn with_fixed_update_rate(mut self, hz: u32) -> Self {
+        self.config.fixed_update_rate = hz;
+        self
+    }
+    /// Changes the most fixed updates that run in a single frame, 5 by default. After a frame slower than that
+    /// many updates, the rest of the time is dropped instead of caught up, so a slow machine falls behind
+    /// gracefully instead of spending every frame catching up on the last
+    pub fn with_max_fixed_steps(mut self, steps: u32) -> Self {
+        self.config.max_fixed_steps = steps;
+        self
+    }
+    /// Only renders when [`Context::request_redraw`] is called or an event (a key, the mouse, a resize...) comes in,
+    /// instead of every frame, and sleeps the rest of the time. For turn based and menu heavy games where nothing
+    /// moves most of the time, to save power.
+    ///
+    /// Ticks only run when the loop wakes up for one of those too, so anything animated has to keep calling
+    /// `request_redraw` every frame while it moves. When more than one window is open, the loop only sleeps
+    /// while every window is waiting
+    pub fn with_redraw_on_demand(mut self) -> Self {
+        self.config.redraw_on_demand = true;
+        self
+    }
+    /// Turns vsync off, so frames render as fast as they can instead of once per screen refresh, for benchmarking or
+    /// screens faster than vsync manages. Simple scenes can then hit thousands of frames a second and keep the gpu at
+    /// full load for nothing, so `soft_cap` still holds it to at most that many a second, e.g. `Some(1000)`. `None`
+    /// is truly uncapped.
+    ///
+    /// The cap waits before each frame, sleeping and then spinning for the last millisecond, so it's accurate but
+    /// keeps a core a little busy. It sits on top of vsync rather than replacing it: turning vsync back on with
+    /// [`graphics::State::set_vsync`] keeps the cap, which then only matters if it's under the refresh rate.
+    /// Ticks aren't affected either way
+    pub fn with_uncapped_fps(mut self, soft_cap: Option<u32>) -> Self {
+        self.vsync = false;
+        self.config.max_fps = soft_cap;
+        self
+    }
     /// Changes margin of game window, functionally equivalent to html margin
     pub fn with_margin(mut self, margin: f32) -> Self {
         self.margin = margin;
@@ -116,20 +227,114 @@ impl ContextBuilder {
         self.resource_mgr = path;
         self
     }
+    /// Forces gamma correction on or off instead of matching the surface format, see [`graphics::State::set_gamma_correction`].
+    /// Leave it on for sRGB surfaces (most platforms), off for linear ones or for already linear colors
+    pub fn with_gamma_correction(mut self, gamma_correction: bool) -> Self {
+        self.gamma_correction = Some(gamma_correction);
+        self
+    }
+    /// Changes the color the screen is cleared to every frame, before anything's drawn.
+    /// `clear_background` still overrides it for a frame, and [`graphics::State::set_clear_color`] changes it later
+    pub fn with_clear_color(mut self, color: graphics::color::Color) -> Self {
+        self.clear_color = color;
+        self
+    }
+    /// Keeps the world at `aspect` (width / height), scaling it to fit the window and letterboxing the rest
+    /// instead of stretching it, see [`graphics::State::set_fixed_aspect`]
+    pub fn with_fixed_aspect(mut self, aspect: f32) -> Self {
+        self.fixed_aspect = Some(aspect);
+        self
+    }
+    /// Changes the longest, in seconds, a frame can be reported to take, see [`time::Time::max_delta`].
+    ///
+    /// Panics if `max_delta` is negative, NaN or infinite. Zero is allowed, but stops time altogether
+    pub fn with_max_delta(mut self, max_delta: f32) -> Self {
+        // Checked here so the panic points at the builder call instead of `build`
+        time::max_delta_duration(max_delta);
+        self.max_delta = max_delta;
+        self
+    }
+    /// Turns the window's title bar and border on or off. Off makes a borderless window, which can't be moved or
+    /// closed with the mouse, so give the player another way out. See [`Context::set_decorations`] to change it later
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+    /// Keeps the window above every other window, e.g. for a tool that floats over the game.
+    /// It's only a request to the window manager, and some (mostly on Linux) ignore it.
+    /// See [`Context::set_always_on_top`] to change it later
+    pub fn with_always_on_top(mut self, always_on_top: bool) -> Self {
+        self.always_on_top = always_on_top;
+        self
+    }
+    /// Asks for at most `frames` frames to be queued up for the screen at once: 1 is the least input latency,
+    /// more keeps the gpu busier for a steadier frame rate. Left alone, it's whatever the backend picks,
+    /// usually 2 or 3.
+    ///
+    /// wgpu only lets the surface be configured with this from 0.19 on, and this engine is on 0.12, so for now
+    /// the request is only logged next to the adapter info, along with the backend default that's used instead.
+    /// Even where it's supported it's a hint, some platforms and drivers ignore it
+    pub fn with_desired_maximum_frame_latency(mut self, frames: u32) -> Self {
+        self.frame_latency = Some(frames);
+        self
+    }
+    /// Longest two presses of a mouse button can be apart, in seconds, to count as a double click for
+    /// [`Context::is_double_click`]. Defaults to 0.3, around what most desktops use
+    pub fn with_double_click_interval(mut self, seconds: f32) -> Self {
+        self.double_click_interval = std::time::Duration::from_secs_f32(seconds);
+        self
+    }
+    /// Opens the window on the monitor at `index` in [`Context::available_monitors`], instead of wherever the
+    /// platform puts it. The margin is kept around it on that monitor. Indices come from the order the platform
+    /// lists monitors in, which can change when one's plugged in or out, so look the index up by name if it's
+    /// saved between runs. An index past the last monitor logs a warning and falls back to the default
+    pub fn with_monitor(mut self, index: usize) -> Self {
+        self.monitor = Some(index);
+        self
+    }
+    /// Changes format of the surface instead of using the adapter's preferred one.
+    /// The format has to be supported by the surface, otherwise configuring it panics
+    pub fn with_surface_format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.surface_format = Some(format);
+        self
+    }
+    /// Antialiases shapes' edges by drawing them with `sample_count` samples a pixel and resolving them onto the
+    /// frame, so diagonal lines and circles aren't jagged. `4` turns multisampling on and `1` (the default) leaves it
+    /// off, anything above 1 is treated as 4. If the adapter can't do it, a warning is logged and drawing goes on
+    /// without it, see [`graphics::State::sample_count`] for what was picked.
+    /// Text is already smooth and isn't multisampled, and can't be drawn under shapes with it on
+    pub fn with_sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
     /// Creates a [`Context`] and [`EventLoop<()>`] using current settings, consuming the builder
+    ///
+    /// Panics if anything fails, use [`ContextBuilder::try_build`] to handle errors instead
     pub fn build(self) -> (EventLoop<()>, context::Context) {
-        // Init logger for errors, etc.
-        env_logger::init();
-
+        self.try_build().expect("Unable to build context")
+    }
+    /// Same as [`ContextBuilder::build`], but returns an error instead of panicking
+    pub fn try_build(self) -> error::Result<(EventLoop<()>, context::Context)> {
         // Create event loop
         let event_loop = EventLoop::new();
+        let context = self.try_build_window(&event_loop)?;
+        Ok((event_loop, context))
+    }
+    /// Creates another window on an existing event loop, with its own [`Context`] using current settings.
+    /// Only needed for running more than one window, see [`main::run_windows`]
+    pub fn try_build_window(&self, event_loop: &EventLoop<()>) -> error::Result<context::Context> {
+        // Init logger for errors, etc.
+        // Ignore the error, since a logger could already be set if building a second time
+        let _ = env_logger::try_init();
 
         // Load icon
-        let icon = match self.icon {
+        let icon = match &self.icon {
             Some(icon_path) => {
-                let image = image::open(icon_path).expect("Unable to find image");
+                let image = image::open(icon_path)?.to_rgba8();
+                let (width, height) = image.dimensions();
                 Some(
-                    winit::window::Icon::from_rgba(image.to_bytes(), image.width(), image.height())
+                    // Can't fail, since the image was just converted to rgba
+                    winit::window::Icon::from_rgba(image.into_raw(), width, height)
                         .expect("Bad image"),
                 )
             }
@@ -138,42 +343,76 @@ impl ContextBuilder {
 
         // Create window, with `margin`
         let builder = winit::window::WindowBuilder::new()
-            .with_title(self.title)
+            .with_title(&self.title)
             .with_visible(false)
-            .with_window_icon(icon);
-        let window = builder.build(&event_loop).unwrap();
-        let mut size = window.current_monitor().unwrap().size();
-        size.width -= (self.margin * 2.0) as u32;
-        size.height -= (self.margin * 2.0) as u32;
-        window.set_inner_size(size);
-        window.set_outer_position(PhysicalPosition {
-            x: self.margin,
-            y: self.margin,
+            .with_window_icon(icon)
+            .with_decorations(self.decorations)
+            .with_always_on_top(self.always_on_top);
+        let window = builder.build(event_loop)?;
+        let chosen = self.monitor.and_then(|index| {
+            let monitor = window.available_monitors().nth(index);
+            if monitor.is_none() {
+                log::warn!("No monitor {}, opening on the default one", index);
+            }
+            monitor
         });
+        if let Some(monitor) = chosen.or_else(|| window.current_monitor()) {
+            monitor::fit_to(&window, &monitor, self.margin);
+        }
 
         // Init [`wgpu`]
-        let graphics = futures::executor::block_on(graphics::State::new(&window));
+        let mut graphics = futures::executor::block_on(graphics::State::new(
+            &window,
+            self.surface_format,
+            self.sample_count,
+        ))?;
+        if self.gamma_correction.is_some() {
+            let format = graphics.surface_format();
+            graphics
+                .set_gamma_correction(graphics::gamma_correction(format, self.gamma_correction));
+        }
+        graphics.set_clear_color(Some(self.clear_color));
+        graphics.set_fixed_aspect(self.fixed_aspect);
+        if !self.vsync {
+            graphics.set_vsync(false);
+        }
+        if let Some(frames) = self.frame_latency {
+            log::warn!(
+                "Desired maximum frame latency of {} can't be set on this wgpu, using the backend's default",
+                frames
+            );
+        }
         // Init keyboard controller
         let keyboard = keyboard::Keyboard::new();
 
         // After everything's loaded make window visible
         window.set_visible(true);
 
-        let resource_mgr = ResourceManager::new(self.resource_mgr);
+        let resource_mgr = ResourceManager::new(self.resource_mgr.clone());
 
         let audio = Audio::new();
 
         let context = Context {
             graphics,
             keyboard,
+            input: TickInput::default(),
+            mouse: mouse::Mouse::with_double_click_interval(self.double_click_interval),
+            ui: ui::Ui::default(),
+            time: time::Time::new(self.max_delta),
             window,
             audio,
-            // Doesn't matter if we move here 'cause self is consumed
-            config: self.config,
+            config: self.config.clone(),
             resource_mgr,
+            #[cfg(feature = "clipboard")]
+            clipboard: None,
+            #[cfg(feature = "serde")]
+            storage: storage::Storage::for_game(&self.title),
+            exit_requested: false,
+            // The first frame always gets drawn
+            redraw_requested: true,
         };
 
-        (event_loop, context)
+        Ok(context)
     }
 }
 
@@ -185,127 +424,393 @@ impl Default for ContextBuilder {
 
 /// A struct with this trait must be passed into [`main::run`]
 pub trait Runnable {
-    /// Runs every tick, as defined with [`ContextBuidler::with_ticks`]
+    /// Runs every tick, as defined with [`ContextBuilder::with_ticks`]
     /// Put your game logic here
+    ///
+    /// Ticks are decoupled from the frame rate: they run as many times as the time
+    /// that passed calls for (140 a second by default), and time left over carries on to the next frame. Catching up
+    /// after a slow frame is capped by [`time::Time::max_delta`], so it can't spiral into ever longer frames.
+    /// [`Context::delta_time`] is the length of one tick, the same every tick
     fn tick(&mut self, ctx: &mut context::Context);
 
+    /// Runs at a fixed rate ([`ContextBuilder::with_fixed_update_rate`], 60 a second by default), for physics and
+    /// anything else that has to step the same way every time to stay deterministic. Like ticks, updates run as many
+    /// times as the time that passed calls for and leftover time carries on to the next frame, but at most
+    /// [`ContextBuilder::with_max_fixed_steps`] of them a frame. Integrate with [`Context::fixed_delta_time`].
+    /// Does nothing by default
+    fn fixed_update(&mut self, _ctx: &mut context::Context) {}
+
     /// Runs every frame, which matches the refresh rate of whatever device the program
     /// is run on. Use [`Context`]`.graphics.(render)` here
-    fn render(&self, ctx: &mut context::Context);
+    ///
+    /// Frames and ticks don't line up, so something moving every tick looks like it stutters if it's drawn
+    /// exactly where the last tick left it. `alpha` is how far along the game is toward the next tick,
+    /// from `0.0` (just ticked) to `1.0` (about to tick). Keep the previous position around in `tick`,
+    /// then draw at `previous + (current - previous) * alpha`
+    fn render(&self, ctx: &mut context::Context, alpha: f32);
+
+    /// Runs every frame right after [`Runnable::render`], before anything's sent to the gpu.
+    /// Use [`graphics::State::vertices_mut`] here to change what `render` drew, e.g. to make it all wobble
+    fn pre_render(&mut self, _ctx: &mut context::Context) {}
+
+    /// Runs when the window's close button is clicked, or escape is pressed. Return `false` to keep the window open,
+    /// e.g. to show a "Save before quitting?" prompt first, then call [`Context::request_exit`] once it's answered.
+    /// Closes straight away by default
+    fn on_close_requested(&mut self, _ctx: &mut context::Context) -> bool {
+        true
+    }
 }
 
 /// This module includes the [`main::run`] function
 pub mod main {
+    use std::collections::HashMap;
+
+    use winit::window::WindowId;
+
     use super::*;
     /// Takes in an [`EventLoop`] & [`Context`], both of which are generated from [`ContextBuilder::build`]
     /// Also some sort of state that implements [`Runnable`]
-    pub fn run<T: 'static + Runnable>(
-        event_loop: EventLoop<()>,
-        mut context: Context,
-        mut state: T,
-    ) {
-        // Game "speed" or "update time"
-        let ticks_per_second: f64 = context.config.ticks as f64;
-        let nanos_per_tick: u128 = (1_000_000_000.0 / ticks_per_second).round() as u128;
-        let mut frames = 0;
-        let mut average_frames = 0;
-        let mut prev_time = Instant::now();
-        let mut timer = Instant::now();
-        let mut lag: u128 = 0;
-        let mut ticks = 0;
-        let mut average_ticks = 0;
+    ///
+    /// Resize events are coalesced: dragging a window edge can fire hundreds of them a second,
+    /// so only the latest size is kept and the surface is reconfigured once, right before the next render
+    pub fn run<T: 'static + Runnable>(event_loop: EventLoop<()>, context: Context, state: T) {
+        run_windows(event_loop, vec![(context, Box::new(state))]);
+    }
+
+    /// Same as [`run`], but for more than one window at once, each with its own [`Runnable`]. Make the first
+    /// window with [`ContextBuilder::build`] and the rest with [`ContextBuilder::try_build_window`] on its event loop.
+    ///
+    /// Most games only need the one window, this is for tools, like a level editor next to the game.
+    /// Every window is completely separate: its own gpu device, textures, keyboard, audio and tick rate,
+    /// so nothing loaded in one can be drawn in another, and they only share what the runnables share themselves.
+    /// Events only go to the window they happened in. Closing a window (or pressing escape in it) drops
+    /// only that one, and the program exits when the last one is closed
+    pub fn run_windows(event_loop: EventLoop<()>, windows: Vec<(Context, Box<dyn Runnable>)>) {
+        let mut windows: HashMap<WindowId, Running> = windows
+            .into_iter()
+            .map(|(context, state)| (context.window.id(), Running::new(context, state)))
+            .collect();
 
         // Here's the 'game loop'
         event_loop.run(move |event, _, control_flow| {
             // ControlFlow Poll v. ControlFlow Wait, two different power v. performance cases
-            *control_flow = ControlFlow::Poll;
+            // Only sleep once nothing's waiting to be drawn
+            *control_flow = if windows.values().all(|running| {
+                running.context.config.redraw_on_demand && !running.context.redraw_requested
+            }) {
+                ControlFlow::Wait
+            } else {
+                ControlFlow::Poll
+            };
 
             // world.tick,
             match event {
-                Event::WindowEvent { ref event, .. } => {
-                    if !context.keyboard.input(event) {
-                        match event {
-                            WindowEvent::CloseRequested => exit(control_flow),
-                            WindowEvent::KeyboardInput {
-                                input:
-                                    KeyboardInput {
-                                        state: ElementState::Pressed,
-                                        virtual_keycode: Some(VirtualKeyCode::Escape),
-                                        ..
-                                    },
-                                ..
-                            } => exit(control_flow),
-                            WindowEvent::Resized(size) => context.graphics.resize(*size),
-                            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                                // new_inner_size is &&mut so we have to dereference it twice
-                                context.graphics.resize(**new_inner_size);
-                            }
-                            _ => (),
+                Event::WindowEvent {
+                    ref event,
+                    window_id,
+                } => {
+                    if let Some(running) = windows.get_mut(&window_id) {
+                        if !running.window_event(event) {
+                            windows.remove(&window_id);
                         }
                     }
                 }
                 Event::MainEventsCleared => {
-                    let time_passed = prev_time.elapsed();
-                    lag += time_passed.as_nanos();
-                    prev_time = Instant::now();
-
-                    // So long as time passed is above the designated nanos per fps
-                    while lag > nanos_per_tick {
-                        state.tick(&mut context);
-                        ticks += 1;
-                        lag -= nanos_per_tick;
-                    }
-
-                    // Unconditionally rerender
-                    context.window.request_redraw();
-                    frames += 1;
-
-                    if timer.elapsed().as_millis() > 1000 {
-                        timer = Instant::now();
-                        average_frames = frames;
-                        average_ticks = ticks;
-                        frames = 0;
-                        ticks = 0;
+                    for running in windows.values_mut() {
+                        running.tick();
                     }
                 }
-                Event::RedrawRequested(_) => {
-                    state.render(&mut context);
-
-                    // Write fps
-                    context.graphics.draw_text(
-                        &format!("FPS: {}", average_frames),
-                        0.0,
-                        0.0,
-                        wgpu::Color::GREEN,
-                        20.0,
-                    );
-                    context.graphics.draw_text(
-                        &format!("Ticks/s: {}", average_ticks),
-                        140.0,
-                        0.0,
-                        wgpu::Color::GREEN,
-                        20.0,
-                    );
-
-                    context.graphics.update();
-                    match context.graphics.render() {
-                        Ok(_) => {}
-                        // Recreate the swap_chain if lost
-                        Err(wgpu::SurfaceError::Lost) => {
-                            context.graphics.resize(*context.graphics.size())
+                Event::RedrawRequested(window_id) => {
+                    if let Some(running) = windows.get_mut(&window_id) {
+                        if !running.redraw() {
+                            windows.remove(&window_id);
                         }
-                        // The system is out of memory, we should probably quit
-                        Err(wgpu::SurfaceError::OutOfMemory) => exit(control_flow),
-                        // All other errors (Outdated, Timeout) should be resolved by the next frame
-                        Err(e) => eprintln!("Err: {:?}", e),
-                    };
+                    }
                 }
                 _ => (),
             }
+            // Anything can call `request_exit`, not only window events
+            windows.retain(|_, running| !running.context.exit_requested);
+
+            if windows.is_empty() {
+                exit(control_flow);
+            }
         });
     }
+
+    /// A window and the game running in it, along with its timing
+    struct Running {
+        context: Context,
+        state: Box<dyn Runnable>,
+        nanos_per_tick: u128,
+        nanos_per_fixed_update: u128,
+        /// Time fixed updates haven't caught up on yet, kept apart from `lag` since they run at their own rate
+        fixed_lag: u128,
+        frames: u32,
+        average_frames: u32,
+        prev_time: Instant,
+        timer: Instant,
+        lag: u128,
+        ticks: u32,
+        average_ticks: u32,
+        pending_resize: Option<PhysicalSize<u32>>,
+        last_frame: Instant,
+        alpha: f32,
+        frame_limiter: Option<time::FrameLimiter>,
+    }
+
+    impl Running {
+        fn new(context: Context, state: Box<dyn Runnable>) -> Self {
+            // Game "speed" or "update time"
+            let ticks_per_second: f64 = context.config.ticks as f64;
+            let nanos_per_tick: u128 = (1_000_000_000.0 / ticks_per_second).round() as u128;
+            let nanos_per_fixed_update =
+                (1_000_000_000.0 / context.config.fixed_update_rate.max(1) as f64).round() as u128;
+            let frame_limiter = context.config.max_fps.map(time::FrameLimiter::new);
+            Self {
+                context,
+                state,
+                nanos_per_tick,
+                nanos_per_fixed_update,
+                fixed_lag: 0,
+                frames: 0,
+                average_frames: 0,
+                prev_time: Instant::now(),
+                timer: Instant::now(),
+                lag: 0,
+                ticks: 0,
+                average_ticks: 0,
+                pending_resize: None,
+                last_frame: Instant::now(),
+                alpha: 0.0,
+                frame_limiter,
+            }
+        }
+
+        /// Returns false if the window should close
+        fn window_event(&mut self, event: &WindowEvent) -> bool {
+            let context = &mut self.context;
+            // Whatever happened might change what's on screen
+            context.redraw_requested = true;
+            context.mouse.input(event);
+            if !context.keyboard.input(event) {
+                match event {
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => {
+                        let allowed = self.state.on_close_requested(context);
+                        close_if_allowed(allowed, &mut context.exit_requested);
+                    }
+                    WindowEvent::Resized(size) => self.pending_resize = Some(*size),
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        // new_inner_size is &&mut so we have to dereference it twice
+                        self.pending_resize = Some(**new_inner_size);
+                    }
+                    _ => (),
+                }
+            }
+            !context.exit_requested
+        }
+
+        fn tick(&mut self) {
+            // Clamped, so a stall doesn't turn into hundreds of ticks in a row
+            let time_passed = self.context.time.advance(self.prev_time.elapsed());
+            self.lag += time_passed.as_nanos();
+            self.prev_time = Instant::now();
+
+            let (steps, fixed_lag) = fixed_steps(
+                self.fixed_lag + time_passed.as_nanos(),
+                self.nanos_per_fixed_update,
+                self.context.config.max_fixed_steps,
+            );
+            self.fixed_lag = fixed_lag;
+            for _ in 0..steps {
+                self.state.fixed_update(&mut self.context);
+            }
+
+            // So long as time passed is above the designated nanos per fps
+            while self.lag > self.nanos_per_tick {
+                self.context
+                    .input
+                    .begin_tick(&self.context.keyboard, &self.context.mouse);
+                self.context.audio.listener = self.context.graphics.camera.center();
+                self.state.tick(&mut self.context);
+                self.ticks += 1;
+                self.lag -= self.nanos_per_tick;
+            }
+            self.alpha = render_alpha(self.lag, self.nanos_per_tick);
+
+            if needs_redraw(
+                self.context.config.redraw_on_demand,
+                self.context.redraw_requested,
+            ) {
+                self.context.window.request_redraw();
+                self.frames += 1;
+            }
+
+            if self.timer.elapsed().as_millis() > 1000 {
+                self.timer = Instant::now();
+                self.average_frames = self.frames;
+                self.average_ticks = self.ticks;
+                self.frames = 0;
+                self.ticks = 0;
+            }
+        }
+
+        /// Returns false if the window can't go on rendering
+        fn redraw(&mut self) -> bool {
+            if let Some(limiter) = &mut self.frame_limiter {
+                limiter.wait();
+            }
+            let context = &mut self.context;
+            // Cleared before rendering, so `render` can ask for the next frame
+            context.redraw_requested = false;
+            // Apply the latest resize only once per frame
+            if let Some(size) = self.pending_resize.take() {
+                context.graphics.resize(size);
+            }
+
+            #[cfg(feature = "hot-reload")]
+            context.graphics.reload_changed_assets();
+
+            context
+                .ui
+                .begin_frame(context.mouse, context.graphics.gamma_correction());
+            self.state.render(context, self.alpha);
+            context.graphics.screen_batch.append(&mut context.ui.batch);
+            self.state.pre_render(context);
+
+            let frame_time = self.last_frame.elapsed();
+            self.last_frame = Instant::now();
+            context.graphics.draw_floating_text(frame_time);
+
+            // Write fps
+            context.graphics.draw_text(
+                &format!("FPS: {}", self.average_frames),
+                0.0,
+                0.0,
+                graphics::color::Color::GREEN,
+                20.0,
+            );
+            context.graphics.draw_text(
+                &format!("Ticks/s: {}", self.average_ticks),
+                140.0,
+                0.0,
+                graphics::color::Color::GREEN,
+                20.0,
+            );
+            if let Some(gpu_frame_time) = context.gpu_frame_time() {
+                context.graphics.draw_text(
+                    &format!("GPU: {:.2}ms", gpu_frame_time),
+                    320.0,
+                    0.0,
+                    graphics::color::Color::GREEN,
+                    20.0,
+                );
+            }
+
+            context.graphics.camera.update_shake(context.time.delta());
+            context.graphics.update();
+            match context.graphics.render() {
+                Ok(_) => {}
+                // Recreate the swap_chain if lost
+                Err(wgpu::SurfaceError::Lost) => context.graphics.resize(*context.graphics.size()),
+                // The system is out of memory, we should probably quit
+                Err(wgpu::SurfaceError::OutOfMemory) => return false,
+                // All other errors (Outdated, Timeout) should be resolved by the next frame
+                Err(e) => eprintln!("Err: {:?}", e),
+            };
+            context.mouse.end_frame();
+            true
+        }
+    }
+
     #[inline]
     fn exit(control_flow: &mut ControlFlow) {
         *control_flow = ControlFlow::Exit;
     }
+
+    /// Whether to render this time around the loop. Always, unless only rendering on demand
+    fn needs_redraw(redraw_on_demand: bool, redraw_requested: bool) -> bool {
+        !redraw_on_demand || redraw_requested
+    }
+
+    /// Sets the exit flag only if [`Runnable::on_close_requested`] let the window close
+    fn close_if_allowed(allowed: bool, exit_requested: &mut bool) {
+        if allowed {
+            *exit_requested = true;
+        }
+    }
+
+    /// Fraction of a tick the leftover `lag` makes up, passed to [`Runnable::render`]
+    fn render_alpha(lag: u128, nanos_per_tick: u128) -> f32 {
+        (lag as f64 / nanos_per_tick as f64).clamp(0.0, 1.0) as f32
+    }
+
+    /// How many fixed updates of `step` nanoseconds `lag` calls for, at most `max_steps`, and the lag left after
+    /// them. Time past the cap is dropped, only the part of a step that's left over carries on
+    fn fixed_steps(lag: u128, step: u128, max_steps: u32) -> (u32, u128) {
+        let step = step.max(1);
+        let due = lag / step;
+        if due > max_steps as u128 {
+            (max_steps, lag % step)
+        } else {
+            (due as u32, lag - due * step)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::{close_if_allowed, fixed_steps, needs_redraw, render_alpha};
+
+        #[test]
+        fn long_frames_only_run_up_to_the_cap() {
+            // 60 updates a second
+            let step = 16_666_667;
+            // A frame and a half runs once, and carries the half over
+            assert_eq!(fixed_steps(step * 3 / 2, step, 5), (1, step / 2));
+            assert_eq!(fixed_steps(step / 2, step, 5), (0, step / 2));
+
+            // A whole second would be 60 updates, but only 5 run and the rest is dropped
+            let (steps, lag) = fixed_steps(1_000_000_000, step, 5);
+            assert_eq!(steps, 5);
+            assert!(lag < step);
+        }
+
+        #[test]
+        fn on_demand_only_redraws_when_asked() {
+            assert!(!needs_redraw(true, false));
+            assert!(needs_redraw(true, true));
+            // Continuous rendering ignores requests
+            assert!(needs_redraw(false, false));
+            assert!(needs_redraw(false, true));
+        }
+
+        #[test]
+        fn refusing_to_close_keeps_the_window_open() {
+            let mut exit_requested = false;
+            close_if_allowed(false, &mut exit_requested);
+            assert!(!exit_requested);
+            close_if_allowed(true, &mut exit_requested);
+            assert!(exit_requested);
+        }
+
+        #[test]
+        fn alpha_is_leftover_fraction_of_a_tick() {
+            // 60 ticks per second
+            let nanos_per_tick = 16_666_667;
+            assert_eq!(render_alpha(0, nanos_per_tick), 0.0);
+            assert!((render_alpha(nanos_per_tick / 4, nanos_per_tick) - 0.25).abs() < 1e-6);
+            assert_eq!(render_alpha(nanos_per_tick, nanos_per_tick), 1.0);
+            // Lag never builds past a tick in the loop, but if it did alpha still stays in range
+            assert_eq!(render_alpha(nanos_per_tick * 3, nanos_per_tick), 1.0);
+        }
+    }
 }
@@ -0,0 +1,87 @@
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::monitor::MonitorHandle;
+
+/// A display the window can go on, from [`crate::context::Context::available_monitors`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    /// `None` if the platform doesn't name its monitors
+    pub name: Option<String>,
+    /// Resolution in physical pixels
+    pub size: (u32, u32),
+    /// Highest refresh rate it can do at its current resolution, in hz. `None` if it doesn't list any video modes
+    pub refresh_rate: Option<u16>,
+    /// Top left corner on the desktop, in physical pixels. The primary monitor's usually at `(0, 0)`
+    pub position: (i32, i32),
+    pub scale_factor: f64,
+}
+
+impl MonitorInfo {
+    pub(crate) fn from_handle(monitor: &MonitorHandle) -> Self {
+        let size = monitor.size();
+        let position = monitor.position();
+        let refresh_rate = monitor
+            .video_modes()
+            .filter(|mode| mode.size() == size)
+            .map(|mode| mode.refresh_rate())
+            .max();
+        Self {
+            name: monitor.name(),
+            size: (size.width, size.height),
+            refresh_rate,
+            position: (position.x, position.y),
+            scale_factor: monitor.scale_factor(),
+        }
+    }
+}
+
+/// Where a window with `margin` pixels on every side goes on a monitor at `position` that's `size` big
+pub(crate) fn placement(
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+    margin: f32,
+) -> (PhysicalPosition<i32>, PhysicalSize<u32>) {
+    let margin = margin as u32;
+    (
+        PhysicalPosition::new(position.x + margin as i32, position.y + margin as i32),
+        PhysicalSize::new(
+            size.width.saturating_sub(margin * 2).max(1),
+            size.height.saturating_sub(margin * 2).max(1),
+        ),
+    )
+}
+
+/// Moves `window` onto `monitor`, filling it all but `margin` pixels on every side
+pub(crate) fn fit_to(window: &winit::window::Window, monitor: &MonitorHandle, margin: f32) {
+    let (position, size) = placement(monitor.position(), monitor.size(), margin);
+    window.set_inner_size(size);
+    window.set_outer_position(position);
+}
+
+#[cfg(test)]
+mod test {
+    use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+    use super::placement;
+
+    #[test]
+    fn window_is_placed_on_its_own_monitor() {
+        // Second monitor to the right of a 1920 wide one
+        let (position, size) = placement(
+            PhysicalPosition::new(1920, 0),
+            PhysicalSize::new(2560, 1440),
+            100.0,
+        );
+        assert_eq!(position, PhysicalPosition::new(2020, 100));
+        assert_eq!(size, PhysicalSize::new(2360, 1240));
+    }
+
+    #[test]
+    fn margin_bigger_than_the_monitor_leaves_a_tiny_window() {
+        let (_, size) = placement(
+            PhysicalPosition::new(0, 0),
+            PhysicalSize::new(640, 480),
+            400.0,
+        );
+        assert_eq!(size, PhysicalSize::new(1, 1));
+    }
+}
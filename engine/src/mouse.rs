@@ -0,0 +1,252 @@
+use std::time::{Duration, Instant};
+
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+
+/// How many pixels a touchpad has to scroll to count as one line of a mouse wheel
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// Two presses of a button this close together are a double click, unless changed with
+/// [`crate::ContextBuilder::with_double_click_interval`]
+pub const DEFAULT_DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Where the cursor is, in physical pixels from the top left of the window, and which buttons are held,
+/// see [`Mouse::is_button_down`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mouse {
+    pub x: f32,
+    pub y: f32,
+    pub(crate) double_click_interval: Duration,
+    /// Left, right, middle
+    pub(crate) clicks: [Clicks; 3],
+    /// Scrolled this frame, in lines
+    pub(crate) wheel: f32,
+}
+
+/// When a button was pressed, to tell double clicks and long presses apart
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct Clicks {
+    /// When it was pressed, if it's held
+    held_since: Option<Instant>,
+    /// Last press that could still be the first half of a double click
+    last_press: Option<Instant>,
+    double_click: bool,
+    /// Went down this frame
+    pressed: bool,
+}
+
+impl Mouse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Same as [`Mouse::new`], but presses up to `interval` apart count as a double click
+    pub fn with_double_click_interval(interval: Duration) -> Self {
+        Self {
+            double_click_interval: interval,
+            ..Self::default()
+        }
+    }
+
+    /// Returns whether the event was a mouse event
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.x = position.x as f32;
+                self.y = position.y as f32;
+                true
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                if let MouseButton::Other(_) = button {
+                    return false;
+                }
+                let is_pressed = *state == ElementState::Pressed;
+                self.set_button(*button, is_pressed, Instant::now());
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.wheel += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether `button` is held down
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.clicks(button)
+            .is_some_and(|clicks| clicks.held_since.is_some())
+    }
+
+    /// Whether `button` went down this frame. Every tick in the frame sees it, and it's cleared once the frame's drawn
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.clicks(button).is_some_and(|clicks| clicks.pressed)
+    }
+
+    /// Lines scrolled this frame, positive is away from the player. Touchpads scrolling by pixels are converted
+    /// to lines, so both feel about the same. Like [`Mouse::is_button_pressed`], it's reset once the frame's drawn
+    pub fn wheel_delta(&self) -> f32 {
+        self.wheel
+    }
+
+    /// Called after every frame's drawn, so presses and scrolling are only seen for the frame they happened in
+    pub(crate) fn end_frame(&mut self) {
+        self.wheel = 0.0;
+        self.clicks
+            .iter_mut()
+            .for_each(|clicks| clicks.pressed = false);
+    }
+
+    /// Whether `button` is held down as the second click of a double click, i.e. it was pressed within the
+    /// double click interval of the press before it. Like the buttons themselves it stays true while the button's
+    /// held, so check it against the last tick's value to do something once per double click. A third quick
+    /// press starts over instead of being another double click
+    pub fn is_double_click(&self, button: MouseButton) -> bool {
+        self.clicks(button)
+            .is_some_and(|clicks| clicks.held_since.is_some() && clicks.double_click)
+    }
+
+    /// Seconds `button` has been held down for, `0.0` if it isn't. For long presses, e.g. holding to charge a throw
+    pub fn held_duration(&self, button: MouseButton) -> f32 {
+        self.held_duration_at(button, Instant::now())
+    }
+
+    fn held_duration_at(&self, button: MouseButton, now: Instant) -> f32 {
+        self.clicks(button)
+            .and_then(|clicks| clicks.held_since)
+            .map_or(0.0, |since| {
+                now.saturating_duration_since(since).as_secs_f32()
+            })
+    }
+
+    pub fn double_click_interval(&self) -> Duration {
+        self.double_click_interval
+    }
+
+    pub fn set_double_click_interval(&mut self, interval: Duration) {
+        self.double_click_interval = interval;
+    }
+
+    pub(crate) fn set_button(&mut self, button: MouseButton, pressed: bool, now: Instant) {
+        let interval = self.double_click_interval;
+        let clicks = match Self::index(button) {
+            Some(index) => &mut self.clicks[index],
+            None => return,
+        };
+        if !pressed {
+            clicks.held_since = None;
+            return;
+        }
+        // Pressed again without a release in between, e.g. the release happened outside the window
+        if clicks.held_since.is_some() {
+            return;
+        }
+        clicks.held_since = Some(now);
+        clicks.pressed = true;
+        clicks.double_click = clicks
+            .last_press
+            .is_some_and(|last| now.saturating_duration_since(last) <= interval);
+        clicks.last_press = if clicks.double_click { None } else { Some(now) };
+    }
+
+    fn clicks(&self, button: MouseButton) -> Option<&Clicks> {
+        Self::index(button).map(|index| &self.clicks[index])
+    }
+
+    fn index(button: MouseButton) -> Option<usize> {
+        match button {
+            MouseButton::Left => Some(0),
+            MouseButton::Right => Some(1),
+            MouseButton::Middle => Some(2),
+            MouseButton::Other(_) => None,
+        }
+    }
+}
+
+impl Default for Mouse {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            double_click_interval: DEFAULT_DOUBLE_CLICK_INTERVAL,
+            clicks: [Clicks::default(); 3],
+            wheel: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use winit::event::MouseButton;
+
+    use super::Mouse;
+
+    fn click(mouse: &mut Mouse, at: Instant) {
+        mouse.set_button(MouseButton::Left, true, at);
+        mouse.set_button(MouseButton::Left, false, at + Duration::from_millis(50));
+    }
+
+    #[test]
+    fn quick_clicks_are_a_double_click_and_slow_ones_are_not() {
+        let start = Instant::now();
+        let mut mouse = Mouse::new();
+        click(&mut mouse, start);
+        mouse.set_button(MouseButton::Left, true, start + Duration::from_millis(200));
+        assert!(mouse.is_double_click(MouseButton::Left));
+        assert!(!mouse.is_double_click(MouseButton::Right));
+        // Only while it's held
+        mouse.set_button(MouseButton::Left, false, start + Duration::from_millis(250));
+        assert!(!mouse.is_double_click(MouseButton::Left));
+
+        let mut slow = Mouse::new();
+        click(&mut slow, start);
+        slow.set_button(MouseButton::Left, true, start + Duration::from_millis(600));
+        assert!(!slow.is_double_click(MouseButton::Left));
+    }
+
+    #[test]
+    fn third_quick_click_starts_over() {
+        let start = Instant::now();
+        let mut mouse = Mouse::with_double_click_interval(Duration::from_millis(500));
+        click(&mut mouse, start);
+        click(&mut mouse, start + Duration::from_millis(400));
+        mouse.set_button(MouseButton::Left, true, start + Duration::from_millis(800));
+        assert!(!mouse.is_double_click(MouseButton::Left));
+    }
+
+    #[test]
+    fn presses_and_scrolling_last_one_frame() {
+        let start = Instant::now();
+        let mut mouse = Mouse::new();
+        mouse.set_button(MouseButton::Right, true, start);
+        mouse.wheel += 2.0;
+        assert!(mouse.is_button_pressed(MouseButton::Right));
+        assert!(mouse.is_button_down(MouseButton::Right));
+        assert!(!mouse.is_button_pressed(MouseButton::Left));
+        assert_eq!(mouse.wheel_delta(), 2.0);
+
+        mouse.end_frame();
+        assert!(!mouse.is_button_pressed(MouseButton::Right));
+        assert!(mouse.is_button_down(MouseButton::Right));
+        assert_eq!(mouse.wheel_delta(), 0.0);
+    }
+
+    #[test]
+    fn held_duration_counts_from_the_press() {
+        let start = Instant::now();
+        let mut mouse = Mouse::new();
+        assert_eq!(mouse.held_duration_at(MouseButton::Right, start), 0.0);
+        mouse.set_button(MouseButton::Right, true, start);
+        let held = mouse.held_duration_at(MouseButton::Right, start + Duration::from_millis(1500));
+        assert!((held - 1.5).abs() < 1e-4);
+        mouse.set_button(MouseButton::Right, false, start + Duration::from_secs(2));
+        assert_eq!(
+            mouse.held_duration_at(MouseButton::Right, start + Duration::from_secs(3)),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::Result;
+
+/// Saves small bits of game state (high scores, settings) as json files, one per key.
+///
+/// By default they go in the platform's config directory for the game, named after the window title:
+/// `~/.config/<title>` on Linux, `%APPDATA%\<title>\config` on Windows,
+/// and `~/Library/Application Support/<title>` on macOS. Needs the `serde` feature
+pub struct Storage {
+    dir: PathBuf,
+}
+
+impl Storage {
+    /// Keeps saves in `dir`, which is created on the first save
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Keeps saves in the config directory for `game`, or the working directory if there's no home directory
+    pub fn for_game(game: &str) -> Self {
+        match directories::ProjectDirs::from("", "", game) {
+            Some(dirs) => Self::new(dirs.config_dir()),
+            None => Self::new("."),
+        }
+    }
+
+    /// Where the saves go
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes `value` to `<key>.json`, replacing whatever was saved under `key` before.
+    /// `key` ends up as a file name, so stick to letters, numbers, `-` and `_`
+    pub fn save<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_string_pretty(value)?;
+        fs::write(self.path(key), json)?;
+        Ok(())
+    }
+
+    /// What was saved under `key`. `None` if nothing was, or if it doesn't parse as a `T`
+    /// (e.g. the save is from an older version of the game)
+    pub fn load<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let json = fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::Storage;
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = std::env::temp_dir().join(format!("engine-storage-test-{}", std::process::id()));
+        let storage = Storage::new(&dir);
+
+        let mut scores = HashMap::new();
+        scores.insert(String::from("ash"), 120u32);
+        scores.insert(String::from("gary"), 90);
+        storage.save("scores", &scores).unwrap();
+
+        assert_eq!(storage.load::<HashMap<String, u32>>("scores"), Some(scores));
+        assert_eq!(storage.load::<u32>("missing"), None);
+        // Saved data of the wrong shape doesn't load
+        assert_eq!(storage.load::<u32>("scores"), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
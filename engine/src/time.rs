@@ -0,0 +1,151 @@
+use std::time::{Duration, Instant};
+
+/// How much time passed between frames, from `ctx.time`
+pub struct Time {
+    delta: Duration,
+    max_delta: Duration,
+    elapsed: Duration,
+}
+
+impl Time {
+    /// Never reports a delta longer than `max_delta` seconds.
+    ///
+    /// Panics if `max_delta` is negative, NaN or infinite. Zero is allowed, but stops time altogether
+    pub fn new(max_delta: f32) -> Self {
+        Self {
+            delta: Duration::ZERO,
+            max_delta: max_delta_duration(max_delta),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Seconds since the last frame, at most [`Time::max_delta`]
+    pub fn delta(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Seconds since the first frame, adding up every frame's delta, so stalls are clamped out of it the same way.
+    /// For anything animated by time instead of ticks, like [`crate::context::Context::draw_marching_ants`]
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// Longest a frame can be reported to take, in seconds. If the program was stalled (suspended,
+    /// stopped in a debugger, dragged around on some platforms) the next frame would otherwise be seconds long,
+    /// and anything moved by the delta would jump across the screen.
+    ///
+    /// Ticks are caught up from the same clamped time, so after a stall the game skips ahead at most this much
+    /// instead of running a burst of ticks
+    pub fn max_delta(&self) -> f32 {
+        self.max_delta.as_secs_f32()
+    }
+
+    /// Panics if `max_delta` is negative, NaN or infinite, like [`Time::new`]
+    pub fn set_max_delta(&mut self, max_delta: f32) {
+        self.max_delta = max_delta_duration(max_delta);
+    }
+
+    /// Starts a new frame, `elapsed` after the last one. Returns the clamped delta
+    pub(crate) fn advance(&mut self, elapsed: Duration) -> Duration {
+        self.delta = elapsed.min(self.max_delta);
+        self.elapsed += self.delta;
+        self.delta
+    }
+}
+
+/// `Duration::from_secs_f32` panics on these too, but with a message that doesn't say which setting was wrong
+pub(crate) fn max_delta_duration(max_delta: f32) -> Duration {
+    assert!(
+        max_delta.is_finite() && max_delta >= 0.0,
+        "max_delta must be a finite, non-negative number of seconds, got {}",
+        max_delta
+    );
+    Duration::from_secs_f32(max_delta)
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new(0.1)
+    }
+}
+
+/// Holds frames to at most some number a second, see [`crate::ContextBuilder::with_uncapped_fps`]
+pub(crate) struct FrameLimiter {
+    min_frame: Duration,
+    last: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new(max_fps: u32) -> Self {
+        Self {
+            min_frame: Duration::from_secs_f64(1.0 / max_fps.max(1) as f64),
+            last: Instant::now(),
+        }
+    }
+
+    /// Blocks until a whole frame's time has passed since the last call. Sleeps while there's plenty left, since
+    /// sleeping can overshoot by a millisecond or so, then spins for the rest to land on time
+    pub fn wait(&mut self) {
+        let target = self.last + self.min_frame;
+        loop {
+            let now = Instant::now();
+            if now >= target {
+                break;
+            }
+            let left = target - now;
+            if left > Duration::from_millis(2) {
+                std::thread::sleep(left - Duration::from_millis(1));
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+        self.last = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{FrameLimiter, Time};
+
+    #[test]
+    fn stall_is_clamped_to_max_delta() {
+        let mut time = Time::new(0.1);
+        assert_eq!(
+            time.advance(Duration::from_millis(16)),
+            Duration::from_millis(16)
+        );
+        assert!((time.delta() - 0.016).abs() < 1e-6);
+
+        // Five seconds stuck on a breakpoint
+        time.advance(Duration::from_secs(5));
+        assert!((time.delta() - 0.1).abs() < 1e-6);
+        // Elapsed only counts what got through
+        assert!((time.elapsed() - 0.116).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "max_delta must be a finite, non-negative number of seconds")]
+    fn negative_max_delta_is_rejected() {
+        Time::new(-0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "max_delta must be a finite, non-negative number of seconds")]
+    fn nan_max_delta_is_rejected() {
+        Time::default().set_max_delta(f32::NAN);
+    }
+
+    #[test]
+    fn soft_cap_limits_frame_rate() {
+        let mut limiter = FrameLimiter::new(200);
+        let start = Instant::now();
+        // Frames that take no time at all, like an empty menu with vsync off
+        for _ in 0..20 {
+            limiter.wait();
+        }
+        let fps = 20.0 / start.elapsed().as_secs_f64();
+        assert!(fps <= 205.0, "{} fps", fps);
+    }
+}
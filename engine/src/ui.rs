@@ -0,0 +1,193 @@
+use winit::event::MouseButton;
+
+use crate::graphics::batch::{Batch, DrawStyle};
+use crate::graphics::color::Color;
+use crate::graphics::rect::Rect;
+use crate::mouse::Mouse;
+
+const BACKGROUND: Color = Color {
+    r: 40,
+    g: 40,
+    b: 48,
+    a: 255,
+};
+const BORDER: Color = Color {
+    r: 200,
+    g: 200,
+    b: 210,
+    a: 255,
+};
+const FILL: Color = Color {
+    r: 90,
+    g: 160,
+    b: 240,
+    a: 255,
+};
+
+/// Immediate mode widgets: call them every frame in `Runnable::render`, they draw themselves and change the value
+/// passed in when clicked or dragged. Rects are in screen pixels, and widgets are drawn on top of everything else
+#[derive(Default)]
+pub struct Ui {
+    /// Shapes drawn this frame, moved into the screen batch before rendering
+    pub(crate) batch: Batch,
+    mouse: Mouse,
+    was_down: bool,
+    /// Slider being dragged. It keeps following the mouse even off the slider, until the button is let go
+    dragging: Option<Rect>,
+}
+
+impl Ui {
+    /// Called before every render, with the mouse as it is now
+    pub(crate) fn begin_frame(&mut self, mouse: Mouse, gamma_correction: bool) {
+        self.was_down = self.mouse.is_button_down(MouseButton::Left);
+        self.mouse = mouse;
+        if !mouse.is_button_down(MouseButton::Left) {
+            self.dragging = None;
+        }
+        self.batch.gamma_correction = gamma_correction;
+    }
+
+    /// Whether the left button went down this frame over `rect`
+    fn pressed(&self, rect: &Rect) -> bool {
+        self.mouse.is_button_down(MouseButton::Left)
+            && !self.was_down
+            && rect.contains(self.mouse.x, self.mouse.y)
+    }
+
+    /// Box that flips `value` when clicked. Returns whether it was flipped this frame
+    pub fn checkbox(&mut self, rect: Rect, value: &mut bool) -> bool {
+        let clicked = self.pressed(&rect);
+        if clicked {
+            *value = !*value;
+        }
+
+        self.batch
+            .draw_rectangle(rect.x, rect.y, rect.width, rect.height, BACKGROUND);
+        if *value {
+            let inset = rect.width.min(rect.height) / 5.0;
+            self.batch.draw_rectangle(
+                rect.x + inset,
+                rect.y + inset,
+                rect.width - inset * 2.0,
+                rect.height - inset * 2.0,
+                FILL,
+            );
+        }
+        self.outline(rect);
+
+        clicked
+    }
+
+    /// Horizontal slider, setting `value` to somewhere between `min` and `max` depending on where along `rect`
+    /// it's dragged. Returns whether the value changed this frame
+    pub fn slider(&mut self, rect: Rect, value: &mut f32, min: f32, max: f32) -> bool {
+        if self.pressed(&rect) {
+            self.dragging = Some(rect);
+        }
+
+        let mut changed = false;
+        if self.dragging == Some(rect) {
+            let t = ((self.mouse.x - rect.x) / rect.width).clamp(0.0, 1.0);
+            let new = min + (max - min) * t;
+            changed = new != *value;
+            *value = new;
+        }
+
+        let t = if max > min {
+            ((*value - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.batch
+            .draw_rectangle(rect.x, rect.y, rect.width, rect.height, BACKGROUND);
+        self.batch
+            .draw_rectangle(rect.x, rect.y, rect.width * t, rect.height, FILL);
+        self.outline(rect);
+
+        changed
+    }
+
+    fn outline(&mut self, rect: Rect) {
+        let style = std::mem::replace(&mut self.batch.style, DrawStyle::Line { thickness: 1.0 });
+        self.batch
+            .draw_rectangle(rect.x, rect.y, rect.width, rect.height, BORDER);
+        self.batch.style = style;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use winit::event::MouseButton;
+
+    use super::Ui;
+    use crate::graphics::rect::Rect;
+    use crate::mouse::Mouse;
+
+    fn frame(ui: &mut Ui, x: f32, y: f32, left: bool) {
+        let mut mouse = Mouse {
+            x,
+            y,
+            ..Mouse::default()
+        };
+        mouse.set_button(MouseButton::Left, left, Instant::now());
+        ui.begin_frame(mouse, true);
+        ui.batch.clear();
+    }
+
+    #[test]
+    fn dragging_slider_sets_value_proportionally() {
+        let rect = Rect::new(100.0, 100.0, 200.0, 20.0);
+        let mut value = 0.0;
+        let mut ui = Ui::default();
+
+        frame(&mut ui, 150.0, 110.0, true);
+        assert!(ui.slider(rect, &mut value, 0.0, 10.0));
+        assert_eq!(value, 2.5);
+
+        frame(&mut ui, 250.0, 110.0, true);
+        ui.slider(rect, &mut value, 0.0, 10.0);
+        assert_eq!(value, 7.5);
+
+        // Still dragging while the mouse is off the slider, but clamped to the end
+        frame(&mut ui, 900.0, 400.0, true);
+        ui.slider(rect, &mut value, 0.0, 10.0);
+        assert_eq!(value, 10.0);
+
+        frame(&mut ui, 150.0, 110.0, false);
+        assert!(!ui.slider(rect, &mut value, 0.0, 10.0));
+        assert_eq!(value, 10.0);
+    }
+
+    #[test]
+    fn press_held_onto_slider_does_not_grab_it() {
+        let rect = Rect::new(0.0, 0.0, 100.0, 10.0);
+        let mut value = 0.5;
+        let mut ui = Ui::default();
+
+        frame(&mut ui, 500.0, 5.0, true);
+        frame(&mut ui, 50.0, 5.0, true);
+        assert!(!ui.slider(rect, &mut value, 0.0, 1.0));
+        assert_eq!(value, 0.5);
+    }
+
+    #[test]
+    fn checkbox_flips_once_per_click() {
+        let rect = Rect::new(10.0, 10.0, 20.0, 20.0);
+        let mut checked = false;
+        let mut ui = Ui::default();
+
+        frame(&mut ui, 20.0, 20.0, true);
+        assert!(ui.checkbox(rect, &mut checked));
+        frame(&mut ui, 20.0, 20.0, true);
+        assert!(!ui.checkbox(rect, &mut checked));
+        assert!(checked);
+
+        frame(&mut ui, 20.0, 20.0, false);
+        ui.checkbox(rect, &mut checked);
+        frame(&mut ui, 20.0, 20.0, true);
+        ui.checkbox(rect, &mut checked);
+        assert!(!checked);
+    }
+}
@@ -1,18 +1,21 @@
 use engine::graphics::color::Color;
+use engine::graphics::texture::TextureId;
 use engine::{context::Context, Runnable};
 
 fn main() {
     println!("Hello, world!");
 
+    let (event_loop, mut ctx) = engine::ContextBuilder::new().with_title("Booboo").build();
+
+    let texture1 = std::fs::read("game/res/floor.png").unwrap();
+    let floor = ctx.graphics.load_texture(&texture1).unwrap();
+
     let game = Game {
         playerx: 0.0,
         playery: 0.0,
+        floor,
     };
 
-    let (event_loop, ctx) = engine::ContextBuilder::new().with_title("Booboo").build();
-
-    let texture1 = std::fs::read("game/res/floor.png").unwrap();
-
 
 
 
@@ -22,22 +25,25 @@ fn main() {
 struct Game {
     playerx: f32,
     playery: f32,
+    floor: TextureId,
 }
 
 impl Runnable for Game {
-    fn tick(&mut self, _ctx: &mut Context) {
-        self.playerx += 1.0;
-        self.playery += 1.0;
+    fn tick(&mut self, ctx: &mut Context) {
+        // Pixels a second
+        let speed = 140.0;
+        let dt = ctx.delta_time();
+        self.playerx += speed * dt;
+        self.playery += speed * dt;
+        // Trail the middle of the player
+        ctx.graphics
+            .camera
+            .follow((self.playerx + 50.0, self.playery + 50.0), 4.0, dt);
     }
 
-    fn render(&self, ctx: &mut Context) {
+    fn render(&self, ctx: &mut Context, _alpha: f32) {
+        ctx.graphics.draw_texture(self.floor, 0.0, 0.0, Color::WHITE);
         ctx.graphics
-            .clear_background(Color::from_hex("#000000").unwrap());
-        ctx.graphics.draw_square(
-            self.playerx,
-            self.playery,
-            100.0,
-            Color::from_hex("#FFFFFF").unwrap(),
-        );
+            .draw_square(self.playerx, self.playery, 100.0, Color::WHITE);
     }
 }